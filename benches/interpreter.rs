@@ -0,0 +1,25 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use cpu_emu::{
+    harness::benchmark::{run_workload, Workload},
+    shared::logger::logger,
+};
+
+const INSTRUCTIONS: u64 = 100_000;
+
+// throughput is reported per emulated instruction, compare runs with --save-baseline/--baseline
+fn workloads(c: &mut Criterion) {
+    logger().set_enabled(false);
+
+    let mut group = c.benchmark_group("interpreter");
+    group.throughput(Throughput::Elements(INSTRUCTIONS));
+    for workload in Workload::ALL {
+        group.bench_function(workload.name(), |b| {
+            b.iter(|| run_workload(workload, INSTRUCTIONS).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, workloads);
+criterion_main!(benches);
@@ -0,0 +1,62 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use cpu_emu::{
+    cpu::cpu::CPU,
+    shared::logger::{logger, ring_buffer_sink::RingBufferSink, LogLevel, LoggingHw},
+};
+
+// debug call on the instruction loop with the default info level
+fn disabled_debug(c: &mut Criterion) {
+    let cpu = CPU::new();
+    logger().clear_filter_rules();
+    logger().set_global_level(LogLevel::Info);
+
+    c.bench_function("log_debug disabled", |b| {
+        b.iter(|| cpu.log_debug(black_box("step"), black_box("fetch")))
+    });
+    c.bench_function("log_fmt disabled", |b| {
+        b.iter(|| {
+            cpu.log_fmt(
+                LogLevel::Debug,
+                black_box("step"),
+                format_args!("opcode {:#04X}", black_box(0xA9)),
+            )
+        })
+    });
+}
+
+// a rule lets MEM debug through, so CPU calls have to consult the rules
+fn filtered_with_rules(c: &mut Criterion) {
+    let cpu = CPU::new();
+    logger().configure("info,MEM=debug").unwrap();
+
+    c.bench_function("log_debug filtered by rules", |b| {
+        b.iter(|| cpu.log_debug(black_box("step"), black_box("fetch")))
+    });
+
+    logger().clear_filter_rules();
+}
+
+// full record with register context delivered to an in-memory sink
+fn enabled_ring_buffer(c: &mut Criterion) {
+    let cpu = CPU::new();
+    logger().clear_sinks();
+    logger().add_sink(RingBufferSink::new(1024), LogLevel::Verbose);
+    logger().set_global_level(LogLevel::Debug);
+
+    c.bench_function("log_debug to ring buffer", |b| {
+        b.iter(|| cpu.log_debug(black_box("step"), black_box("fetch")))
+    });
+
+    logger().set_global_level(LogLevel::Info);
+}
+
+criterion_group!(
+    benches,
+    disabled_debug,
+    filtered_with_rules,
+    enabled_ring_buffer
+);
+criterion_main!(benches);
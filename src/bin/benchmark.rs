@@ -0,0 +1,131 @@
+use std::{env, process::ExitCode};
+
+use cpu_emu::{
+    harness::benchmark::{
+        results_to_json, run_best_of, Baseline, Workload, DEFAULT_BENCHMARK_INSTRUCTIONS,
+    },
+    shared::{error_chain::error_chain, logger::logger},
+};
+
+const USAGE: &str = "usage: benchmark [workload...] [--instructions n] [--runs n] [--json] \
+[--save file.json] [--baseline file.json] [--max-regression percent]
+build with --release, workloads: tight_loop memory_scan lda_indirect_y interrupt_heavy run_loop
+baselines are host specific, --save one on the machine that later runs with --baseline";
+
+const DEFAULT_RUNS: usize = 3;
+
+fn main() -> ExitCode {
+    let mut workloads = Vec::new();
+    let mut instructions = DEFAULT_BENCHMARK_INSTRUCTIONS;
+    let mut runs = DEFAULT_RUNS;
+    let mut use_json = false;
+    let mut save_path = None;
+    let mut baseline_path = None;
+    let mut max_regression = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let is_valid = match arg.as_str() {
+            "--json" => {
+                use_json = true;
+                true
+            }
+            "--instructions" | "--runs" | "--save" | "--baseline" | "--max-regression" => {
+                match args.next() {
+                    Some(value) => match arg.as_str() {
+                        "--instructions" => value.parse().map(|n| instructions = n).is_ok(),
+                        "--runs" => value.parse().map(|n| runs = n).is_ok() && runs > 0,
+                        "--save" => {
+                            save_path = Some(value);
+                            true
+                        }
+                        "--baseline" => {
+                            baseline_path = Some(value);
+                            true
+                        }
+                        _ => value
+                            .parse::<f64>()
+                            .map(|percent| max_regression = Some(percent))
+                            .is_ok(),
+                    },
+                    None => false,
+                }
+            }
+            name => match Workload::parse(name) {
+                Some(workload) => {
+                    workloads.push(workload);
+                    true
+                }
+                None => false,
+            },
+        };
+
+        if !is_valid {
+            eprintln!("invalid option {arg}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    }
+
+    if workloads.is_empty() {
+        workloads = Workload::ALL.to_vec();
+    }
+
+    let baseline = match baseline_path.as_deref().map(Baseline::load).transpose() {
+        Ok(baseline) => baseline,
+        Err(error) => {
+            eprintln!("failed to read baseline: {error}");
+            return ExitCode::from(2);
+        }
+    };
+
+    // logging must not skew the numbers
+    logger().set_enabled(false);
+
+    let mut results = Vec::new();
+    let mut is_regression = false;
+    for workload in workloads {
+        let result = match run_best_of(workload, instructions, runs) {
+            Ok(result) => result,
+            Err(error) => {
+                eprintln!("{workload} failed: {}", error_chain(&error));
+                return ExitCode::FAILURE;
+            }
+        };
+
+        let change = baseline
+            .as_ref()
+            .and_then(|baseline| baseline.change_percent(&result));
+        if let (Some(change), Some(max_regression)) = (change, max_regression) {
+            is_regression |= change < -max_regression;
+        }
+
+        if !use_json {
+            match change {
+                Some(change) => println!("{result}  {change:+.1}% vs baseline"),
+                None => println!("{result}"),
+            }
+        }
+        results.push(result);
+    }
+
+    if use_json {
+        println!("{}", results_to_json(&results));
+    }
+
+    if let Some(path) = save_path {
+        if let Err(error) = Baseline::save(&path, &results) {
+            eprintln!("failed to save {path}: {error}");
+            return ExitCode::from(2);
+        }
+    }
+
+    if is_regression {
+        eprintln!(
+            "throughput dropped more than {}%",
+            max_regression.unwrap_or_default()
+        );
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,68 @@
+use std::{env, process::ExitCode};
+
+use cpu_emu::{
+    harness::klaus_dormann::{KlausDormannConfig, KlausDormannRunner},
+    shared::{error_chain::error_chain, parsing::parse_word},
+};
+
+const USAGE: &str = "usage: klaus_dormann <functional|interrupt> <image.bin> \
+[--load $addr] [--start $addr] [--success $addr] [--max-cycles n]";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let (suite, path) = match args.as_slice() {
+        [suite, path, ..] => (suite.as_str(), path.as_str()),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut config = match suite {
+        "functional" => KlausDormannConfig::functional_test(),
+        "interrupt" => KlausDormannConfig::interrupt_test(),
+        _ => {
+            eprintln!("unknown suite {suite}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    for option in args[2..].chunks(2) {
+        let [name, value] = option else {
+            eprintln!("missing value for {}\n{USAGE}", option[0]);
+            return ExitCode::from(2);
+        };
+
+        let parsed = match name.as_str() {
+            "--max-cycles" => value.parse::<u64>().ok().map(|cycles| {
+                config.max_cycles = cycles;
+            }),
+            "--load" => parse_word(value).map(|address| config.load_address = address),
+            "--start" => parse_word(value).map(|address| config.start_address = address),
+            "--success" => parse_word(value).map(|address| config.success_address = address),
+            _ => None,
+        };
+
+        if parsed.is_none() {
+            eprintln!("invalid option {name} {value}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    }
+
+    let outcome = match KlausDormannRunner::new(config).run_file(path) {
+        Ok(outcome) => outcome,
+        Err(error) => {
+            eprintln!("{}", error_chain(&error));
+            return ExitCode::from(2);
+        }
+    };
+
+    println!("{suite} test: {outcome}");
+
+    if outcome.is_success() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
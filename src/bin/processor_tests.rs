@@ -0,0 +1,55 @@
+use std::{env, path::Path, process::ExitCode};
+
+use cpu_emu::harness::processor_tests::ProcessorTestRunner;
+
+const USAGE: &str = "usage: processor_tests <dir-or-file.json> [--bus-cycles] [--only-implemented]";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let Some(path) = args.first().map(Path::new) else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+
+    let compare_bus_cycles = args.iter().any(|arg| arg == "--bus-cycles");
+    let only_implemented = args.iter().any(|arg| arg == "--only-implemented");
+
+    let mut runner = ProcessorTestRunner::new(compare_bus_cycles);
+    let run_result = if path.is_dir() {
+        runner.run_directory(path)
+    } else {
+        runner.run_file(path).map(|report| vec![report])
+    };
+
+    let reports = match run_result {
+        Ok(reports) => reports,
+        Err(error) => {
+            eprintln!("failed to run {}: {error}", path.display());
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut file_count = 0;
+    let mut failed_opcodes = 0;
+    for report in &reports {
+        if only_implemented && report.unimplemented {
+            continue;
+        }
+
+        file_count += 1;
+        if !report.is_success() {
+            failed_opcodes += 1;
+        }
+
+        println!("{report}");
+    }
+
+    println!("{file_count} files, {failed_opcodes} with failures");
+
+    if failed_opcodes == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,115 @@
+use std::ops::RangeInclusive;
+
+use crate::{
+    memory::page_table::MEMORY_SIZE,
+    shared::types::{Byte, Word},
+};
+
+// how a byte was touched, an address collects every kind it saw
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct CoverageFlags(Byte);
+
+impl CoverageFlags {
+    pub const OPCODE: CoverageFlags = CoverageFlags(0x01);
+    pub const OPERAND: CoverageFlags = CoverageFlags(0x02);
+    pub const DATA_READ: CoverageFlags = CoverageFlags(0x04);
+    pub const DATA_WRITE: CoverageFlags = CoverageFlags(0x08);
+
+    pub fn contains(&self, other: CoverageFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn is_code(&self) -> bool {
+        self.0 & (Self::OPCODE.0 | Self::OPERAND.0) != 0
+    }
+
+    pub fn bits(&self) -> Byte {
+        self.0
+    }
+
+    // single character for maps: X opcode, o operand, r/w/b data read/write/both, . untouched,
+    // code wins over data so self-modifying code still shows as executed
+    pub fn symbol(&self) -> char {
+        if self.contains(Self::OPCODE) {
+            'X'
+        } else if self.contains(Self::OPERAND) {
+            'o'
+        } else {
+            match (
+                self.contains(Self::DATA_READ),
+                self.contains(Self::DATA_WRITE),
+            ) {
+                (true, true) => 'b',
+                (true, false) => 'r',
+                (false, true) => 'w',
+                (false, false) => '.',
+            }
+        }
+    }
+
+    fn insert(&mut self, other: CoverageFlags) {
+        self.0 |= other.0;
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct CoverageSummary {
+    pub bytes: usize,
+    pub opcodes: usize,
+    // opcode or operand
+    pub code: usize,
+    pub data_read: usize,
+    pub data_written: usize,
+    pub untouched: usize,
+}
+
+// per address record of how the CPU used memory, kept by Memory while enabled
+pub struct Coverage {
+    flags: Vec<CoverageFlags>,
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self {
+            flags: vec![CoverageFlags::default(); MEMORY_SIZE],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.flags.fill(CoverageFlags::default());
+    }
+
+    pub fn flags(&self, address: Word) -> CoverageFlags {
+        self.flags[usize::from(address)]
+    }
+
+    pub fn summary(&self, range: RangeInclusive<Word>) -> CoverageSummary {
+        let mut summary = CoverageSummary::default();
+
+        for address in range {
+            let flags = self.flags(address);
+            summary.bytes += 1;
+            summary.opcodes += usize::from(flags.contains(CoverageFlags::OPCODE));
+            summary.code += usize::from(flags.is_code());
+            summary.data_read += usize::from(flags.contains(CoverageFlags::DATA_READ));
+            summary.data_written += usize::from(flags.contains(CoverageFlags::DATA_WRITE));
+            summary.untouched += usize::from(flags.is_empty());
+        }
+
+        summary
+    }
+
+    pub(crate) fn mark(&mut self, address: Word, flags: CoverageFlags) {
+        self.flags[usize::from(address)].insert(flags);
+    }
+}
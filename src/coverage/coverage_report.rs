@@ -0,0 +1,202 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    ops::RangeInclusive,
+    path::Path,
+};
+
+use crate::{
+    disassembler::disassembler::disassemble, memory::memory::Memory, shared::types::Word,
+    symbols::symbol_table::SymbolTable,
+};
+
+use super::coverage::{Coverage, CoverageFlags};
+
+const MAP_ROW_LENGTH: usize = 64;
+
+impl Coverage {
+    // one character per address, 64 per row, see CoverageFlags::symbol
+    pub fn write_map<W: Write>(
+        &self,
+        output: &mut W,
+        range: RangeInclusive<Word>,
+    ) -> io::Result<()> {
+        let (start, end) = (*range.start(), *range.end());
+        writeln!(
+            output,
+            "; coverage ${start:04X}-${end:04X}: X opcode, o operand, r read, w write, b read+write, . untouched"
+        )?;
+
+        let addresses: Vec<Word> = range.collect();
+        for row in addresses.chunks(MAP_ROW_LENGTH) {
+            let symbols: String = row
+                .iter()
+                .map(|address| self.flags(*address).symbol())
+                .collect();
+            writeln!(output, "${:04X}  {symbols}", row[0])?;
+        }
+
+        Ok(())
+    }
+
+    // disassembly of executed code, touched data as bytes and runs of untouched
+    // bytes collapsed to one line, those are the dead code candidates
+    pub fn write_listing<W: Write>(
+        &self,
+        output: &mut W,
+        memory: &Memory,
+        symbols: &SymbolTable,
+        range: RangeInclusive<Word>,
+    ) -> io::Result<()> {
+        let (start, end) = (*range.start(), *range.end());
+        let summary = self.summary(range);
+        writeln!(
+            output,
+            "; coverage ${start:04X}-${end:04X}: {} of {} bytes executed as code ({:.1}%), \
+{} read, {} written, {} untouched",
+            summary.code,
+            summary.bytes,
+            summary.code as f64 * 100.0 / summary.bytes as f64,
+            summary.data_read,
+            summary.data_written,
+            summary.untouched
+        )?;
+
+        let mut address = u32::from(start);
+        while address <= u32::from(end) {
+            let current = address as Word;
+            let flags = self.flags(current);
+
+            if let Some(name) = symbols.name_for(current) {
+                writeln!(output, "{name}:")?;
+            }
+
+            if flags.is_empty() {
+                let run_end = (current..=end)
+                    .take_while(|next| {
+                        self.flags(*next).is_empty()
+                            && (*next == current || symbols.name_for(*next).is_none())
+                    })
+                    .last()
+                    .unwrap_or(current);
+                let length = u32::from(run_end) - address + 1;
+                if length == 1 {
+                    writeln!(output, "    ${current:04X}  never accessed")?;
+                } else {
+                    writeln!(
+                        output,
+                        "    ${current:04X}-${run_end:04X}  {length} bytes never accessed"
+                    )?;
+                }
+                address += length;
+            } else if flags.contains(CoverageFlags::OPCODE) {
+                let instruction = disassemble(memory, current);
+                writeln!(output, "  + {}", instruction.line_with_symbols(symbols))?;
+                address += u32::from(instruction.length());
+            } else {
+                writeln!(
+                    output,
+                    "    ${current:04X}  {:02X}        .byte ${:02X}  ; {}",
+                    memory.peek(current),
+                    memory.peek(current),
+                    flags.symbol()
+                )?;
+                address += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn save_map<P: AsRef<Path>>(&self, path: P, range: RangeInclusive<Word>) -> io::Result<()> {
+        let mut output = BufWriter::new(File::create(path)?);
+        self.write_map(&mut output, range)?;
+        output.flush()
+    }
+
+    pub fn save_listing<P: AsRef<Path>>(
+        &self,
+        path: P,
+        memory: &Memory,
+        symbols: &SymbolTable,
+        range: RangeInclusive<Word>,
+    ) -> io::Result<()> {
+        let mut output = BufWriter::new(File::create(path)?);
+        self.write_listing(&mut output, memory, symbols, range)?;
+        output.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // LDA $0300 at $0200 run once, $0203-$0204 untouched, $0205 a label
+    fn covered_program() -> (Coverage, Memory, SymbolTable) {
+        let mut memory = Memory::new();
+        memory.load(0x0200, &[0xAD, 0x00, 0x03, 0xEA, 0xEA, 0xEA]);
+        let mut coverage = Coverage::new();
+        coverage.mark(0x0200, CoverageFlags::OPCODE);
+        coverage.mark(0x0201, CoverageFlags::OPERAND);
+        coverage.mark(0x0202, CoverageFlags::OPERAND);
+        coverage.mark(0x0300, CoverageFlags::DATA_READ);
+        coverage.mark(0x0301, CoverageFlags::DATA_READ);
+        coverage.mark(0x0301, CoverageFlags::DATA_WRITE);
+        coverage.mark(0x0302, CoverageFlags::DATA_WRITE);
+        let mut symbols = SymbolTable::new();
+        symbols.insert("tail", 0x0205);
+
+        (coverage, memory, symbols)
+    }
+
+    fn written(write: impl FnOnce(&mut Vec<u8>) -> io::Result<()>) -> String {
+        let mut output = Vec::new();
+        write(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn map_has_one_symbol_per_address() {
+        let (coverage, _, _) = covered_program();
+
+        let map = written(|output| coverage.write_map(output, 0x0200..=0x0303));
+
+        let lines: Vec<&str> = map.lines().collect();
+        assert!(lines[0].starts_with("; coverage $0200-$0303"));
+        assert_eq!(lines[1], format!("$0200  Xoo{}", ".".repeat(61)));
+        assert_eq!(lines[5], "$0300  rbw.");
+        assert_eq!(lines.len(), 6);
+    }
+
+    #[test]
+    fn listing_collapses_untouched_runs_up_to_labels() {
+        let (coverage, memory, symbols) = covered_program();
+
+        let listing =
+            written(|output| coverage.write_listing(output, &memory, &symbols, 0x0200..=0x0205));
+
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(
+            lines[0],
+            "; coverage $0200-$0205: 3 of 6 bytes executed as code (50.0%), 0 read, 0 written, 3 untouched"
+        );
+        assert!(lines[1].starts_with("  + "));
+        assert!(lines[1].contains("$0300"));
+        assert_eq!(lines[2], "    $0203-$0204  2 bytes never accessed");
+        assert_eq!(lines[3], "tail:");
+        assert_eq!(lines[4], "    $0205  never accessed");
+        assert_eq!(lines.len(), 5);
+    }
+
+    #[test]
+    fn listing_shows_touched_data_as_bytes() {
+        let (coverage, memory, symbols) = covered_program();
+
+        let listing =
+            written(|output| coverage.write_listing(output, &memory, &symbols, 0x0300..=0x0301));
+
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(lines[1], "    $0300  00        .byte $00  ; r");
+        assert_eq!(lines[2], "    $0301  00        .byte $00  ; b");
+    }
+}
@@ -0,0 +1,2 @@
+pub mod coverage;
+pub mod coverage_report;
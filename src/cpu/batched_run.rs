@@ -0,0 +1,72 @@
+use super::{
+    breakpoints::stop_reason::StopReason, cpu::CPU, instruction_set::opcode_table::OPCODE_TABLE,
+};
+
+impl CPU {
+    // nothing needs a look at the CPU between instructions
+    pub(super) fn can_run_batched(&self) -> bool {
+        self.breakpoints.is_empty()
+            && self.replayer.is_none()
+            && self.rewind.is_none()
+            && self.profiler.is_none()
+            && !self.memory.is_observed()
+    }
+
+    // run() without debugger hooks, cycles are summed locally and stored when the batch ends,
+    // so log context inside the batch shows the count from its start
+    pub(super) fn run_batched(&mut self, cycle_limit: Option<u64>) -> StopReason {
+        let budget = cycle_limit.map_or(u64::MAX, |cycle_limit| {
+            cycle_limit.saturating_sub(self.cycles)
+        });
+        let mut batch_cycles: u64 = 0;
+
+        let stop_reason = loop {
+            if batch_cycles >= budget {
+                break StopReason::CycleLimit;
+            }
+
+            let instruction_address = self.program_counter;
+            let stack_ptr_before = self.stack_ptr;
+
+            if self.nmi_pending || self.irq_line {
+                if let Some(consumed_cycles) = self.service_interrupts() {
+                    self.track_interrupt_entry(instruction_address, stack_ptr_before);
+                    batch_cycles += u64::from(consumed_cycles);
+                    if let Some(error) = self.take_bus_error(instruction_address) {
+                        break StopReason::BusError(error);
+                    }
+                    continue;
+                }
+            }
+
+            let opcode = self.fetch_opcode();
+            let Some(handler) = OPCODE_TABLE[opcode as usize] else {
+                self.cycles += batch_cycles;
+                if let Some(error) = self.take_bus_error(instruction_address) {
+                    self.program_counter = instruction_address;
+                    return StopReason::BusError(error);
+                }
+                self.report_unimplemented_opcode(opcode, instruction_address);
+                return StopReason::UnimplementedOpcode {
+                    opcode,
+                    address: instruction_address,
+                };
+            };
+            batch_cycles += u64::from(handler(self));
+            self.track_call_stack(opcode, instruction_address, stack_ptr_before);
+
+            if let Some(error) = self.take_bus_error(instruction_address) {
+                break StopReason::BusError(error);
+            }
+
+            // jump or branch to itself
+            if self.program_counter == instruction_address {
+                break StopReason::Trapped(instruction_address);
+            }
+        };
+
+        self.cycles += batch_cycles;
+
+        stop_reason
+    }
+}
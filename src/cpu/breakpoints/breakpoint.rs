@@ -0,0 +1,103 @@
+use crate::{cpu::registers::Registers, memory::memory::Memory, shared::types::Word};
+
+use super::condition::Condition;
+
+// address only, condition only (checked before every instruction) or both
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub id: u32,
+    pub address: Option<Word>,
+    pub condition: Option<Condition>,
+    pub enabled: bool,
+}
+
+impl Breakpoint {
+    pub fn is_hit(&self, registers: &Registers, memory: &Memory) -> bool {
+        if !self.enabled {
+            return false;
+        }
+
+        if self
+            .address
+            .is_some_and(|address| address != registers.program_counter)
+        {
+            return false;
+        }
+
+        self.condition
+            .as_ref()
+            .is_none_or(|condition| condition.evaluate(registers, memory))
+    }
+}
+
+pub struct Breakpoints {
+    breakpoints: Vec<Breakpoint>,
+    next_id: u32,
+}
+
+impl Default for Breakpoints {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Breakpoints {
+    pub fn new() -> Self {
+        Self {
+            breakpoints: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    pub fn add(&mut self, address: Option<Word>, condition: Option<Condition>) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.breakpoints.push(Breakpoint {
+            id,
+            address,
+            condition,
+            enabled: true,
+        });
+
+        id
+    }
+
+    pub fn remove(&mut self, id: u32) -> bool {
+        let count_before = self.breakpoints.len();
+        self.breakpoints.retain(|breakpoint| breakpoint.id != id);
+
+        self.breakpoints.len() != count_before
+    }
+
+    pub fn set_enabled(&mut self, id: u32, enabled: bool) -> bool {
+        match self
+            .breakpoints
+            .iter_mut()
+            .find(|breakpoint| breakpoint.id == id)
+        {
+            Some(breakpoint) => {
+                breakpoint.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.breakpoints.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Breakpoint> {
+        self.breakpoints.iter()
+    }
+
+    pub fn find_hit(&self, registers: &Registers, memory: &Memory) -> Option<&Breakpoint> {
+        self.breakpoints
+            .iter()
+            .find(|breakpoint| breakpoint.is_hit(registers, memory))
+    }
+}
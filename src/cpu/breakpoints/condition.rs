@@ -0,0 +1,472 @@
+use std::fmt;
+
+use crate::{cpu::registers::Registers, memory::memory::Memory, shared::types::Word};
+
+// expression over registers and memory, e.g. "A == $10 && [$0200] & $80 != 0"
+// registers: A X Y SP PC P, [addr] reads a byte, numbers are $hex, 0xhex or decimal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    source: String,
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConditionParseError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl fmt::Display for ConditionParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Register {
+    A,
+    X,
+    Y,
+    SP,
+    PC,
+    P,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    BitOr,
+    BitAnd,
+    BitXor,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Literal(u32),
+    Register(Register),
+    MemoryByte(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(u32),
+    Ident(String),
+    Op(&'static str),
+    OpenBracket,
+    CloseBracket,
+    OpenParen,
+    CloseParen,
+}
+
+impl Condition {
+    pub fn parse(source: &str) -> Result<Self, ConditionParseError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+        };
+
+        let expr = parser.parse_or()?;
+        if let Some((position, token)) = parser.peek_with_position() {
+            return Err(ConditionParseError {
+                message: format!("unexpected {token:?}"),
+                position,
+            });
+        }
+
+        Ok(Self {
+            source: source.trim().to_string(),
+            expr,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    // non zero result means condition holds, memory is read through peek
+    pub fn evaluate(&self, registers: &Registers, memory: &Memory) -> bool {
+        self.value(registers, memory) != 0
+    }
+
+    // raw expression result, debuggers use it for watch expressions
+    pub fn value(&self, registers: &Registers, memory: &Memory) -> u32 {
+        evaluate(&self.expr, registers, memory)
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+fn evaluate(expr: &Expr, registers: &Registers, memory: &Memory) -> u32 {
+    match expr {
+        Expr::Literal(value) => *value,
+        Expr::Register(register) => match register {
+            Register::A => registers.acc.into(),
+            Register::X => registers.x_reg.into(),
+            Register::Y => registers.y_reg.into(),
+            Register::SP => registers.stack_ptr.into(),
+            Register::PC => registers.program_counter.into(),
+            Register::P => registers.status.into(),
+        },
+        Expr::MemoryByte(address) => {
+            let address = evaluate(address, registers, memory) as Word;
+            memory.peek(address).into()
+        }
+        Expr::Not(inner) => u32::from(evaluate(inner, registers, memory) == 0),
+        Expr::Binary(op, left, right) => {
+            let left = evaluate(left, registers, memory);
+
+            // short circuit, right side may read memory for nothing
+            match op {
+                BinaryOp::Or if left != 0 => return 1,
+                BinaryOp::And if left == 0 => return 0,
+                _ => {}
+            }
+
+            let right = evaluate(right, registers, memory);
+            match op {
+                BinaryOp::Or | BinaryOp::And => u32::from(right != 0),
+                BinaryOp::Eq => u32::from(left == right),
+                BinaryOp::Ne => u32::from(left != right),
+                BinaryOp::Lt => u32::from(left < right),
+                BinaryOp::Le => u32::from(left <= right),
+                BinaryOp::Gt => u32::from(left > right),
+                BinaryOp::Ge => u32::from(left >= right),
+                BinaryOp::BitOr => left | right,
+                BinaryOp::BitAnd => left & right,
+                BinaryOp::BitXor => left ^ right,
+            }
+        }
+    }
+}
+
+// longest operators first, so "<=" is not split into "<" and "="
+const OPERATORS: [&str; 12] = [
+    "||", "&&", "==", "!=", "<=", ">=", "<", ">", "|", "&", "^", "!",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ConditionParseError> {
+    let mut tokens = Vec::new();
+    let bytes = source.as_bytes();
+    let mut position = 0;
+
+    while position < bytes.len() {
+        let rest = &source[position..];
+        let current = bytes[position];
+
+        if current.is_ascii_whitespace() {
+            position += 1;
+            continue;
+        }
+
+        let single = match current {
+            b'[' => Some(Token::OpenBracket),
+            b']' => Some(Token::CloseBracket),
+            b'(' => Some(Token::OpenParen),
+            b')' => Some(Token::CloseParen),
+            _ => None,
+        };
+        if let Some(token) = single {
+            tokens.push((position, token));
+            position += 1;
+            continue;
+        }
+
+        if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push((position, Token::Op(op)));
+            position += op.len();
+            continue;
+        }
+
+        let word_length = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '$' || c == '_'))
+            .unwrap_or(rest.len());
+        if word_length == 0 {
+            return Err(ConditionParseError {
+                message: format!("unexpected character '{}'", current as char),
+                position,
+            });
+        }
+
+        let word = &rest[..word_length];
+        let token = if let Some(hex) = word.strip_prefix('$') {
+            Token::Number(parse_number(hex, 16, position)?)
+        } else if let Some(hex) = word.strip_prefix("0x") {
+            Token::Number(parse_number(hex, 16, position)?)
+        } else if word.as_bytes()[0].is_ascii_digit() {
+            Token::Number(parse_number(word, 10, position)?)
+        } else {
+            Token::Ident(word.to_ascii_uppercase())
+        };
+
+        tokens.push((position, token));
+        position += word_length;
+    }
+
+    Ok(tokens)
+}
+
+fn parse_number(digits: &str, radix: u32, position: usize) -> Result<u32, ConditionParseError> {
+    u32::from_str_radix(digits, radix).map_err(|_| ConditionParseError {
+        message: format!("invalid number {digits}"),
+        position,
+    })
+}
+
+struct Parser<'a> {
+    tokens: &'a [(usize, Token)],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek_with_position(&self) -> Option<(usize, &Token)> {
+        self.tokens
+            .get(self.position)
+            .map(|(position, token)| (*position, token))
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.peek_with_position().map(|(_, token)| token)
+    }
+
+    fn error(&self, message: &str) -> ConditionParseError {
+        let position = self.peek_with_position().map_or_else(
+            || self.tokens.last().map_or(0, |(position, _)| *position + 1),
+            |(position, _)| position,
+        );
+
+        ConditionParseError {
+            message: message.to_string(),
+            position,
+        }
+    }
+
+    fn take_op(&mut self, candidates: &[(&str, BinaryOp)]) -> Option<BinaryOp> {
+        let Some(Token::Op(op)) = self.peek() else {
+            return None;
+        };
+
+        let found = candidates
+            .iter()
+            .find(|(candidate, _)| candidate == op)
+            .map(|(_, binary_op)| *binary_op);
+        if found.is_some() {
+            self.position += 1;
+        }
+
+        found
+    }
+
+    fn parse_binary(
+        &mut self,
+        candidates: &[(&str, BinaryOp)],
+        next: fn(&mut Self) -> Result<Expr, ConditionParseError>,
+    ) -> Result<Expr, ConditionParseError> {
+        let mut left = next(self)?;
+
+        while let Some(op) = self.take_op(candidates) {
+            let right = next(self)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+
+        Ok(left)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ConditionParseError> {
+        self.parse_binary(&[("||", BinaryOp::Or)], Self::parse_and)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ConditionParseError> {
+        self.parse_binary(&[("&&", BinaryOp::And)], Self::parse_comparison)
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, ConditionParseError> {
+        self.parse_binary(
+            &[
+                ("==", BinaryOp::Eq),
+                ("!=", BinaryOp::Ne),
+                ("<=", BinaryOp::Le),
+                (">=", BinaryOp::Ge),
+                ("<", BinaryOp::Lt),
+                (">", BinaryOp::Gt),
+            ],
+            Self::parse_bitwise,
+        )
+    }
+
+    fn parse_bitwise(&mut self) -> Result<Expr, ConditionParseError> {
+        self.parse_binary(
+            &[
+                ("|", BinaryOp::BitOr),
+                ("&", BinaryOp::BitAnd),
+                ("^", BinaryOp::BitXor),
+            ],
+            Self::parse_unary,
+        )
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ConditionParseError> {
+        if self.peek() == Some(&Token::Op("!")) {
+            self.position += 1;
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ConditionParseError> {
+        let Some(token) = self.peek().cloned() else {
+            return Err(self.error("unexpected end of expression"));
+        };
+
+        match token {
+            Token::Number(value) => {
+                self.position += 1;
+                Ok(Expr::Literal(value))
+            }
+            Token::Ident(name) => {
+                let register = match name.as_str() {
+                    "A" => Register::A,
+                    "X" => Register::X,
+                    "Y" => Register::Y,
+                    "SP" | "S" => Register::SP,
+                    "PC" => Register::PC,
+                    "P" | "SR" => Register::P,
+                    _ => return Err(self.error(&format!("unknown register {name}"))),
+                };
+                self.position += 1;
+                Ok(Expr::Register(register))
+            }
+            Token::OpenBracket => {
+                self.position += 1;
+                let address = self.parse_or()?;
+                self.expect(Token::CloseBracket, "expected ]")?;
+                Ok(Expr::MemoryByte(Box::new(address)))
+            }
+            Token::OpenParen => {
+                self.position += 1;
+                let inner = self.parse_or()?;
+                self.expect(Token::CloseParen, "expected )")?;
+                Ok(inner)
+            }
+            _ => Err(self.error(&format!("unexpected {token:?}"))),
+        }
+    }
+
+    fn expect(&mut self, expected: Token, message: &str) -> Result<(), ConditionParseError> {
+        if self.peek() != Some(&expected) {
+            return Err(self.error(message));
+        }
+
+        self.position += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registers() -> Registers {
+        Registers {
+            acc: 0x10,
+            x_reg: 0x02,
+            y_reg: 0xFF,
+            stack_ptr: 0xFD,
+            program_counter: 0xC000,
+            status: 0x24,
+        }
+    }
+
+    fn value_of(source: &str) -> u32 {
+        let mut memory = Memory::new();
+        memory.load(0x0200, &[0x81, 0x7F]);
+
+        Condition::parse(source)
+            .unwrap()
+            .value(&registers(), &memory)
+    }
+
+    #[test]
+    fn reads_registers_and_number_formats() {
+        assert_eq!(value_of("A"), 0x10);
+        assert_eq!(value_of("pc"), 0xC000);
+        assert_eq!(value_of("S"), 0xFD);
+        assert_eq!(value_of("sr"), 0x24);
+        assert_eq!(value_of("$FF"), 0xFF);
+        assert_eq!(value_of("0x10"), 0x10);
+        assert_eq!(value_of("42"), 42);
+    }
+
+    #[test]
+    fn reads_memory_through_computed_address() {
+        assert_eq!(value_of("[$0200]"), 0x81);
+        assert_eq!(value_of("[$01FF ^ $03FE]"), 0x7F);
+        assert_eq!(value_of("[$0200 | 1]"), 0x7F);
+    }
+
+    #[test]
+    fn bitwise_binds_tighter_than_comparison() {
+        assert_eq!(value_of("[$0200] & $80 != 0"), 1);
+        assert_eq!(value_of("[$0201] & $80 != 0"), 0);
+        assert_eq!(value_of("A ^ $10 == 0"), 1);
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(value_of("A == 1 && X == 2 || Y == $FF"), 1);
+        assert_eq!(value_of("A == 1 && (X == 2 || Y == $FF)"), 0);
+        assert_eq!(value_of("!(A == $10)"), 0);
+        assert_eq!(value_of("!!A"), 1);
+    }
+
+    #[test]
+    fn comparisons() {
+        assert_eq!(value_of("X < 3"), 1);
+        assert_eq!(value_of("X <= 2"), 1);
+        assert_eq!(value_of("X > 2"), 0);
+        assert_eq!(value_of("X >= 3"), 0);
+        assert_eq!(value_of("PC != $C000"), 0);
+    }
+
+    #[test]
+    fn keeps_trimmed_source() {
+        let condition = Condition::parse("  A == $10 ").unwrap();
+        assert_eq!(condition.source(), "A == $10");
+        assert_eq!(condition.to_string(), "A == $10");
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        let error = Condition::parse("A == Q").unwrap_err();
+        assert_eq!(error.message, "unknown register Q");
+        assert_eq!(error.position, 5);
+
+        let error = Condition::parse("[$0200").unwrap_err();
+        assert_eq!(error.message, "expected ]");
+        assert_eq!(error.position, 2);
+
+        let error = Condition::parse("A ==").unwrap_err();
+        assert_eq!(error.message, "unexpected end of expression");
+
+        let error = Condition::parse("A # 1").unwrap_err();
+        assert_eq!(error.message, "unexpected character '#'");
+        assert_eq!(error.position, 2);
+
+        assert!(Condition::parse("$XYZ").is_err());
+        assert!(Condition::parse("A 1").is_err());
+    }
+}
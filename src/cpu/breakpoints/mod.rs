@@ -0,0 +1,3 @@
+pub mod breakpoint;
+pub mod condition;
+pub mod stop_reason;
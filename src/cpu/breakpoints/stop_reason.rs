@@ -0,0 +1,21 @@
+use crate::{
+    cpu::cpu_error::CpuError,
+    memory::watchpoint::WatchpointHit,
+    shared::types::{Byte, Word},
+};
+
+// why CPU::run returned control to the caller
+#[derive(Debug)]
+pub enum StopReason {
+    // PC is on the instruction that was not executed yet
+    Breakpoint { id: u32, address: Word },
+    // instruction that made the access has completed
+    Watchpoint(WatchpointHit),
+    Trapped(Word),
+    UnimplementedOpcode { opcode: Byte, address: Word },
+    // instruction that raised it has completed
+    BusError(CpuError),
+    CycleLimit,
+    // reverse execution reached the oldest recorded instruction
+    HistoryExhausted,
+}
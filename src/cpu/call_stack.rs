@@ -0,0 +1,93 @@
+use crate::shared::types::{Byte, Word};
+
+use super::{
+    cpu::CPU,
+    instruction_set::opcodes::{BRK_OPCODE, JSR_LENGTH, JSR_OPCODE, RTI_OPCODE, RTS_OPCODE},
+};
+
+// programs that never return (stack resets, JSR used as jump) would grow it forever
+const MAX_CALL_DEPTH: usize = 256;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CallKind {
+    Subroutine,
+    Interrupt,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct CallFrame {
+    pub kind: CallKind,
+    // address of JSR/BRK or of the interrupted instruction
+    pub call_site: Word,
+    // first instruction of the subroutine or handler
+    pub target: Word,
+    pub return_address: Word,
+    // SP before return address was pushed, frame is gone once SP climbs back
+    pub stack_ptr: Byte,
+}
+
+impl CPU {
+    pub fn call_stack(&self) -> &[CallFrame] {
+        &self.call_stack
+    }
+
+    // called by step after instruction completed
+    pub(super) fn track_call_stack(
+        &mut self,
+        opcode: Byte,
+        instruction_address: Word,
+        stack_ptr_before: Byte,
+    ) {
+        match opcode {
+            JSR_OPCODE => self.push_call_frame(CallFrame {
+                kind: CallKind::Subroutine,
+                call_site: instruction_address,
+                target: self.program_counter,
+                return_address: instruction_address.wrapping_add(JSR_LENGTH),
+                stack_ptr: stack_ptr_before,
+            }),
+            BRK_OPCODE => self.push_call_frame(CallFrame {
+                kind: CallKind::Interrupt,
+                call_site: instruction_address,
+                target: self.program_counter,
+                return_address: instruction_address.wrapping_add(2),
+                stack_ptr: stack_ptr_before,
+            }),
+            RTS_OPCODE | RTI_OPCODE => self.unwind_call_stack(),
+            _ => {}
+        }
+    }
+
+    pub(super) fn track_interrupt_entry(
+        &mut self,
+        interrupted_address: Word,
+        stack_ptr_before: Byte,
+    ) {
+        self.push_call_frame(CallFrame {
+            kind: CallKind::Interrupt,
+            call_site: interrupted_address,
+            target: self.program_counter,
+            return_address: interrupted_address,
+            stack_ptr: stack_ptr_before,
+        });
+    }
+
+    fn push_call_frame(&mut self, frame: CallFrame) {
+        if self.call_stack.len() == MAX_CALL_DEPTH {
+            self.call_stack.remove(0);
+        }
+
+        self.call_stack.push(frame);
+    }
+
+    // drops every frame whose return address was pulled off the stack
+    fn unwind_call_stack(&mut self) {
+        while self
+            .call_stack
+            .last()
+            .is_some_and(|frame| frame.stack_ptr <= self.stack_ptr)
+        {
+            self.call_stack.pop();
+        }
+    }
+}
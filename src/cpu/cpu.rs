@@ -1,13 +1,28 @@
+use serde_json::Value;
+
 use crate::{
-    memory::memory::Memory,
+    memory::{memory::Memory, memory_errors::MemoryError},
+    profiler::profiler::Profiler,
+    replay::{event_log::EventLog, input_event::InputEvent, replayer::Replayer},
     shared::{
+        constants::{RESET_VECTOR, STACK_PAGE_START},
         logger::LoggingHw,
         traits::ToWord,
         types::{Byte, Word},
     },
+    symbols::symbol_table::SymbolTable,
 };
 
-use super::status_register::status_register::StatusRegister;
+use super::{
+    breakpoints::{breakpoint::Breakpoints, stop_reason::StopReason},
+    call_stack::CallFrame,
+    cpu_error::CpuError,
+    instruction_set::opcode_table::OPCODE_TABLE,
+    registers::Registers,
+    rewind::rewind_history::RewindHistory,
+    status_register::status_register::StatusRegister,
+    step_result::StepResult,
+};
 
 // LE
 pub struct CPU {
@@ -19,12 +34,51 @@ pub struct CPU {
     pub(super) program_counter: Word,      // PC
     pub(super) stack_ptr: Byte,            // SP
 
+    // interrupt inputs, IRQ is level triggered, NMI is latched on edge
+    pub(super) irq_line: bool,
+    pub(super) nmi_pending: bool,
+
+    // total cycles consumed since power on
+    pub(super) cycles: u64,
+
+    // JSR/interrupt frames for debuggers, innermost last
+    pub(super) call_stack: Vec<CallFrame>,
+
+    // checked by run() before every instruction
+    pub(super) breakpoints: Breakpoints,
+
+    // external event log being written or replayed
+    pub(super) recording: Option<EventLog>,
+    pub(super) replayer: Option<Replayer>,
+
+    // journal for reverse execution, None while rewind is off
+    pub(super) rewind: Option<RewindHistory>,
+
+    // execution counts, None while profiling is off
+    pub(super) profiler: Option<Profiler>,
+
+    // names for addresses in log context and debugger output
+    pub(super) symbols: SymbolTable,
+
+    // first failed bus access of the current instruction, reported once it completes
+    pub(super) bus_fault: Option<MemoryError>,
+
     // TODO: add memory bus to decouple it from CPU
     pub(super) memory: Memory,
 }
 
+impl Default for CPU {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CPU {
     pub fn new() -> Self {
+        Self::with_memory(Memory::new())
+    }
+
+    pub fn with_memory(memory: Memory) -> Self {
         Self {
             program_counter: 0x0000,
             stack_ptr: 0x00,
@@ -32,13 +86,26 @@ impl CPU {
             x_reg: 0x00,
             y_reg: 0x00,
             status_reg: StatusRegister::new(),
-            memory: Memory::new(None),
+            irq_line: false,
+            nmi_pending: false,
+            cycles: 0,
+            call_stack: Vec::new(),
+            breakpoints: Breakpoints::new(),
+            recording: None,
+            replayer: None,
+            rewind: None,
+            profiler: None,
+            symbols: SymbolTable::new(),
+            bus_fault: None,
+            memory,
         }
     }
 
+    // a failed vector read is reported by the following step
     pub fn reset(&mut self) {
-        // TODO: implement specific memory addresses enum?
-        self.program_counter = self.read_byte(0xFFFC).to_word();
+        self.record_event(InputEvent::Reset);
+
+        self.program_counter = self.read_word(RESET_VECTOR);
 
         // Stack 8 bit range 0x0100 - 0x01FF
         self.stack_ptr = 0xFF;
@@ -48,37 +115,160 @@ impl CPU {
         self.acc = 0x00;
         self.x_reg = 0x00;
         self.y_reg = 0x00;
+
+        self.irq_line = false;
+        self.nmi_pending = false;
+
+        self.call_stack.clear();
+
+        // history can not be undone across a reset
+        if let Some(rewind) = self.rewind.as_mut() {
+            rewind.clear();
+        }
     }
 
-    pub fn execute_loop(&mut self) {
-        let opcode = self.fetch_byte();
-
-        let consumed_cycles = match opcode {
-            0xA9 => self.lda_immediate(),
-            0xA5 => self.lda_zero_page(),
-            0xB5 => self.lda_zero_page_x(),
-            0xAD => self.lda_absolute(),
-            0xBD => self.lda_absolute_x(),
-            0xB9 => self.lda_absolute_y(),
-            0xA1 => self.lda_indirect_x(),
-            0xB1 => self.lda_indirect_y(),
-            _ => {
-                todo!()
+    // executes single instruction or services pending interrupt
+    pub fn step(&mut self) -> StepResult {
+        if self.replayer.is_some() {
+            self.apply_due_events();
+        }
+
+        if self.rewind.is_some() {
+            return self.step_recording();
+        }
+
+        self.execute_step()
+    }
+
+    pub(super) fn execute_step(&mut self) -> StepResult {
+        let instruction_address = self.program_counter;
+        let stack_ptr_before = self.stack_ptr;
+        self.memory.set_current_pc(instruction_address);
+
+        if let Some(consumed_cycles) = self.service_interrupts() {
+            self.track_interrupt_entry(instruction_address, stack_ptr_before);
+            self.cycles += u64::from(consumed_cycles);
+            if self.profiler.is_some() {
+                self.profile_interrupt_entry(u64::from(consumed_cycles));
             }
+            if let Some(error) = self.take_bus_error(instruction_address) {
+                return StepResult::BusError(error);
+            }
+            return StepResult::Executed(consumed_cycles);
+        }
+
+        let opcode = self.fetch_opcode();
+
+        let Some(handler) = OPCODE_TABLE[opcode as usize] else {
+            // opcode came from the open bus
+            if let Some(error) = self.take_bus_error(instruction_address) {
+                self.program_counter = instruction_address;
+                return StepResult::BusError(error);
+            }
+            self.report_unimplemented_opcode(opcode, instruction_address);
+            return StepResult::UnimplementedOpcode {
+                opcode,
+                address: instruction_address,
+            };
         };
+        let consumed_cycles = handler(self);
+
+        self.cycles += u64::from(consumed_cycles);
+        if self.profiler.is_some() {
+            self.profile_instruction(instruction_address, opcode, u64::from(consumed_cycles));
+        }
+        self.track_call_stack(opcode, instruction_address, stack_ptr_before);
+
+        if let Some(error) = self.take_bus_error(instruction_address) {
+            return StepResult::BusError(error);
+        }
+
+        // jump or branch to itself, test suites use it to signal end of run
+        if self.program_counter == instruction_address {
+            return StepResult::Trapped(instruction_address);
+        }
+
+        StepResult::Executed(consumed_cycles)
+    }
+
+    // leaves PC on the opcode so state can be inspected
+    pub(super) fn report_unimplemented_opcode(&mut self, opcode: Byte, instruction_address: Word) {
+        self.program_counter = instruction_address;
+        self.log_error_source(
+            "step",
+            &CpuError::UnimplementedOpcode {
+                opcode,
+                address: instruction_address,
+            },
+        );
+    }
+
+    // runs until a debug stop, trap, unimplemented opcode or cycle budget is used up,
+    // breakpoint on the starting PC is ignored so run can resume from it
+    pub fn run(&mut self, max_cycles: Option<u64>) -> StopReason {
+        let cycle_limit = max_cycles.map(|max_cycles| self.cycles.saturating_add(max_cycles));
+        let mut is_first_instruction = true;
+
+        // drop hit left by accesses made outside of run
+        self.memory.take_watchpoint_hit();
+
+        if self.can_run_batched() {
+            return self.run_batched(cycle_limit);
+        }
+
+        loop {
+            // checked before the cycle limit, a sliced run resumes past the starting PC
+            if !is_first_instruction && !self.breakpoints.is_empty() {
+                let registers = self.registers();
+                if let Some(breakpoint) = self.breakpoints.find_hit(&registers, &self.memory) {
+                    return StopReason::Breakpoint {
+                        id: breakpoint.id,
+                        address: registers.program_counter,
+                    };
+                }
+            }
+            is_first_instruction = false;
+
+            if cycle_limit.is_some_and(|cycle_limit| self.cycles >= cycle_limit) {
+                return StopReason::CycleLimit;
+            }
+
+            match self.step() {
+                StepResult::Executed(_) => {}
+                StepResult::Trapped(address) => return StopReason::Trapped(address),
+                StepResult::UnimplementedOpcode { opcode, address } => {
+                    return StopReason::UnimplementedOpcode { opcode, address };
+                }
+                StepResult::BusError(error) => return StopReason::BusError(error),
+            }
+
+            if let Some(hit) = self.memory.take_watchpoint_hit() {
+                return StopReason::Watchpoint(hit);
+            }
+        }
     }
 
+    #[inline]
     pub fn fetch_byte(&mut self) -> Byte {
         let pc_value = self.program_counter;
-        let fetch_result = self.memory.read(pc_value);
-        self.program_counter += 1;
+        let fetch_result = self.memory.fetch(pc_value);
+        self.program_counter = self.program_counter.wrapping_add(1);
 
         match fetch_result {
             Ok(value) => value,
-            Err(error) => {
-                self.log_error("fetch_byte", error.to_string().as_str());
-                panic!("Memory read error");
-            }
+            Err(error) => self.bus_error(error),
+        }
+    }
+
+    #[inline]
+    pub(super) fn fetch_opcode(&mut self) -> Byte {
+        let pc_value = self.program_counter;
+        let fetch_result = self.memory.fetch_opcode(pc_value);
+        self.program_counter = self.program_counter.wrapping_add(1);
+
+        match fetch_result {
+            Ok(value) => value,
+            Err(error) => self.bus_error(error),
         }
     }
 
@@ -89,22 +279,122 @@ impl CPU {
         (high_byte << 8) | low_byte
     }
 
-    pub fn read_byte(&self, addr: Word) -> Byte {
+    #[inline]
+    pub fn read_byte(&mut self, addr: Word) -> Byte {
         match self.memory.read(addr) {
             Ok(value) => value,
-            Err(error) => {
-                self.log_error("read_byte", error.to_string().as_str());
-                panic!("Memory read error");
-            }
+            Err(error) => self.bus_error(error),
         }
     }
 
-    pub fn read_word(&self, addr: Word) -> Word {
+    pub fn read_word(&mut self, addr: Word) -> Word {
         let low_byte = self.read_byte(addr);
-        let high_byte = self.read_byte(addr + 1);
+        let high_byte = self.read_byte(addr.wrapping_add(1));
 
         (high_byte.to_word() << 8) | low_byte.to_word()
     }
+
+    #[inline]
+    pub fn write_byte(&mut self, addr: Word, value: Byte) {
+        if let Err(error) = self.memory.write(addr, value) {
+            self.bus_error(error);
+        }
+    }
+
+    // failed access reads the open bus like an unanswered one, the instruction carries on
+    #[cold]
+    pub(super) fn bus_error(&mut self, source: MemoryError) -> Byte {
+        if self.bus_fault.is_none() {
+            self.bus_fault = Some(source);
+        }
+
+        self.memory.data_bus()
+    }
+
+    #[inline(always)]
+    pub(super) fn take_bus_error(&mut self, instruction_address: Word) -> Option<CpuError> {
+        match self.bus_fault.is_some() {
+            true => self.report_bus_error(instruction_address),
+            false => None,
+        }
+    }
+
+    #[cold]
+    fn report_bus_error(&mut self, instruction_address: Word) -> Option<CpuError> {
+        let error = CpuError::Memory {
+            pc: instruction_address,
+            source: self.bus_fault.take()?,
+        };
+        self.log_error_source("step", &error);
+
+        Some(error)
+    }
+
+    pub fn push_byte(&mut self, value: Byte) {
+        self.write_byte(STACK_PAGE_START | self.stack_ptr.to_word(), value);
+        self.stack_ptr = self.stack_ptr.wrapping_sub(1);
+    }
+
+    pub fn pull_byte(&mut self) -> Byte {
+        self.stack_ptr = self.stack_ptr.wrapping_add(1);
+        self.read_byte(STACK_PAGE_START | self.stack_ptr.to_word())
+    }
+
+    pub fn registers(&self) -> Registers {
+        Registers {
+            acc: self.acc,
+            x_reg: self.x_reg,
+            y_reg: self.y_reg,
+            stack_ptr: self.stack_ptr,
+            program_counter: self.program_counter,
+            status: self.status_reg.get_raw(),
+        }
+    }
+
+    pub fn set_registers(&mut self, registers: &Registers) {
+        self.acc = registers.acc;
+        self.x_reg = registers.x_reg;
+        self.y_reg = registers.y_reg;
+        self.stack_ptr = registers.stack_ptr;
+        self.program_counter = registers.program_counter;
+        self.status_reg.set_raw(registers.status);
+    }
+
+    pub fn program_counter(&self) -> Word {
+        self.program_counter
+    }
+
+    pub fn set_program_counter(&mut self, address: Word) {
+        self.program_counter = address;
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn breakpoints(&self) -> &Breakpoints {
+        &self.breakpoints
+    }
+
+    pub fn breakpoints_mut(&mut self) -> &mut Breakpoints {
+        &mut self.breakpoints
+    }
+
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    pub fn set_symbols(&mut self, symbols: SymbolTable) {
+        self.symbols = symbols;
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    pub fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
 }
 
 impl LoggingHw for CPU {
@@ -112,11 +402,33 @@ impl LoggingHw for CPU {
         "CPU"
     }
 
-    // TODO: add cycle counting
     fn get_ctx(&self) -> Option<String> {
+        let pc = match self.symbols.name_for(self.program_counter) {
+            Some(name) => format!("{} <{name}>", self.program_counter),
+            None => self.program_counter.to_string(),
+        };
+
         Some(format!(
-            "SP={}, PC={}, REG_A={}, REG_X={}, REG_Y={}, STATUS_REG={}",
-            self.stack_ptr, self.program_counter, self.acc, self.x_reg, self.y_reg, self.status_reg
+            "CYC={}, SP={}, PC={}, REG_A={}, REG_X={}, REG_Y={}, STATUS_REG={}",
+            self.cycles, self.stack_ptr, pc, self.acc, self.x_reg, self.y_reg, self.status_reg
         ))
     }
+
+    fn get_ctx_fields(&self) -> Vec<(&'static str, Value)> {
+        let registers = self.registers();
+        let mut fields = vec![
+            ("cycles", Value::from(self.cycles)),
+            ("pc", Value::from(registers.program_counter)),
+            ("sp", Value::from(registers.stack_ptr)),
+            ("a", Value::from(registers.acc)),
+            ("x", Value::from(registers.x_reg)),
+            ("y", Value::from(registers.y_reg)),
+            ("p", Value::from(registers.status)),
+        ];
+        if let Some(name) = self.symbols.name_for(self.program_counter) {
+            fields.push(("symbol", Value::from(name)));
+        }
+
+        fields
+    }
 }
@@ -0,0 +1,70 @@
+use std::{error::Error, fmt};
+
+use serde_json::{json, Value};
+
+use crate::{
+    memory::memory_errors::MemoryError,
+    save_state::save_state_error::SaveStateError,
+    shared::types::{Byte, Word},
+};
+
+#[derive(Debug)]
+pub enum CpuError {
+    UnimplementedOpcode { opcode: Byte, address: Word },
+    // bus error raised while executing instruction at pc
+    Memory { pc: Word, source: MemoryError },
+    // recorded history could not be put back, state is only partly rewound
+    Rewind(SaveStateError),
+}
+
+impl CpuError {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CpuError::UnimplementedOpcode { .. } => "unimplementedOpcode",
+            CpuError::Memory { .. } => "memory",
+            CpuError::Rewind(_) => "rewind",
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut value = json!({
+            "error": self.name(),
+            "message": self.to_string(),
+        });
+        match self {
+            CpuError::UnimplementedOpcode { opcode, address } => {
+                value["opcode"] = json!(opcode);
+                value["address"] = json!(address);
+            }
+            CpuError::Memory { pc, source } => {
+                value["pc"] = json!(pc);
+                value["source"] = source.to_json();
+            }
+            CpuError::Rewind(source) => value["source"] = json!(source.to_string()),
+        }
+
+        value
+    }
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CpuError::UnimplementedOpcode { opcode, address } => {
+                write!(f, "unimplemented opcode {opcode:#04X} at {address:#06X}")
+            }
+            CpuError::Memory { pc, .. } => write!(f, "bus error at pc {pc:#06X}"),
+            CpuError::Rewind(_) => write!(f, "step back failed"),
+        }
+    }
+}
+
+impl Error for CpuError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CpuError::Memory { source, .. } => Some(source),
+            CpuError::Rewind(source) => Some(source),
+            CpuError::UnimplementedOpcode { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{
+    memory::memory_errors::MemoryError,
+    replay::{
+        event_log::EventLog,
+        input_event::{InputEvent, TimedEvent},
+        replayer::Replayer,
+    },
+    shared::types::{Byte, Word},
+};
+
+use super::cpu::CPU;
+
+impl CPU {
+    // logs every external event from now on, pair the log with save_state() taken here
+    pub fn start_recording(&mut self) {
+        self.recording = Some(EventLog::new());
+    }
+
+    pub fn stop_recording(&mut self) -> Option<EventLog> {
+        self.recording.take()
+    }
+
+    pub fn recording(&self) -> Option<&EventLog> {
+        self.recording.as_ref()
+    }
+
+    // events are applied before the first instruction that starts at or after their cycle,
+    // the CPU must be in the state the log was recorded from
+    pub fn start_replay(&mut self, log: EventLog) {
+        self.replayer = Some(Replayer::new(log));
+    }
+
+    pub fn stop_replay(&mut self) {
+        self.replayer = None;
+    }
+
+    pub fn replayer(&self) -> Option<&Replayer> {
+        self.replayer.as_ref()
+    }
+
+    // device register written by the outside world, RAM takes the byte as is
+    pub fn input_byte(&mut self, address: Word, value: Byte) -> Result<(), MemoryError> {
+        self.record_event(InputEvent::InputByte { address, value });
+        self.memory.poke(address, value)
+    }
+
+    pub fn apply_event(&mut self, event: InputEvent) -> Result<(), MemoryError> {
+        match event {
+            InputEvent::IrqLine(asserted) => self.set_irq_line(asserted),
+            InputEvent::Nmi => self.trigger_nmi(),
+            InputEvent::InputByte { address, value } => self.input_byte(address, value)?,
+            InputEvent::Reset => self.reset(),
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn record_event(&mut self, event: InputEvent) {
+        if let Some(recording) = self.recording.as_mut() {
+            recording.push(TimedEvent {
+                cycle: self.cycles,
+                event,
+            });
+        }
+    }
+
+    pub(super) fn apply_due_events(&mut self) {
+        let cycle = self.cycles;
+        while let Some(event) = self
+            .replayer
+            .as_mut()
+            .and_then(|replayer| replayer.next_due(cycle))
+        {
+            // reported with the instruction that follows
+            if let Err(error) = self.apply_event(event) {
+                self.bus_error(error);
+            }
+        }
+    }
+}
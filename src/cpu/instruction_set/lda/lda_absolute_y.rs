@@ -8,7 +8,7 @@ impl CPU {
     pub fn lda_absolute_y(&mut self) -> Byte {
         let base_address = self.fetch_word();
 
-        let target_address = base_address + self.y_reg.to_word();
+        let target_address = base_address.wrapping_add(self.y_reg.to_word());
 
         let page_crossed = base_address & 0xFF00 != target_address & 0xFF00;
 
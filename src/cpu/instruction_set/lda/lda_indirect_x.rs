@@ -8,7 +8,7 @@ impl CPU {
     pub fn lda_indirect_x(&mut self) -> Byte {
         let zero_page_base_address = self.fetch_byte();
 
-        let pointer_address = ((zero_page_base_address + self.x_reg) & 0xFF).to_word();
+        let pointer_address = zero_page_base_address.wrapping_add(self.x_reg).to_word();
 
         let address_low = self.read_byte(pointer_address).to_word();
         let address_high = self.read_byte((pointer_address + 1) & 0xFF).to_word();
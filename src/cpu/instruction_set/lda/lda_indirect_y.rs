@@ -13,7 +13,7 @@ impl CPU {
 
         let base_address = (address_high << 8) | address_low;
 
-        let target_address = base_address + self.y_reg.to_word();
+        let target_address = base_address.wrapping_add(self.y_reg.to_word());
 
         let page_crossed = base_address & 0xFF00 != target_address & 0xFF00;
 
@@ -8,7 +8,7 @@ impl CPU {
     pub fn lda_zero_page_x(&mut self) -> Byte {
         let zero_page_base_address = self.fetch_byte();
 
-        let target_address = ((zero_page_base_address + self.x_reg) & 0xFF).to_word();
+        let target_address = zero_page_base_address.wrapping_add(self.x_reg).to_word();
 
         let value = self.read_byte(target_address);
 
@@ -1 +1,3 @@
 pub mod lda;
+pub mod opcode_table;
+pub mod opcodes;
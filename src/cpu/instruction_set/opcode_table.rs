@@ -0,0 +1,22 @@
+use crate::{cpu::cpu::CPU, shared::types::Byte};
+
+// executes one instruction with the opcode already fetched, returns consumed cycles
+pub(in crate::cpu) type OpcodeHandler = fn(&mut CPU) -> Byte;
+
+// indexed by opcode, None until the instruction is implemented
+pub(in crate::cpu) static OPCODE_TABLE: [Option<OpcodeHandler>; 256] = build_opcode_table();
+
+const fn build_opcode_table() -> [Option<OpcodeHandler>; 256] {
+    let mut table: [Option<OpcodeHandler>; 256] = [None; 256];
+
+    table[0xA9] = Some(CPU::lda_immediate);
+    table[0xA5] = Some(CPU::lda_zero_page);
+    table[0xB5] = Some(CPU::lda_zero_page_x);
+    table[0xAD] = Some(CPU::lda_absolute);
+    table[0xBD] = Some(CPU::lda_absolute_x);
+    table[0xB9] = Some(CPU::lda_absolute_y);
+    table[0xA1] = Some(CPU::lda_indirect_x);
+    table[0xB1] = Some(CPU::lda_indirect_y);
+
+    table
+}
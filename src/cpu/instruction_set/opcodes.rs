@@ -0,0 +1,13 @@
+use crate::shared::types::{Byte, Word};
+
+// opcodes the call stack, profiler and debuggers look at before executing them
+pub const BRK_OPCODE: Byte = 0x00;
+pub const JSR_OPCODE: Byte = 0x20;
+pub const RTI_OPCODE: Byte = 0x40;
+pub const RTS_OPCODE: Byte = 0x60;
+
+// JSR $addr, step over resumes after it
+pub const JSR_LENGTH: Word = 3;
+
+// instructions that push or pop call frames
+pub const CALL_STACK_OPCODES: [Byte; 4] = [JSR_OPCODE, RTS_OPCODE, BRK_OPCODE, RTI_OPCODE];
@@ -0,0 +1,67 @@
+use crate::{
+    cpu::{cpu::CPU, status_register::status_register_bitflag_enum::StatusRegisterBitFlag},
+    replay::input_event::InputEvent,
+    shared::{
+        constants::{IRQ_VECTOR, NMI_VECTOR},
+        logger::LoggingHw,
+        types::{Byte, Word},
+    },
+};
+
+const INTERRUPT_SEQUENCE_CYCLES: Byte = 7;
+
+impl CPU {
+    // IRQ stays asserted until device releases it
+    pub fn set_irq_line(&mut self, asserted: bool) {
+        if self.irq_line != asserted {
+            self.record_event(InputEvent::IrqLine(asserted));
+        }
+        self.irq_line = asserted;
+    }
+
+    pub fn irq_line(&self) -> bool {
+        self.irq_line
+    }
+
+    // NMI is edge triggered, every call latches a single interrupt
+    pub fn trigger_nmi(&mut self) {
+        self.record_event(InputEvent::Nmi);
+        self.nmi_pending = true;
+    }
+
+    pub fn nmi_pending(&self) -> bool {
+        self.nmi_pending
+    }
+
+    // NMI wins over IRQ, IRQ is masked by I flag
+    pub(super) fn service_interrupts(&mut self) -> Option<Byte> {
+        if self.nmi_pending {
+            self.nmi_pending = false;
+            self.log_debug("service_interrupts", "NMI");
+            self.enter_interrupt(NMI_VECTOR);
+            return Some(INTERRUPT_SEQUENCE_CYCLES);
+        }
+
+        let irq_masked = self.status_reg.get_val(StatusRegisterBitFlag::I) == 1;
+        if self.irq_line && !irq_masked {
+            self.log_debug("service_interrupts", "IRQ");
+            self.enter_interrupt(IRQ_VECTOR);
+            return Some(INTERRUPT_SEQUENCE_CYCLES);
+        }
+
+        None
+    }
+
+    // hardware interrupts push status with B clear, BRK pushes it set
+    fn enter_interrupt(&mut self, vector: Word) {
+        let [pc_low, pc_high] = self.program_counter.to_le_bytes();
+        self.push_byte(pc_high);
+        self.push_byte(pc_low);
+
+        let break_bit: Byte = StatusRegisterBitFlag::B.into();
+        self.push_byte(self.status_reg.get_raw() & !(1 << break_bit));
+
+        self.status_reg.set_val(StatusRegisterBitFlag::I, true);
+        self.program_counter = self.read_word(vector);
+    }
+}
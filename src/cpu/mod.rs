@@ -1,3 +1,15 @@
+mod batched_run;
+pub mod breakpoints;
+pub mod call_stack;
 pub mod cpu;
-mod instruction_set;
+pub mod cpu_error;
+mod external_events;
+pub mod instruction_set;
+mod interrupts;
+mod profiling;
+pub mod registers;
+mod reverse;
+pub mod rewind;
+mod snapshot;
 mod status_register;
+pub mod step_result;
@@ -0,0 +1,55 @@
+use crate::{
+    profiler::profiler::Profiler,
+    shared::types::{Byte, Word},
+};
+
+use super::{cpu::CPU, instruction_set::opcodes::JSR_OPCODE};
+
+impl CPU {
+    // counts are not undone by step_back, clear the profiler around reverse execution
+    pub fn enable_profiler(&mut self) {
+        if self.profiler.is_none() {
+            self.profiler = Some(Profiler::new());
+        }
+    }
+
+    // returns the collected profile
+    pub fn disable_profiler(&mut self) -> Option<Profiler> {
+        self.profiler.take()
+    }
+
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    pub fn profiler_mut(&mut self) -> Option<&mut Profiler> {
+        self.profiler.as_mut()
+    }
+
+    // called by step after the instruction completed, before the call stack is updated
+    pub(super) fn profile_instruction(
+        &mut self,
+        instruction_address: Word,
+        opcode: Byte,
+        cycles: u64,
+    ) {
+        let Some(profiler) = self.profiler.as_mut() else {
+            return;
+        };
+
+        profiler.record_instruction(instruction_address, opcode, cycles, &self.call_stack);
+        if opcode == JSR_OPCODE {
+            profiler.record_call(self.program_counter);
+        }
+    }
+
+    // called once the handler frame was pushed
+    pub(super) fn profile_interrupt_entry(&mut self, cycles: u64) {
+        let Some(profiler) = self.profiler.as_mut() else {
+            return;
+        };
+
+        profiler.record_call(self.program_counter);
+        profiler.record_interrupt_entry(cycles, &self.call_stack);
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt;
+
+use crate::shared::types::{Byte, Word};
+
+// plain copy of programmer visible registers, status is the raw P byte
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Registers {
+    pub acc: Byte,
+    pub x_reg: Byte,
+    pub y_reg: Byte,
+    pub stack_ptr: Byte,
+    pub program_counter: Word,
+    pub status: Byte,
+}
+
+impl Registers {
+    // NV-BDIZC, upper case for set flags
+    pub fn status_flags(&self) -> String {
+        "NV-BDIZC"
+            .chars()
+            .enumerate()
+            .map(|(index, flag)| {
+                let is_set = (self.status >> (7 - index)) & 1 == 1;
+                match (flag, is_set) {
+                    ('-', _) => '-',
+                    (flag, true) => flag,
+                    (flag, false) => flag.to_ascii_lowercase(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Registers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "PC=${:04X} A=${:02X} X=${:02X} Y=${:02X} SP=${:02X} P=${:02X} [{}]",
+            self.program_counter,
+            self.acc,
+            self.x_reg,
+            self.y_reg,
+            self.stack_ptr,
+            self.status,
+            self.status_flags()
+        )
+    }
+}
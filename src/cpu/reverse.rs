@@ -0,0 +1,362 @@
+use crate::{
+    memory::{bus_access::BusAccessKind, device_journal::DeviceJournal, watchpoint::WatchpointHit},
+    shared::types::Word,
+};
+
+use super::{
+    breakpoints::stop_reason::StopReason,
+    cpu::CPU,
+    cpu_error::CpuError,
+    instruction_set::opcodes::CALL_STACK_OPCODES,
+    rewind::{journal_entry::JournalEntry, rewind_history::RewindHistory},
+    step_result::StepResult,
+};
+
+impl CPU {
+    // every instruction from now on is journaled, memory edits made through
+    // memory_mut() bypass the journal and are only corrected at segment snapshots
+    pub fn enable_rewind(&mut self, history: RewindHistory) {
+        self.memory.set_write_journaling(true);
+        self.rewind = Some(history);
+    }
+
+    pub fn disable_rewind(&mut self) {
+        self.memory.set_write_journaling(false);
+        self.rewind = None;
+    }
+
+    pub fn rewind_history(&self) -> Option<&RewindHistory> {
+        self.rewind.as_ref()
+    }
+
+    pub(super) fn step_recording(&mut self) -> StepResult {
+        if self
+            .rewind
+            .as_ref()
+            .is_some_and(RewindHistory::needs_snapshot)
+        {
+            let snapshot = self.save_state();
+            if let Some(rewind) = self.rewind.as_mut() {
+                rewind.begin_segment(snapshot);
+            }
+        }
+
+        // cloning the call stack for every instruction would dominate the journal
+        let may_change_call_stack = self.nmi_pending
+            || self.irq_line
+            || CALL_STACK_OPCODES.contains(&self.memory.peek(self.program_counter));
+        let mut entry = JournalEntry {
+            registers: self.registers(),
+            cycles: self.cycles,
+            irq_line: self.irq_line,
+            nmi_pending: self.nmi_pending,
+            data_bus: self.memory.data_bus(),
+            call_stack: may_change_call_stack.then(|| self.call_stack.clone()),
+            writes: Vec::new(),
+            devices: DeviceJournal::default(),
+        };
+
+        // bank switches from host pokes since the last step are not this instruction's
+        self.memory.take_device_journal();
+        let step_result = self.execute_step();
+        entry.writes = self.memory.take_write_journal();
+        entry.devices = self.memory.take_device_journal();
+
+        if !matches!(step_result, StepResult::UnimplementedOpcode { .. }) {
+            if let Some(rewind) = self.rewind.as_mut() {
+                rewind.push(entry);
+            }
+        }
+
+        step_result
+    }
+
+    // undoes the last recorded instruction, false when history is empty
+    pub fn step_back(&mut self) -> Result<bool, CpuError> {
+        let Some((entry, snapshot)) = self.rewind.as_mut().and_then(RewindHistory::pop) else {
+            return Ok(false);
+        };
+
+        // writes go through the page table the instruction left behind, undone before it
+        for &(address, value) in entry.writes.iter().rev() {
+            self.memory.load(address, &[value]);
+        }
+        self.memory
+            .undo_device_journal(&entry.devices)
+            .map_err(|error| CpuError::Rewind(error.into()))?;
+        self.set_registers(&entry.registers);
+        self.cycles = entry.cycles;
+        self.irq_line = entry.irq_line;
+        self.nmi_pending = entry.nmi_pending;
+        self.memory.set_data_bus(entry.data_bus);
+        if let Some(call_stack) = entry.call_stack {
+            self.call_stack = call_stack;
+        }
+
+        // taken on this machine, fails only when devices were unmapped since
+        if let Some(snapshot) = snapshot {
+            self.apply_state(&snapshot).map_err(CpuError::Rewind)?;
+        }
+
+        Ok(true)
+    }
+
+    // steps back until a breakpoint is reached or an undone instruction wrote a
+    // watched address, read watchpoints can not fire since reads are not journaled
+    pub fn reverse_continue(&mut self) -> Result<StopReason, CpuError> {
+        self.memory.take_watchpoint_hit();
+
+        loop {
+            let watchpoint_hit = self.last_write_watchpoint_hit();
+
+            if !self.step_back()? {
+                return Ok(StopReason::HistoryExhausted);
+            }
+
+            if let Some(hit) = watchpoint_hit {
+                return Ok(StopReason::Watchpoint(hit));
+            }
+
+            if !self.breakpoints.is_empty() {
+                let registers = self.registers();
+                if let Some(breakpoint) = self.breakpoints.find_hit(&registers, &self.memory) {
+                    return Ok(StopReason::Breakpoint {
+                        id: breakpoint.id,
+                        address: registers.program_counter,
+                    });
+                }
+            }
+        }
+    }
+
+    // moves back to right before the last instruction that wrote address and
+    // returns its PC, state is left untouched when no such write was recorded
+    pub fn step_back_to_write(&mut self, address: Word) -> Result<Option<Word>, CpuError> {
+        let Some(steps) = self
+            .rewind
+            .as_ref()
+            .and_then(|rewind| rewind.steps_since_write(address))
+        else {
+            return Ok(None);
+        };
+        for _ in 0..steps {
+            self.step_back()?;
+        }
+
+        Ok(Some(self.program_counter))
+    }
+
+    // watchpoint matching a write of the newest journaled instruction, value is what it wrote
+    fn last_write_watchpoint_hit(&self) -> Option<WatchpointHit> {
+        let entry = self.rewind.as_ref()?.newest()?;
+
+        entry.writes.iter().find_map(|&(address, _)| {
+            self.memory
+                .watchpoints()
+                .iter()
+                .find(|watchpoint| {
+                    watchpoint.kind.matches(BusAccessKind::Write)
+                        && watchpoint.range.contains(&address)
+                })
+                .map(|watchpoint| WatchpointHit {
+                    id: watchpoint.id,
+                    address,
+                    value: self.memory.peek(address),
+                    access: BusAccessKind::Write,
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cpu::registers::Registers,
+        memory::{
+            device::Device,
+            device_error::DeviceError,
+            page_table::{BankId, BankSwitch},
+        },
+        shared::{constants::NMI_VECTOR, types::Byte},
+    };
+
+    use super::*;
+
+    // LDA #$11, LDA $0300, then the NMI handler at $0300 runs LDA #$44, LDA #$55
+    const PROGRAM: [Byte; 5] = [0xA9, 0x11, 0xAD, 0x00, 0x03];
+    const STEPS: usize = 5;
+    // step that services the NMI and pushes PC and status
+    const NMI_STEP: usize = 2;
+
+    #[derive(Debug, PartialEq)]
+    struct Machine {
+        registers: Registers,
+        cycles: u64,
+        nmi_pending: bool,
+        data_bus: Byte,
+        stack: [Byte; 3],
+        call_depth: usize,
+    }
+
+    fn machine_of(cpu: &CPU) -> Machine {
+        Machine {
+            registers: cpu.registers(),
+            cycles: cpu.cycles(),
+            nmi_pending: cpu.nmi_pending(),
+            data_bus: cpu.memory().data_bus(),
+            stack: [0x01FD, 0x01FE, 0x01FF].map(|address| cpu.memory().peek(address)),
+            call_depth: cpu.call_stack.len(),
+        }
+    }
+
+    // a short snapshot interval makes step_back cross segment boundaries
+    fn recording_cpu() -> CPU {
+        let mut cpu = CPU::new();
+        cpu.memory_mut().load(0x0200, &PROGRAM);
+        cpu.memory_mut().load(0x0300, &[0xA9, 0x44, 0xA9, 0x55]);
+        cpu.memory_mut().load(NMI_VECTOR, &[0x00, 0x03]);
+        cpu.set_registers(&Registers {
+            stack_ptr: 0xFF,
+            program_counter: 0x0200,
+            ..Registers::default()
+        });
+        cpu.enable_rewind(RewindHistory::new(1024 * 1024, 2));
+        cpu
+    }
+
+    // state before every step, followed by the final state
+    fn run_forward(cpu: &mut CPU) -> Vec<Machine> {
+        let mut states = Vec::new();
+        for step in 0..STEPS {
+            if step == NMI_STEP {
+                cpu.trigger_nmi();
+            }
+            states.push(machine_of(cpu));
+            assert!(matches!(cpu.step(), StepResult::Executed(_)));
+        }
+        states.push(machine_of(cpu));
+        states
+    }
+
+    #[test]
+    fn step_back_restores_every_recorded_state() {
+        let mut cpu = recording_cpu();
+        let states = run_forward(&mut cpu);
+        assert_eq!(cpu.rewind_history().unwrap().len(), STEPS);
+
+        for expected in states[..STEPS].iter().rev() {
+            assert!(cpu.step_back().unwrap());
+            assert_eq!(&machine_of(&cpu), expected);
+        }
+
+        assert!(!cpu.step_back().unwrap());
+        assert!(cpu.rewind_history().unwrap().is_empty());
+    }
+
+    #[test]
+    fn stepping_again_after_step_back_reaches_the_same_state() {
+        let mut cpu = recording_cpu();
+        let states = run_forward(&mut cpu);
+
+        while cpu.step_back().unwrap() {}
+        let replayed = run_forward(&mut cpu);
+
+        assert_eq!(replayed, states);
+    }
+
+    #[test]
+    fn step_back_to_write_stops_before_the_interrupt_push() {
+        let mut cpu = recording_cpu();
+        let states = run_forward(&mut cpu);
+
+        assert_eq!(cpu.step_back_to_write(0x01FF).unwrap(), Some(0x0205));
+        assert_eq!(machine_of(&cpu), states[NMI_STEP]);
+        assert_eq!(cpu.step_back_to_write(0x0000).unwrap(), None);
+    }
+
+    // counts reads, latches writes and maps bank 1 at $C000 on every write
+    struct Mapper {
+        reads: Byte,
+        latch: Byte,
+        bank: BankId,
+        switch: Option<BankSwitch>,
+    }
+
+    impl Device for Mapper {
+        fn name(&self) -> &'static str {
+            "mapper"
+        }
+
+        fn read(&mut self, _offset: Word) -> Result<Byte, DeviceError> {
+            self.reads += 1;
+            Ok(self.reads)
+        }
+
+        fn write(&mut self, _offset: Word, value: Byte) -> Result<(), DeviceError> {
+            self.latch = value;
+            self.switch = Some(BankSwitch {
+                first_page: 0xC0,
+                page_count: 1,
+                bank: self.bank,
+                bank_page: 0,
+                is_writable: false,
+            });
+            Ok(())
+        }
+
+        fn peek(&self, offset: Word) -> Byte {
+            match offset {
+                0 => self.reads,
+                _ => self.latch,
+            }
+        }
+
+        fn take_bank_switch(&mut self) -> Option<BankSwitch> {
+            self.switch.take()
+        }
+
+        fn save_state(&self) -> Vec<Byte> {
+            vec![self.reads, self.latch]
+        }
+
+        fn restore_state(&mut self, state: &[Byte]) -> Result<(), DeviceError> {
+            self.reads = state[0];
+            self.latch = state[1];
+            Ok(())
+        }
+    }
+
+    // LDA $D000 reads the mapper, the NMI pushes onto the stack page, which is a mapper too
+    #[test]
+    fn step_back_undoes_device_state_and_bank_switches() {
+        let mut cpu = recording_cpu();
+        cpu.memory_mut().load(0x0200, &[0xAD, 0x00, 0xD0]);
+        cpu.memory_mut().load(0xC000, &[0x11]);
+        let bank = cpu.memory_mut().add_bank(&[0x22; 0x100]);
+        for first_page in [0xD0, 0x01] {
+            let mapper = Mapper {
+                reads: 0,
+                latch: 0,
+                bank,
+                switch: None,
+            };
+            cpu.memory_mut().map_device(first_page, 1, mapper).unwrap();
+        }
+
+        cpu.step();
+        cpu.trigger_nmi();
+        cpu.step();
+        assert_eq!(cpu.memory().peek(0xD000), 1);
+        // status is pushed last, with the unused bit set
+        assert_eq!(cpu.memory().peek(0x01FF), 0x20);
+        assert_eq!(cpu.memory().peek(0xC000), 0x22);
+
+        assert!(cpu.step_back().unwrap());
+        assert_eq!(cpu.memory().peek(0x01FF), 0x00);
+        assert_eq!(cpu.memory().peek(0xC000), 0x11);
+        assert_eq!(cpu.memory().peek(0xD000), 1);
+
+        assert!(cpu.step_back().unwrap());
+        assert_eq!(cpu.memory().peek(0xD000), 0);
+    }
+}
@@ -0,0 +1,44 @@
+use std::mem;
+
+use crate::{
+    cpu::{call_stack::CallFrame, registers::Registers},
+    memory::device_journal::DeviceJournal,
+    shared::types::{Byte, Word},
+};
+
+// everything needed to undo one executed instruction or interrupt entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    // CPU state before the instruction
+    pub registers: Registers,
+    pub cycles: u64,
+    pub irq_line: bool,
+    pub nmi_pending: bool,
+    // open bus latch, unmapped reads after a step back must see the old value
+    pub data_bus: Byte,
+    // stored only when the instruction changed the call stack
+    pub call_stack: Option<Vec<CallFrame>>,
+    // (address, previous value) in write order
+    pub writes: Vec<(Word, Byte)>,
+    // devices and bank mapping the instruction changed
+    pub devices: DeviceJournal,
+}
+
+impl JournalEntry {
+    pub fn wrote(&self, address: Word) -> bool {
+        self.writes
+            .iter()
+            .any(|&(written_address, _)| written_address == address)
+    }
+
+    // approximate heap + inline footprint, used for the memory budget
+    pub fn size_bytes(&self) -> usize {
+        mem::size_of::<Self>()
+            + self.writes.capacity() * mem::size_of::<(Word, Byte)>()
+            + self
+                .call_stack
+                .as_ref()
+                .map_or(0, |frames| frames.capacity() * mem::size_of::<CallFrame>())
+            + self.devices.size_bytes()
+    }
+}
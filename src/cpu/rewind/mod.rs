@@ -0,0 +1,2 @@
+pub mod journal_entry;
+pub mod rewind_history;
@@ -0,0 +1,227 @@
+use std::{collections::VecDeque, mem};
+
+use crate::{save_state::save_state::SaveState, shared::types::Word};
+
+use super::journal_entry::JournalEntry;
+
+// --rewind and the monitor take budgets in megabytes
+pub const BYTES_PER_MEGABYTE: usize = 1024 * 1024;
+pub const DEFAULT_REWIND_BUDGET_BYTES: usize = 64 * BYTES_PER_MEGABYTE;
+pub const DEFAULT_SNAPSHOT_INTERVAL: usize = 100_000;
+// a segment is only cut short for the budget after this many instructions, a budget
+// below one snapshot would otherwise copy the whole memory on every step
+const MIN_SNAPSHOT_INTERVAL: usize = 1_000;
+
+// snapshot of the state before the first journaled instruction,
+// restoring it when the segment runs empty drops any drift from unjournaled edits
+struct Segment {
+    snapshot: SaveState,
+    journal: Vec<JournalEntry>,
+    size_bytes: usize,
+}
+
+// recorded past of a CPU, oldest segments are dropped once budget is used up
+pub struct RewindHistory {
+    segments: VecDeque<Segment>,
+    snapshot_interval: usize,
+    budget_bytes: usize,
+    used_bytes: usize,
+    recorded_instructions: usize,
+}
+
+impl Default for RewindHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_REWIND_BUDGET_BYTES, DEFAULT_SNAPSHOT_INTERVAL)
+    }
+}
+
+impl RewindHistory {
+    pub fn new(budget_bytes: usize, snapshot_interval: usize) -> Self {
+        Self {
+            segments: VecDeque::new(),
+            snapshot_interval: snapshot_interval.max(1),
+            budget_bytes,
+            used_bytes: 0,
+            recorded_instructions: 0,
+        }
+    }
+
+    // instructions that can be stepped back
+    pub fn len(&self) -> usize {
+        self.recorded_instructions
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recorded_instructions == 0
+    }
+
+    pub fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+
+    pub fn budget_bytes(&self) -> usize {
+        self.budget_bytes
+    }
+
+    pub fn clear(&mut self) {
+        self.segments.clear();
+        self.used_bytes = 0;
+        self.recorded_instructions = 0;
+    }
+
+    // a full segment, or running over budget, starts a new one so the old can be evicted
+    pub(crate) fn needs_snapshot(&self) -> bool {
+        self.segments.back().is_none_or(|segment| {
+            segment.journal.len() >= self.snapshot_interval
+                || (self.used_bytes > self.budget_bytes
+                    && segment.journal.len() >= MIN_SNAPSHOT_INTERVAL.min(self.snapshot_interval))
+        })
+    }
+
+    pub(crate) fn begin_segment(&mut self, snapshot: SaveState) {
+        let size_bytes = mem::size_of::<Segment>()
+            + snapshot.memory.data.len()
+            + mem::size_of_val(snapshot.call_stack.as_slice());
+
+        self.used_bytes += size_bytes;
+        self.segments.push_back(Segment {
+            snapshot,
+            journal: Vec::new(),
+            size_bytes,
+        });
+    }
+
+    pub(crate) fn push(&mut self, entry: JournalEntry) {
+        let Some(segment) = self.segments.back_mut() else {
+            return;
+        };
+
+        let entry_size = entry.size_bytes();
+        segment.size_bytes += entry_size;
+        segment.journal.push(entry);
+        self.used_bytes += entry_size;
+        self.recorded_instructions += 1;
+
+        // newest segment is always kept, even alone over budget
+        while self.used_bytes > self.budget_bytes && self.segments.len() > 1 {
+            if let Some(evicted) = self.segments.pop_front() {
+                self.used_bytes -= evicted.size_bytes;
+                self.recorded_instructions -= evicted.journal.len();
+            }
+        }
+    }
+
+    // newest entry, with the segment snapshot when it was the first one recorded after it
+    pub(crate) fn pop(&mut self) -> Option<(JournalEntry, Option<SaveState>)> {
+        loop {
+            let segment = self.segments.back_mut()?;
+
+            let Some(entry) = segment.journal.pop() else {
+                // segment opened by an instruction that did not execute
+                if let Some(empty) = self.segments.pop_back() {
+                    self.used_bytes -= empty.size_bytes;
+                }
+                continue;
+            };
+
+            let entry_size = entry.size_bytes();
+            segment.size_bytes -= entry_size;
+            self.used_bytes -= entry_size;
+            self.recorded_instructions -= 1;
+
+            if !segment.journal.is_empty() {
+                return Some((entry, None));
+            }
+
+            let snapshot = self.segments.pop_back().map(|segment| {
+                self.used_bytes -= segment.size_bytes;
+                segment.snapshot
+            });
+            return Some((entry, snapshot));
+        }
+    }
+
+    pub fn newest(&self) -> Option<&JournalEntry> {
+        self.segments
+            .iter()
+            .rev()
+            .find_map(|segment| segment.journal.last())
+    }
+
+    // how many steps back reach the instruction that last wrote address
+    pub fn steps_since_write(&self, address: Word) -> Option<usize> {
+        self.segments
+            .iter()
+            .rev()
+            .flat_map(|segment| segment.journal.iter().rev())
+            .position(|entry| entry.wrote(address))
+            .map(|position| position + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cpu::{cpu::CPU, registers::Registers},
+        memory::device_journal::DeviceJournal,
+    };
+
+    use super::*;
+
+    fn entry() -> JournalEntry {
+        JournalEntry {
+            registers: Registers::default(),
+            cycles: 0,
+            irq_line: false,
+            nmi_pending: false,
+            data_bus: 0x00,
+            call_stack: None,
+            writes: Vec::new(),
+            devices: DeviceJournal::default(),
+        }
+    }
+
+    // records like CPU::step_recording does, returns how many snapshots were taken
+    fn record(history: &mut RewindHistory, instructions: usize) -> usize {
+        let snapshot = CPU::new().save_state();
+        let mut snapshots = 0;
+        for _ in 0..instructions {
+            if history.needs_snapshot() {
+                history.begin_segment(snapshot.clone());
+                snapshots += 1;
+            }
+            history.push(entry());
+        }
+
+        snapshots
+    }
+
+    #[test]
+    fn snapshots_every_interval_within_budget() {
+        let mut history = RewindHistory::new(DEFAULT_REWIND_BUDGET_BYTES, 10);
+
+        assert_eq!(record(&mut history, 25), 3);
+        assert_eq!(history.len(), 25);
+    }
+
+    #[test]
+    fn budget_below_one_snapshot_keeps_a_minimum_interval() {
+        let mut history = RewindHistory::new(1, DEFAULT_SNAPSHOT_INTERVAL);
+
+        assert_eq!(record(&mut history, 2 * MIN_SNAPSHOT_INTERVAL + 1), 3);
+        // only the newest segment survives the budget
+        assert_eq!(history.len(), 1);
+    }
+
+    #[test]
+    fn pop_hands_back_the_snapshot_with_the_first_entry_of_a_segment() {
+        let mut history = RewindHistory::new(DEFAULT_REWIND_BUDGET_BYTES, 2);
+        record(&mut history, 3);
+
+        assert!(matches!(history.pop(), Some((_, Some(_)))));
+        assert!(matches!(history.pop(), Some((_, None))));
+        assert!(matches!(history.pop(), Some((_, Some(_)))));
+        assert!(history.pop().is_none());
+        assert_eq!(history.used_bytes(), 0);
+    }
+}
@@ -0,0 +1,40 @@
+use crate::save_state::{save_state::SaveState, save_state_error::SaveStateError};
+
+use super::cpu::CPU;
+
+impl CPU {
+    // breakpoints and symbols are debugging session setup, not machine state
+    pub fn save_state(&self) -> SaveState {
+        SaveState {
+            registers: self.registers(),
+            cycles: self.cycles,
+            irq_line: self.irq_line,
+            nmi_pending: self.nmi_pending,
+            call_stack: self.call_stack.clone(),
+            memory: self.memory.snapshot(),
+        }
+    }
+
+    // recorded rewind history does not lead to the restored state and is dropped,
+    // the CPU is left untouched when memory can not take the state
+    pub fn restore_state(&mut self, state: &SaveState) -> Result<(), SaveStateError> {
+        self.apply_state(state)?;
+
+        if let Some(rewind) = self.rewind.as_mut() {
+            rewind.clear();
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn apply_state(&mut self, state: &SaveState) -> Result<(), SaveStateError> {
+        self.memory.restore(&state.memory)?;
+        self.set_registers(&state.registers);
+        self.cycles = state.cycles;
+        self.irq_line = state.irq_line;
+        self.nmi_pending = state.nmi_pending;
+        self.call_stack.clone_from(&state.call_stack);
+
+        Ok(())
+    }
+}
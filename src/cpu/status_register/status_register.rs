@@ -39,6 +39,16 @@ impl StatusRegister {
         (self._data & (1 << shift)) >> shift & 1
     }
 
+    // raw byte view, used when pushing/pulling status on stack
+    pub fn get_raw(&self) -> Byte {
+        self._data
+    }
+
+    // bit 5 is not backed by storage, it always reads as 1
+    pub fn set_raw(&mut self, value: Byte) {
+        self._data = value | 0b00100000;
+    }
+
     // set all status bits to zero
     pub fn clear(&mut self) {
         self._data = 0b00100000;
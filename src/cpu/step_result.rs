@@ -0,0 +1,26 @@
+use crate::shared::types::{Byte, Word};
+
+use super::cpu_error::CpuError;
+
+#[derive(Debug)]
+pub enum StepResult {
+    // consumed cycles
+    Executed(Byte),
+    // instruction at given address jumped to itself
+    Trapped(Word),
+    UnimplementedOpcode { opcode: Byte, address: Word },
+    // instruction completed with failed accesses reading the open bus, first error is kept
+    BusError(CpuError),
+}
+
+impl StepResult {
+    pub fn error(self) -> Option<CpuError> {
+        match self {
+            StepResult::UnimplementedOpcode { opcode, address } => {
+                Some(CpuError::UnimplementedOpcode { opcode, address })
+            }
+            StepResult::BusError(error) => Some(error),
+            _ => None,
+        }
+    }
+}
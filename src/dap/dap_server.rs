@@ -0,0 +1,1154 @@
+use std::{
+    io::{self, BufRead, Write},
+    path::Path,
+    sync::mpsc::{self, TryRecvError},
+    thread,
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    cpu::{
+        breakpoints::{condition::Condition, stop_reason::StopReason},
+        cpu::CPU,
+        instruction_set::opcodes::{JSR_LENGTH, JSR_OPCODE},
+        registers::Registers,
+        rewind::rewind_history::RewindHistory,
+        step_result::StepResult,
+    },
+    disassembler::disassembler::disassemble,
+    memory::{memory::Memory, power_on_pattern::PowerOnPattern, watchpoint::WatchKind},
+    shared::{
+        error_chain::error_chain,
+        logger::LoggingHw,
+        parsing::parse_word,
+        types::{Byte, Word},
+    },
+    symbols::loaders::load_symbols,
+    throttle::{clock_preset::ClockPreset, throttle::Throttle},
+};
+
+use super::{
+    protocol::{decode_base64, encode_base64, read_message, write_message},
+    source_map::SourceMap,
+};
+
+const THREAD_ID: i64 = 1;
+// run is sliced so pause and other requests are served while the program runs
+const RUN_SLICE_CYCLES: u64 = 100_000;
+
+// variablesReference values of the fixed scopes
+const REGISTERS_REFERENCE: i64 = 1;
+const FLAGS_REFERENCE: i64 = 2;
+const STACK_REFERENCE: i64 = 3;
+const ZERO_PAGE_REFERENCE: i64 = 4;
+
+const FLAG_NAMES: [(&str, u8); 7] = [
+    ("N", 7),
+    ("V", 6),
+    ("B", 4),
+    ("D", 3),
+    ("I", 2),
+    ("Z", 1),
+    ("C", 0),
+];
+
+type HandlerResult = Result<Value, String>;
+
+struct Session {
+    cpu: CPU,
+    source_map: Option<SourceMap>,
+    stop_on_entry: bool,
+    // continue runs at real-time speed while set
+    throttle: Option<Throttle>,
+}
+
+pub struct DapServer<W: Write> {
+    writer: W,
+    sequence: i64,
+    session: Option<Session>,
+    lines_start_at_1: bool,
+    running: bool,
+    terminated: bool,
+    // temporary breakpoint used by next/stepOut
+    step_breakpoint: Option<u32>,
+    source_breakpoints: Vec<u32>,
+    instruction_breakpoints: Vec<u32>,
+    data_breakpoints: Vec<u32>,
+}
+
+impl<W: Write> DapServer<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            sequence: 1,
+            session: None,
+            lines_start_at_1: true,
+            running: false,
+            terminated: false,
+            step_breakpoint: None,
+            source_breakpoints: Vec::new(),
+            instruction_breakpoints: Vec::new(),
+            data_breakpoints: Vec::new(),
+        }
+    }
+
+    // requests are read on a separate thread so pause reaches a running program
+    pub fn serve<R: BufRead + Send + 'static>(&mut self, mut reader: R) -> io::Result<()> {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            while let Ok(Some(message)) = read_message(&mut reader) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        while !self.terminated {
+            let message = if self.running {
+                match receiver.try_recv() {
+                    Ok(message) => message,
+                    Err(TryRecvError::Empty) => {
+                        self.run_slice()?;
+                        continue;
+                    }
+                    Err(TryRecvError::Disconnected) => return Ok(()),
+                }
+            } else {
+                match receiver.recv() {
+                    Ok(message) => message,
+                    Err(_) => return Ok(()),
+                }
+            };
+
+            self.handle_message(&message)?;
+        }
+
+        Ok(())
+    }
+
+    fn next_sequence(&mut self) -> i64 {
+        let sequence = self.sequence;
+        self.sequence += 1;
+        sequence
+    }
+
+    fn send_event(&mut self, event: &str, body: Value) -> io::Result<()> {
+        let message = json!({
+            "seq": self.next_sequence(),
+            "type": "event",
+            "event": event,
+            "body": body,
+        });
+        write_message(&mut self.writer, &message)
+    }
+
+    fn send_stopped(
+        &mut self,
+        reason: &str,
+        description: Option<String>,
+        hit_ids: &[u32],
+    ) -> io::Result<()> {
+        self.running = false;
+        self.clear_step_breakpoint();
+
+        let mut body = json!({
+            "reason": reason,
+            "threadId": THREAD_ID,
+            "allThreadsStopped": true,
+        });
+        if let Some(description) = description {
+            body["description"] = json!(description);
+            body["text"] = json!(description);
+        }
+        if !hit_ids.is_empty() {
+            body["hitBreakpointIds"] = json!(hit_ids);
+        }
+
+        self.send_event("stopped", body)
+    }
+
+    fn handle_message(&mut self, message: &Value) -> io::Result<()> {
+        if message["type"] != "request" {
+            return Ok(());
+        }
+
+        let command = message["command"].as_str().unwrap_or_default().to_string();
+        let arguments = message.get("arguments").cloned().unwrap_or(Value::Null);
+        self.log_debug("handle_message", &command);
+
+        let result = self.handle_request(&command, &arguments);
+        let response = match &result {
+            Ok(body) => json!({
+                "seq": self.next_sequence(),
+                "type": "response",
+                "request_seq": message["seq"],
+                "success": true,
+                "command": command,
+                "body": body,
+            }),
+            Err(error) => json!({
+                "seq": self.next_sequence(),
+                "type": "response",
+                "request_seq": message["seq"],
+                "success": false,
+                "command": command,
+                "message": error,
+            }),
+        };
+        write_message(&mut self.writer, &response)?;
+
+        // events that must follow their response
+        match command.as_str() {
+            "launch" if result.is_ok() => self.send_event("initialized", json!({})),
+            "configurationDone" => {
+                let stop_on_entry = self
+                    .session
+                    .as_ref()
+                    .is_some_and(|session| session.stop_on_entry);
+                if stop_on_entry {
+                    self.send_stopped("entry", None, &[])
+                } else {
+                    self.running = self.session.is_some();
+                    Ok(())
+                }
+            }
+            "disconnect" | "terminate" => {
+                self.terminated = true;
+                self.send_event("terminated", json!({}))
+            }
+            "reverseContinue" if result.is_ok() => {
+                let stop_reason = self
+                    .session()
+                    .map(|session| session.cpu.reverse_continue())
+                    .unwrap_or(Ok(StopReason::HistoryExhausted));
+                match stop_reason {
+                    Ok(stop_reason) => self.report_stop(stop_reason),
+                    Err(error) => self.send_stopped("exception", Some(error_chain(&error)), &[]),
+                }
+            }
+            "next" | "stepIn" | "stepOut" | "stepBack" if result.is_ok() && !self.running => {
+                self.send_stopped("step", None, &[])
+            }
+            "pause" => self.send_stopped("pause", None, &[]),
+            _ => Ok(()),
+        }
+    }
+
+    fn handle_request(&mut self, command: &str, arguments: &Value) -> HandlerResult {
+        match command {
+            "initialize" => self.initialize(arguments),
+            "launch" => self.launch(arguments),
+            "configurationDone" | "disconnect" | "terminate" => Ok(json!({})),
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "6502" }] })),
+            "setBreakpoints" => self.set_breakpoints(arguments),
+            "setInstructionBreakpoints" => self.set_instruction_breakpoints(arguments),
+            "setExceptionBreakpoints" => Ok(json!({ "breakpoints": [] })),
+            "dataBreakpointInfo" => self.data_breakpoint_info(arguments),
+            "setDataBreakpoints" => self.set_data_breakpoints(arguments),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => Ok(scopes()),
+            "variables" => self.variables(arguments),
+            "setVariable" => self.set_variable(arguments),
+            "continue" => {
+                self.session()?;
+                self.running = true;
+                Ok(json!({ "allThreadsContinued": true }))
+            }
+            "next" => self.next(),
+            "stepIn" => self.step_in(),
+            "stepOut" => self.step_out(),
+            "stepBack" => match self.session()?.cpu.step_back() {
+                Ok(true) => Ok(json!({})),
+                Ok(false) => Err("no recorded history to step back into".to_string()),
+                Err(error) => Err(error_chain(&error)),
+            },
+            "reverseContinue" => match self.session()?.cpu.rewind_history() {
+                Some(_) => Ok(json!({})),
+                None => Err("rewind is disabled for this session".to_string()),
+            },
+            "pause" => Ok(json!({})),
+            "evaluate" => self.evaluate(arguments),
+            "disassemble" => self.disassemble(arguments),
+            "readMemory" => self.read_memory(arguments),
+            "writeMemory" => self.write_memory(arguments),
+            _ => Err(format!("unsupported request {command}")),
+        }
+    }
+
+    fn session(&mut self) -> Result<&mut Session, String> {
+        self.session
+            .as_mut()
+            .ok_or("no program launched".to_string())
+    }
+
+    fn initialize(&mut self, arguments: &Value) -> HandlerResult {
+        self.lines_start_at_1 = arguments["linesStartAt1"].as_bool().unwrap_or(true);
+
+        Ok(json!({
+            "supportsConfigurationDoneRequest": true,
+            "supportsConditionalBreakpoints": true,
+            "supportsSetVariable": true,
+            "supportsDisassembleRequest": true,
+            "supportsReadMemoryRequest": true,
+            "supportsWriteMemoryRequest": true,
+            "supportsInstructionBreakpoints": true,
+            "supportsDataBreakpoints": true,
+            "supportsSteppingGranularity": true,
+            "supportsTerminateRequest": true,
+            "supportsStepBack": true,
+        }))
+    }
+
+    // { program, loadAddress?, startAddress?, listing?, symbols?, stopOnEntry?, rewind?, clock?, speed?,
+    //   ramPattern? },
+    // rewind recording for stepBack/reverseContinue is on unless "rewind": false,
+    // "clock" (ntsc, pal, c64, 1mhz, hz) and "speed" (positive multiplier) pace continue to real time,
+    // "ramPattern" (zeros, ones, c64, nes, random, random:<seed>) fills RAM before loading
+    // without loadAddress the image is mapped as ROM ending at $FFFF and started from reset vector
+    fn launch(&mut self, arguments: &Value) -> HandlerResult {
+        let program = arguments["program"]
+            .as_str()
+            .ok_or("launch configuration needs \"program\"")?;
+        let load_address = address_argument(&arguments["loadAddress"])?;
+        let start_address = address_argument(&arguments["startAddress"])?;
+
+        let ram_pattern = arguments["ramPattern"]
+            .as_str()
+            .map(|name| PowerOnPattern::parse(name).ok_or(format!("unknown RAM pattern {name}")))
+            .transpose()?
+            .unwrap_or_default();
+
+        let mut memory = Memory::new();
+        memory.fill_ram(ram_pattern);
+        match load_address {
+            Some(address) => memory.load_bin(program, address),
+            None => memory.load_rom(program),
+        }
+        .map_err(|error| error_chain(&error))?;
+
+        let source_map = arguments["listing"]
+            .as_str()
+            .map(|listing| {
+                SourceMap::from_listing(listing)
+                    .map_err(|error| format!("failed to read listing {listing}: {error}"))
+            })
+            .transpose()?;
+
+        let symbols = arguments["symbols"]
+            .as_str()
+            .map(|path| {
+                load_symbols(path)
+                    .map_err(|error| format!("failed to load symbols {path}: {error}"))
+            })
+            .transpose()?;
+
+        let clock = arguments["clock"]
+            .as_str()
+            .map(|clock| ClockPreset::parse(clock).ok_or(format!("unknown clock {clock}")))
+            .transpose()?;
+        let speed = match &arguments["speed"] {
+            Value::Null => None,
+            value => Some(
+                value
+                    .as_f64()
+                    .filter(|speed| *speed > 0.0 && speed.is_finite())
+                    .ok_or(format!("speed must be a positive number, got {value}"))?,
+            ),
+        };
+        let throttle = (clock.is_some() || speed.is_some()).then(|| {
+            let mut throttle = Throttle::new(clock.unwrap_or_default());
+            if let Some(speed) = speed {
+                throttle.set_speed(speed);
+            }
+            throttle
+        });
+
+        let mut cpu = CPU::with_memory(memory);
+        if let Some(symbols) = symbols {
+            cpu.set_symbols(symbols);
+        }
+        cpu.reset();
+        if arguments["rewind"].as_bool().unwrap_or(true) {
+            cpu.enable_rewind(RewindHistory::default());
+        }
+        if let Some(address) = start_address.or(load_address) {
+            cpu.set_program_counter(address);
+        }
+
+        self.session = Some(Session {
+            cpu,
+            source_map,
+            stop_on_entry: arguments["stopOnEntry"].as_bool().unwrap_or(false),
+            throttle,
+        });
+
+        Ok(json!({}))
+    }
+
+    fn client_line(&self, line: u32) -> u32 {
+        client_line(line, self.lines_start_at_1).unwrap_or_default()
+    }
+
+    fn set_breakpoints(&mut self, arguments: &Value) -> HandlerResult {
+        let requested_path = arguments["source"]["path"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let requested = arguments["breakpoints"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        let lines: Vec<Option<u32>> = requested
+            .iter()
+            .map(|breakpoint| {
+                server_line(
+                    breakpoint["line"].as_u64().unwrap_or(0),
+                    self.lines_start_at_1,
+                )
+            })
+            .collect();
+        let lines_start_at_1 = self.lines_start_at_1;
+
+        let previous = std::mem::take(&mut self.source_breakpoints);
+        let session = self.session()?;
+        for id in previous {
+            session.cpu.breakpoints_mut().remove(id);
+        }
+
+        let mut added_ids = Vec::new();
+        let mut results = Vec::new();
+        for (breakpoint, line) in requested.iter().zip(lines) {
+            let Some(line) = line else {
+                results.push(json!({ "verified": false, "message": "invalid line" }));
+                continue;
+            };
+            let location = session
+                .source_map
+                .as_ref()
+                .filter(|source_map| same_file(source_map.path(), &requested_path))
+                .and_then(|source_map| source_map.address_for_line(line));
+
+            let Some((client_line, address)) = location.and_then(|(mapped_line, address)| {
+                Some((client_line(mapped_line, lines_start_at_1)?, address))
+            }) else {
+                results.push(json!({ "verified": false, "message": "no code at this line" }));
+                continue;
+            };
+
+            let condition = match breakpoint["condition"]
+                .as_str()
+                .map(Condition::parse)
+                .transpose()
+            {
+                Ok(condition) => condition,
+                Err(error) => {
+                    results.push(json!({ "verified": false, "message": format!("invalid condition: {error}") }));
+                    continue;
+                }
+            };
+
+            let id = session.cpu.breakpoints_mut().add(Some(address), condition);
+            added_ids.push(id);
+            results.push(json!({
+                "id": id,
+                "verified": true,
+                "line": client_line,
+                "instructionReference": format!("0x{address:04X}"),
+            }));
+        }
+
+        self.source_breakpoints = added_ids;
+        Ok(json!({ "breakpoints": results }))
+    }
+
+    fn set_instruction_breakpoints(&mut self, arguments: &Value) -> HandlerResult {
+        let previous = std::mem::take(&mut self.instruction_breakpoints);
+        let session = self.session()?;
+        for id in previous {
+            session.cpu.breakpoints_mut().remove(id);
+        }
+
+        let mut added_ids = Vec::new();
+        let mut results = Vec::new();
+        for breakpoint in arguments["breakpoints"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+        {
+            let address = breakpoint["instructionReference"]
+                .as_str()
+                .and_then(parse_word)
+                .map(|address| {
+                    address.wrapping_add_signed(breakpoint["offset"].as_i64().unwrap_or(0) as i16)
+                });
+            let condition = breakpoint["condition"]
+                .as_str()
+                .map(Condition::parse)
+                .transpose();
+
+            match (address, condition) {
+                (Some(address), Ok(condition)) => {
+                    let id = session.cpu.breakpoints_mut().add(Some(address), condition);
+                    added_ids.push(id);
+                    results.push(json!({
+                        "id": id,
+                        "verified": true,
+                        "instructionReference": format!("0x{address:04X}"),
+                    }));
+                }
+                (None, _) => {
+                    results.push(json!({ "verified": false, "message": "invalid address" }))
+                }
+                (_, Err(error)) => results.push(
+                    json!({ "verified": false, "message": format!("invalid condition: {error}") }),
+                ),
+            }
+        }
+
+        self.instruction_breakpoints = added_ids;
+        Ok(json!({ "breakpoints": results }))
+    }
+
+    // watching a memory cell, names like "$0200" as shown by Stack/Zero Page scopes or symbols
+    fn data_breakpoint_info(&mut self, arguments: &Value) -> HandlerResult {
+        let session = self.session()?;
+        let name = arguments["name"]
+            .as_str()
+            .unwrap_or_default()
+            .trim_start_matches('[')
+            .trim_end_matches(']');
+        let address = session
+            .cpu
+            .symbols()
+            .address_of(name)
+            .or_else(|| parse_word(name));
+
+        Ok(match address {
+            Some(address) => json!({
+                "dataId": format!("${address:04X}"),
+                "description": session.cpu.symbols().describe(address),
+                "accessTypes": ["read", "write", "readWrite"],
+                "canPersist": true,
+            }),
+            None => {
+                json!({ "dataId": null, "description": "only memory addresses can be watched" })
+            }
+        })
+    }
+
+    fn set_data_breakpoints(&mut self, arguments: &Value) -> HandlerResult {
+        let previous = std::mem::take(&mut self.data_breakpoints);
+        let session = self.session()?;
+        for id in previous {
+            session.cpu.memory_mut().remove_watchpoint(id);
+        }
+
+        let mut added_ids = Vec::new();
+        let mut results = Vec::new();
+        for breakpoint in arguments["breakpoints"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+        {
+            let Some(address) = breakpoint["dataId"].as_str().and_then(parse_word) else {
+                results.push(json!({ "verified": false, "message": "invalid data id" }));
+                continue;
+            };
+
+            let kind = match breakpoint["accessType"].as_str() {
+                Some("read") => WatchKind::Read,
+                Some("write") => WatchKind::Write,
+                _ => WatchKind::Access,
+            };
+
+            let id = session
+                .cpu
+                .memory_mut()
+                .add_watchpoint(address..=address, kind);
+            added_ids.push(id);
+            results.push(json!({ "id": id, "verified": true }));
+        }
+
+        self.data_breakpoints = added_ids;
+        Ok(json!({ "breakpoints": results }))
+    }
+
+    fn frame(&self, id: usize, name: String, address: Word) -> Value {
+        let mut frame = json!({
+            "id": id,
+            "name": name,
+            "line": 0,
+            "column": 0,
+            "instructionPointerReference": format!("0x{address:04X}"),
+        });
+
+        let source_map = self
+            .session
+            .as_ref()
+            .and_then(|session| session.source_map.as_ref());
+        if let Some(source_map) = source_map {
+            if let Some(line) = source_map.line_for_address(address) {
+                frame["line"] = json!(self.client_line(line));
+                frame["column"] = json!(if self.lines_start_at_1 { 1 } else { 0 });
+                frame["source"] = json!({
+                    "name": Path::new(source_map.path()).file_name().map(|name| name.to_string_lossy()),
+                    "path": source_map.path(),
+                });
+            }
+        }
+
+        frame
+    }
+
+    // frame 0 is current PC inside innermost subroutine, outer frames sit on their JSR/interrupted instruction
+    fn stack_trace(&mut self) -> HandlerResult {
+        let session = self.session()?;
+        let pc = session.cpu.program_counter();
+        let call_stack = session.cpu.call_stack().to_vec();
+        let symbols = session.cpu.symbols().clone();
+
+        let routine_name =
+            |depth: usize| match depth.checked_sub(1).and_then(|index| call_stack.get(index)) {
+                Some(frame) => symbols
+                    .name_for(frame.target)
+                    .map_or_else(|| format!("${:04X}", frame.target), str::to_string),
+                None => "main".to_string(),
+            };
+
+        let mut frames = vec![self.frame(0, routine_name(call_stack.len()), pc)];
+        for (depth, call_frame) in call_stack.iter().enumerate().rev() {
+            frames.push(self.frame(frames.len(), routine_name(depth), call_frame.call_site));
+        }
+
+        Ok(json!({ "stackFrames": frames, "totalFrames": frames.len() }))
+    }
+
+    fn variables(&mut self, arguments: &Value) -> HandlerResult {
+        let session = self.session()?;
+        let registers = session.cpu.registers();
+        let memory = session.cpu.memory();
+
+        let variables: Vec<Value> = match arguments["variablesReference"].as_i64() {
+            Some(REGISTERS_REFERENCE) => register_values(&registers)
+                .into_iter()
+                .map(|(name, value, width)| variable(name, &format_value(value, width), None))
+                .collect(),
+            Some(FLAGS_REFERENCE) => FLAG_NAMES
+                .iter()
+                .map(|(name, bit)| {
+                    let value = (registers.status >> bit) & 1;
+                    variable(name, &value.to_string(), None)
+                })
+                .collect(),
+            // used part of the stack, top first
+            Some(STACK_REFERENCE) => (Word::from(registers.stack_ptr) + 1..=0xFF)
+                .map(|offset| {
+                    let address = 0x0100 + offset;
+                    let value = memory.peek(address);
+                    variable(
+                        &format!("${address:04X}"),
+                        &format_value(value.into(), 2),
+                        Some(address.into()),
+                    )
+                })
+                .collect(),
+            Some(ZERO_PAGE_REFERENCE) => (0..0x100)
+                .step_by(16)
+                .map(|row| {
+                    let bytes = (row..row + 16)
+                        .map(|address| format!("{:02X}", memory.peek(address)))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    variable(&format!("${row:04X}"), &bytes, Some(row.into()))
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        Ok(json!({ "variables": variables }))
+    }
+
+    fn set_variable(&mut self, arguments: &Value) -> HandlerResult {
+        let name = arguments["name"].as_str().unwrap_or_default();
+        let value = arguments["value"]
+            .as_str()
+            .and_then(parse_word)
+            .ok_or("value must be a number ($hex, 0xhex or decimal)")?;
+        let reference = arguments["variablesReference"].as_i64();
+
+        let session = self.session()?;
+        let mut registers = session.cpu.registers();
+        let byte = || Byte::try_from(value).map_err(|_| format!("{name} is 8 bit"));
+
+        match (reference, name) {
+            (Some(REGISTERS_REFERENCE), "A") => registers.acc = byte()?,
+            (Some(REGISTERS_REFERENCE), "X") => registers.x_reg = byte()?,
+            (Some(REGISTERS_REFERENCE), "Y") => registers.y_reg = byte()?,
+            (Some(REGISTERS_REFERENCE), "SP") => registers.stack_ptr = byte()?,
+            (Some(REGISTERS_REFERENCE), "P") => registers.status = byte()?,
+            (Some(REGISTERS_REFERENCE), "PC") => registers.program_counter = value,
+            (Some(FLAGS_REFERENCE), flag) => {
+                let (_, bit) = FLAG_NAMES
+                    .iter()
+                    .find(|(name, _)| *name == flag)
+                    .ok_or(format!("unknown flag {flag}"))?;
+                match value {
+                    0 => registers.status &= !(1 << bit),
+                    1 => registers.status |= 1 << bit,
+                    _ => return Err("flag value must be 0 or 1".to_string()),
+                }
+            }
+            (Some(STACK_REFERENCE), address) => {
+                let address = parse_word(address).ok_or("invalid address")?;
+                let value = byte()?;
+                session
+                    .cpu
+                    .memory_mut()
+                    .poke(address, value)
+                    .map_err(|error| error_chain(&error))?;
+                return Ok(json!({ "value": format_value(value.into(), 2) }));
+            }
+            _ => return Err(format!("{name} can not be changed")),
+        }
+
+        session.cpu.set_registers(&registers);
+        let width = if name == "PC" { 4 } else { 2 };
+        Ok(json!({ "value": format_value(value.into(), width) }))
+    }
+
+    fn next(&mut self) -> HandlerResult {
+        let session = self.session()?;
+        let pc = session.cpu.program_counter();
+
+        if session.cpu.memory().peek(pc) == JSR_OPCODE {
+            let id = session
+                .cpu
+                .breakpoints_mut()
+                .add(Some(pc.wrapping_add(JSR_LENGTH)), None);
+            self.step_breakpoint = Some(id);
+            self.running = true;
+            return Ok(json!({}));
+        }
+
+        self.step_in()
+    }
+
+    fn step_in(&mut self) -> HandlerResult {
+        let session = self.session()?;
+        session.cpu.memory_mut().take_watchpoint_hit();
+
+        match session.cpu.step() {
+            StepResult::UnimplementedOpcode { opcode, address } => Err(format!(
+                "unimplemented opcode ${opcode:02X} at ${address:04X}"
+            )),
+            StepResult::BusError(error) => Err(error_chain(&error)),
+            StepResult::Executed(_) | StepResult::Trapped(_) => Ok(json!({})),
+        }
+    }
+
+    fn step_out(&mut self) -> HandlerResult {
+        let session = self.session()?;
+        let Some(frame) = session.cpu.call_stack().last().copied() else {
+            return self.step_in();
+        };
+
+        let id = session
+            .cpu
+            .breakpoints_mut()
+            .add(Some(frame.return_address), None);
+        self.step_breakpoint = Some(id);
+        self.running = true;
+
+        Ok(json!({}))
+    }
+
+    fn clear_step_breakpoint(&mut self) {
+        if let (Some(id), Some(session)) = (self.step_breakpoint.take(), self.session.as_mut()) {
+            session.cpu.breakpoints_mut().remove(id);
+        }
+    }
+
+    fn run_slice(&mut self) -> io::Result<()> {
+        let Some(session) = self.session.as_mut() else {
+            self.running = false;
+            return Ok(());
+        };
+
+        // a pause longer than the throttle lag limit resyncs pacing on its own
+        let stop_reason = match session.throttle.as_mut() {
+            Some(throttle) => {
+                let stop_reason = session.cpu.run(Some(throttle.batch_cycles()));
+                throttle.pace(session.cpu.cycles());
+                stop_reason
+            }
+            None => session.cpu.run(Some(RUN_SLICE_CYCLES)),
+        };
+        self.report_stop(stop_reason)
+    }
+
+    fn report_stop(&mut self, stop_reason: StopReason) -> io::Result<()> {
+        match stop_reason {
+            StopReason::CycleLimit => Ok(()),
+            StopReason::Breakpoint { id, .. } if Some(id) == self.step_breakpoint => {
+                self.send_stopped("step", None, &[])
+            }
+            StopReason::Breakpoint { id, .. } => self.send_stopped("breakpoint", None, &[id]),
+            StopReason::Watchpoint(hit) => {
+                let description = format!(
+                    "{} ${:04X} = ${:02X}",
+                    hit.access.as_str(),
+                    hit.address,
+                    hit.value
+                );
+                self.send_stopped("data breakpoint", Some(description), &[hit.id])
+            }
+            StopReason::Trapped(address) => {
+                self.send_stopped("exception", Some(format!("trapped at ${address:04X}")), &[])
+            }
+            StopReason::UnimplementedOpcode { opcode, address } => self.send_stopped(
+                "exception",
+                Some(format!(
+                    "unimplemented opcode ${opcode:02X} at ${address:04X}"
+                )),
+                &[],
+            ),
+            StopReason::BusError(error) => {
+                self.send_stopped("exception", Some(error_chain(&error)), &[])
+            }
+            StopReason::HistoryExhausted => {
+                self.send_stopped("step", Some("reached start of history".to_string()), &[])
+            }
+        }
+    }
+
+    // expressions use breakpoint condition syntax, e.g. "[$0200]" or "A & $80",
+    // a bare symbol name evaluates to its address
+    fn evaluate(&mut self, arguments: &Value) -> HandlerResult {
+        let expression = arguments["expression"].as_str().unwrap_or_default();
+        let session = self.session()?;
+
+        if let Some(address) = session.cpu.symbols().address_of(expression.trim()) {
+            return Ok(json!({
+                "result": format!("${address:04X}"),
+                "memoryReference": format!("0x{address:04X}"),
+                "variablesReference": 0,
+            }));
+        }
+
+        let condition = Condition::parse(expression).map_err(|error| error.to_string())?;
+        let value = condition.value(&session.cpu.registers(), session.cpu.memory());
+
+        Ok(json!({
+            "result": format!("${value:X} ({value})"),
+            "variablesReference": 0,
+        }))
+    }
+
+    fn disassemble(&mut self, arguments: &Value) -> HandlerResult {
+        let reference = arguments["memoryReference"]
+            .as_str()
+            .and_then(parse_word)
+            .ok_or("invalid memoryReference")?;
+        let reference =
+            reference.wrapping_add_signed(arguments["offset"].as_i64().unwrap_or(0) as i16);
+        let instruction_offset = arguments["instructionOffset"].as_i64().unwrap_or(0);
+        let instruction_count = arguments["instructionCount"].as_u64().unwrap_or(0) as usize;
+
+        let session = self.session.as_ref().ok_or("no program launched")?;
+        let memory = session.cpu.memory();
+        let symbols = session.cpu.symbols();
+
+        let mut address = reference;
+        if instruction_offset < 0 {
+            address = find_preceding_start(
+                memory,
+                reference,
+                instruction_offset.unsigned_abs() as usize,
+            );
+        } else {
+            for _ in 0..instruction_offset {
+                address = disassemble(memory, address).next_address();
+            }
+        }
+
+        let mut instructions = Vec::with_capacity(instruction_count);
+        for _ in 0..instruction_count {
+            let instruction = disassemble(memory, address);
+            let bytes = instruction
+                .bytes
+                .iter()
+                .map(|byte| format!("{byte:02X}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            let mut entry = json!({
+                "address": format!("0x{address:04X}"),
+                "instructionBytes": bytes,
+                "instruction": instruction.text_with_symbols(symbols),
+            });
+            if let Some(name) = symbols.name_for(address) {
+                entry["symbol"] = json!(name);
+            }
+            if let Some(source_map) = &session.source_map {
+                if let Some(line) = source_map.line_for_address(address) {
+                    entry["line"] = json!(self.client_line(line));
+                    entry["location"] = json!({ "path": source_map.path() });
+                }
+            }
+
+            instructions.push(entry);
+            address = instruction.next_address();
+        }
+
+        Ok(json!({ "instructions": instructions }))
+    }
+
+    fn read_memory(&mut self, arguments: &Value) -> HandlerResult {
+        let address = memory_reference(arguments)?;
+        let count = arguments["count"]
+            .as_u64()
+            .unwrap_or(0)
+            .min(0x10000 - u64::from(address)) as usize;
+
+        let session = self.session()?;
+        let bytes: Vec<Byte> = (0..count)
+            .map(|offset| session.cpu.memory().peek(address + offset as Word))
+            .collect();
+
+        Ok(json!({
+            "address": format!("0x{address:04X}"),
+            "data": encode_base64(&bytes),
+        }))
+    }
+
+    fn write_memory(&mut self, arguments: &Value) -> HandlerResult {
+        let address = memory_reference(arguments)?;
+        let bytes = arguments["data"]
+            .as_str()
+            .and_then(decode_base64)
+            .ok_or("data must be base64")?;
+        if address as usize + bytes.len() > 0x10000 {
+            return Err("write past end of address space".to_string());
+        }
+
+        let memory = self.session()?.cpu.memory_mut();
+        for (index, &value) in bytes.iter().enumerate() {
+            memory
+                .poke(address + index as Word, value)
+                .map_err(|error| error_chain(&error))?;
+        }
+        Ok(json!({ "bytesWritten": bytes.len() }))
+    }
+}
+
+impl<W: Write> LoggingHw for DapServer<W> {
+    fn hw_name(&self) -> &'static str {
+        "DAP"
+    }
+}
+
+fn scopes() -> Value {
+    json!({
+        "scopes": [
+            { "name": "Registers", "variablesReference": REGISTERS_REFERENCE, "expensive": false },
+            { "name": "Flags", "variablesReference": FLAGS_REFERENCE, "expensive": false },
+            { "name": "Stack", "variablesReference": STACK_REFERENCE, "expensive": false },
+            { "name": "Zero Page", "variablesReference": ZERO_PAGE_REFERENCE, "expensive": true },
+        ]
+    })
+}
+
+fn register_values(registers: &Registers) -> [(&'static str, u32, usize); 6] {
+    [
+        ("A", registers.acc.into(), 2),
+        ("X", registers.x_reg.into(), 2),
+        ("Y", registers.y_reg.into(), 2),
+        ("SP", registers.stack_ptr.into(), 2),
+        ("P", registers.status.into(), 2),
+        ("PC", registers.program_counter.into(), 4),
+    ]
+}
+
+fn format_value(value: u32, width: usize) -> String {
+    format!("${value:0width$X} ({value})")
+}
+
+fn variable(name: &str, value: &str, memory_address: Option<u32>) -> Value {
+    let mut variable = json!({
+        "name": name,
+        "value": value,
+        "variablesReference": 0,
+    });
+    if let Some(address) = memory_address {
+        variable["memoryReference"] = json!(format!("0x{address:04X}"));
+    }
+
+    variable
+}
+
+// accepts numbers and "$hex"/"0xhex" strings
+fn address_argument(value: &Value) -> Result<Option<Word>, String> {
+    match value {
+        Value::Null => Ok(None),
+        Value::Number(number) => number
+            .as_u64()
+            .and_then(|number| Word::try_from(number).ok())
+            .map(Some)
+            .ok_or(format!("invalid address {number}")),
+        Value::String(text) => parse_word(text)
+            .map(Some)
+            .ok_or(format!("invalid address {text}")),
+        _ => Err(format!("invalid address {value}")),
+    }
+}
+
+fn memory_reference(arguments: &Value) -> Result<Word, String> {
+    let reference = arguments["memoryReference"]
+        .as_str()
+        .and_then(parse_word)
+        .ok_or("invalid memoryReference")?;
+
+    Ok(reference.wrapping_add_signed(arguments["offset"].as_i64().unwrap_or(0) as i16))
+}
+
+// none for lines the client can not express, e.g. line 0 of a listing for a client counting from 0
+fn client_line(line: u32, lines_start_at_1: bool) -> Option<u32> {
+    match lines_start_at_1 {
+        true => Some(line),
+        false => line.checked_sub(1),
+    }
+}
+
+// listing lines count from 1, none for requests outside of them
+fn server_line(line: u64, lines_start_at_1: bool) -> Option<u32> {
+    let line = u32::try_from(line).ok()?;
+    match lines_start_at_1 {
+        true => (line > 0).then_some(line),
+        false => line.checked_add(1),
+    }
+}
+
+fn same_file(left: &str, right: &str) -> bool {
+    match (
+        Path::new(left).canonicalize(),
+        Path::new(right).canonicalize(),
+    ) {
+        (Ok(left), Ok(right)) => left == right,
+        _ => left == right,
+    }
+}
+
+// instruction boundaries before an address are ambiguous, pick the furthest start
+// that decodes forward exactly onto the reference
+fn find_preceding_start(memory: &Memory, reference: Word, instruction_count: usize) -> Word {
+    for back_bytes in (instruction_count..=instruction_count * 3).rev() {
+        let start = reference.wrapping_sub(back_bytes as Word);
+        let mut address = start;
+        let mut decoded = 0;
+
+        while decoded < instruction_count && address != reference {
+            address = disassemble(memory, address).next_address();
+            decoded += 1;
+        }
+
+        if address == reference && decoded == instruction_count {
+            return start;
+        }
+    }
+
+    reference.wrapping_sub(instruction_count as Word)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+
+    // "lda #$42" at $0200 listed on the first line
+    fn program_files(name: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("cpu_emu_dap_{name}_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let program = dir.join("program.bin");
+        let listing = dir.join("program.lst");
+        fs::write(&program, [0xA9, 0x42]).unwrap();
+        fs::write(&listing, "0200  A9 42  lda #$42\n").unwrap();
+        (program, listing)
+    }
+
+    fn launched_server(name: &str, lines_start_at_1: bool) -> (DapServer<Vec<u8>>, PathBuf) {
+        let (program, listing) = program_files(name);
+        let mut server = DapServer::new(Vec::new());
+        server
+            .handle_request("initialize", &json!({ "linesStartAt1": lines_start_at_1 }))
+            .unwrap();
+        server
+            .handle_request(
+                "launch",
+                &json!({ "program": program, "loadAddress": 0x0200, "listing": listing }),
+            )
+            .unwrap();
+        (server, listing)
+    }
+
+    #[test]
+    fn converts_lines_between_client_and_listing() {
+        assert_eq!(server_line(0, false), Some(1));
+        assert_eq!(server_line(0, true), None);
+        assert_eq!(server_line(u64::from(u32::MAX), false), None);
+        assert_eq!(server_line(u64::MAX, true), None);
+        assert_eq!(client_line(1, false), Some(0));
+        assert_eq!(client_line(0, false), None);
+        assert_eq!(client_line(0, true), Some(0));
+    }
+
+    #[test]
+    fn zero_based_client_sets_breakpoint_on_line_0() {
+        let (mut server, listing) = launched_server("zero_based", false);
+
+        let body = server
+            .handle_request(
+                "setBreakpoints",
+                &json!({ "source": { "path": listing }, "breakpoints": [{ "line": 0 }] }),
+            )
+            .unwrap();
+
+        assert_eq!(body["breakpoints"][0]["verified"], json!(true));
+        assert_eq!(body["breakpoints"][0]["line"], json!(0));
+        assert_eq!(
+            body["breakpoints"][0]["instructionReference"],
+            json!("0x0200")
+        );
+    }
+
+    #[test]
+    fn one_based_client_gets_line_0_rejected() {
+        let (mut server, listing) = launched_server("one_based", true);
+
+        let body = server
+            .handle_request(
+                "setBreakpoints",
+                &json!({ "source": { "path": listing }, "breakpoints": [{ "line": 0 }, { "line": 1 }] }),
+            )
+            .unwrap();
+
+        assert_eq!(body["breakpoints"][0]["verified"], json!(false));
+        assert_eq!(body["breakpoints"][0]["message"], json!("invalid line"));
+        assert_eq!(body["breakpoints"][1]["line"], json!(1));
+    }
+
+    #[test]
+    fn launch_rejects_non_positive_speed() {
+        let (program, _) = program_files("speed");
+
+        for speed in [json!(0.0), json!(-1.5), json!("fast")] {
+            let result = DapServer::new(Vec::new()).handle_request(
+                "launch",
+                &json!({ "program": program, "loadAddress": 0x0200, "speed": speed }),
+            );
+
+            assert!(result.is_err_and(|error| error.starts_with("speed must be a positive number")));
+        }
+    }
+}
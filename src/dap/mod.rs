@@ -0,0 +1,3 @@
+pub mod dap_server;
+pub mod protocol;
+pub mod source_map;
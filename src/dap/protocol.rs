@@ -0,0 +1,98 @@
+use std::io::{self, BufRead, Write};
+
+use serde_json::Value;
+
+// "Content-Length: n\r\n\r\n" followed by n bytes of JSON, None on end of input
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(length) = header.strip_prefix("Content-Length:") {
+            content_length = length.trim().parse::<usize>().ok();
+        }
+    }
+
+    let Some(content_length) = content_length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message without Content-Length",
+        ));
+    };
+
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body)?;
+
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+pub fn write_message<W: Write>(writer: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    writer.flush()
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// readMemory/writeMemory carry data as standard padded base64
+pub fn encode_base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let block = chunk.iter().enumerate().fold(0u32, |block, (index, byte)| {
+            block | (u32::from(*byte) << (16 - 8 * index))
+        });
+
+        for index in 0..4 {
+            if index <= chunk.len() {
+                let sextet = (block >> (18 - 6 * index)) & 0x3F;
+                encoded.push(BASE64_ALPHABET[sextet as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+pub fn decode_base64(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(encoded.len() / 4 * 3);
+    let mut block = 0u32;
+    let mut sextets = 0;
+
+    for character in encoded.bytes().filter(|character| *character != b'=') {
+        let sextet = BASE64_ALPHABET
+            .iter()
+            .position(|known| *known == character)? as u32;
+        block = (block << 6) | sextet;
+        sextets += 1;
+
+        if sextets == 4 {
+            bytes.extend_from_slice(&block.to_be_bytes()[1..]);
+            block = 0;
+            sextets = 0;
+        }
+    }
+
+    match sextets {
+        0 => {}
+        2 => bytes.push((block >> 4) as u8),
+        3 => bytes.extend_from_slice(&((block >> 2) as u16).to_be_bytes()),
+        _ => return None,
+    }
+
+    Some(bytes)
+}
@@ -0,0 +1,69 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+};
+
+use crate::shared::types::Word;
+
+// maps assembler listing lines to addresses, every line starting with an
+// absolute address counts: "0200  A9 42  lda #$42", "$0200: ..." or ca65 "000200  1  A9 42 ...",
+// relocatable ca65 lines ("000200r") are skipped since their address is not final
+pub struct SourceMap {
+    path: String,
+    address_by_line: BTreeMap<u32, Word>,
+    line_by_address: HashMap<Word, u32>,
+}
+
+impl SourceMap {
+    pub fn from_listing(path: &str) -> io::Result<Self> {
+        let listing = fs::read_to_string(path)?;
+        let mut source_map = Self {
+            path: path.to_string(),
+            address_by_line: BTreeMap::new(),
+            line_by_address: HashMap::new(),
+        };
+
+        for (index, line) in listing.lines().enumerate() {
+            let Some(address) = parse_listing_address(line) else {
+                continue;
+            };
+
+            let line_number = index as u32 + 1;
+            source_map.address_by_line.insert(line_number, address);
+            // labels and comments share the address of the next instruction, which comes last
+            source_map.line_by_address.insert(address, line_number);
+        }
+
+        Ok(source_map)
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn line_for_address(&self, address: Word) -> Option<u32> {
+        self.line_by_address.get(&address).copied()
+    }
+
+    // first mapped line at or after requested one, editors allow breakpoints on blank lines
+    pub fn address_for_line(&self, line: u32) -> Option<(u32, Word)> {
+        self.address_by_line
+            .range(line..)
+            .next()
+            .map(|(line, address)| (*line, *address))
+    }
+}
+
+fn parse_listing_address(line: &str) -> Option<Word> {
+    let token = line.split_whitespace().next()?;
+    let token = token.strip_prefix('$').unwrap_or(token);
+    let token = token.strip_suffix(':').unwrap_or(token);
+
+    if !matches!(token.len(), 4 | 6) || !token.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    u32::from_str_radix(token, 16)
+        .ok()
+        .and_then(|address| Word::try_from(address).ok())
+}
@@ -0,0 +1,162 @@
+use std::fmt;
+
+use crate::{
+    memory::memory::Memory,
+    shared::types::{Byte, Word},
+    symbols::symbol_table::SymbolTable,
+};
+
+use super::{
+    addressing_mode::AddressingMode,
+    opcode_table::{decode_opcode, OpcodeInfo},
+};
+
+pub struct DisassembledInstruction {
+    pub address: Word,
+    pub bytes: Vec<Byte>,
+    // None for undocumented opcodes, shown as a data byte
+    pub info: Option<OpcodeInfo>,
+}
+
+impl DisassembledInstruction {
+    pub fn length(&self) -> Word {
+        self.bytes.len() as Word
+    }
+
+    pub fn next_address(&self) -> Word {
+        self.address.wrapping_add(self.length())
+    }
+
+    // operand as LE value, 0 for implied/accumulator
+    pub fn operand(&self) -> Word {
+        match self.bytes.as_slice() {
+            [_, low] => Word::from(*low),
+            [_, low, high] => Word::from_le_bytes([*low, *high]),
+            _ => 0,
+        }
+    }
+
+    // branch destination, offset is relative to the next instruction
+    pub fn branch_target(&self) -> Word {
+        let offset = self.operand() as Byte as i8;
+        self.next_address().wrapping_add_signed(i16::from(offset))
+    }
+
+    pub fn operand_text(&self) -> String {
+        self.format_operand(None)
+    }
+
+    pub fn text(&self) -> String {
+        self.format_text(None)
+    }
+
+    // "JSR print_string" instead of "JSR $C123", immediates stay numeric
+    pub fn text_with_symbols(&self, symbols: &SymbolTable) -> String {
+        self.format_text(Some(symbols))
+    }
+
+    // same layout as Display with symbolic operands
+    pub fn line_with_symbols(&self, symbols: &SymbolTable) -> String {
+        self.format_line(Some(symbols))
+    }
+
+    fn format_operand(&self, symbols: Option<&SymbolTable>) -> String {
+        let Some(info) = self.info else {
+            return String::new();
+        };
+        let operand = self.operand();
+
+        let zero_page = || match symbols.and_then(|symbols| symbols.name_for(operand)) {
+            Some(name) => name.to_string(),
+            None => format!("${operand:02X}"),
+        };
+        let absolute = |address: Word| match symbols.and_then(|symbols| symbols.name_for(address)) {
+            Some(name) => name.to_string(),
+            None => format!("${address:04X}"),
+        };
+
+        match info.mode {
+            AddressingMode::Implied => String::new(),
+            AddressingMode::Accumulator => "A".to_string(),
+            AddressingMode::Immediate => format!("#${operand:02X}"),
+            AddressingMode::ZeroPage => zero_page(),
+            AddressingMode::ZeroPageX => format!("{},X", zero_page()),
+            AddressingMode::ZeroPageY => format!("{},Y", zero_page()),
+            AddressingMode::Absolute => absolute(operand),
+            AddressingMode::AbsoluteX => format!("{},X", absolute(operand)),
+            AddressingMode::AbsoluteY => format!("{},Y", absolute(operand)),
+            AddressingMode::Indirect => format!("({})", absolute(operand)),
+            AddressingMode::IndirectX => format!("({},X)", zero_page()),
+            AddressingMode::IndirectY => format!("({}),Y", zero_page()),
+            AddressingMode::Relative => absolute(self.branch_target()),
+        }
+    }
+
+    fn format_text(&self, symbols: Option<&SymbolTable>) -> String {
+        match self.info {
+            Some(info)
+                if info.mode.operand_length() == 0 && info.mode != AddressingMode::Accumulator =>
+            {
+                info.mnemonic.to_string()
+            }
+            Some(info) => format!("{} {}", info.mnemonic, self.format_operand(symbols)),
+            None => format!(".byte ${:02X}", self.bytes[0]),
+        }
+    }
+
+    fn format_line(&self, symbols: Option<&SymbolTable>) -> String {
+        let hex_bytes = self
+            .bytes
+            .iter()
+            .map(|byte| format!("{byte:02X}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        format!(
+            "${:04X}  {:<8}  {}",
+            self.address,
+            hex_bytes,
+            self.format_text(symbols)
+        )
+    }
+}
+
+impl fmt::Display for DisassembledInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format_line(None))
+    }
+}
+
+// reads through peek, so disassembling never disturbs bus state
+pub fn disassemble(memory: &Memory, address: Word) -> DisassembledInstruction {
+    let opcode = memory.peek(address);
+    let info = decode_opcode(opcode);
+    let length = info.map_or(1, |info| info.length());
+
+    let bytes = (0..Word::from(length))
+        .map(|offset| memory.peek(address.wrapping_add(offset)))
+        .collect();
+
+    DisassembledInstruction {
+        address,
+        bytes,
+        info,
+    }
+}
+
+pub fn disassemble_range(
+    memory: &Memory,
+    address: Word,
+    count: usize,
+) -> Vec<DisassembledInstruction> {
+    let mut instructions = Vec::with_capacity(count);
+    let mut current = address;
+
+    for _ in 0..count {
+        let instruction = disassemble(memory, current);
+        current = instruction.next_address();
+        instructions.push(instruction);
+    }
+
+    instructions
+}
@@ -0,0 +1,3 @@
+pub mod addressing_mode;
+pub mod disassembler;
+pub mod opcode_table;
@@ -0,0 +1,176 @@
+use crate::shared::types::Byte;
+
+use super::addressing_mode::AddressingMode;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct OpcodeInfo {
+    pub mnemonic: &'static str,
+    pub mode: AddressingMode,
+}
+
+impl OpcodeInfo {
+    // opcode byte plus operand bytes
+    pub fn length(&self) -> u8 {
+        1 + self.mode.operand_length()
+    }
+}
+
+// documented NMOS 6502 opcodes, undocumented ones decode to None
+pub fn decode_opcode(opcode: Byte) -> Option<OpcodeInfo> {
+    let (mnemonic, mode) = match opcode {
+        0x00 => ("BRK", AddressingMode::Implied),
+        0x01 => ("ORA", AddressingMode::IndirectX),
+        0x05 => ("ORA", AddressingMode::ZeroPage),
+        0x06 => ("ASL", AddressingMode::ZeroPage),
+        0x08 => ("PHP", AddressingMode::Implied),
+        0x09 => ("ORA", AddressingMode::Immediate),
+        0x0A => ("ASL", AddressingMode::Accumulator),
+        0x0D => ("ORA", AddressingMode::Absolute),
+        0x0E => ("ASL", AddressingMode::Absolute),
+        0x10 => ("BPL", AddressingMode::Relative),
+        0x11 => ("ORA", AddressingMode::IndirectY),
+        0x15 => ("ORA", AddressingMode::ZeroPageX),
+        0x16 => ("ASL", AddressingMode::ZeroPageX),
+        0x18 => ("CLC", AddressingMode::Implied),
+        0x19 => ("ORA", AddressingMode::AbsoluteY),
+        0x1D => ("ORA", AddressingMode::AbsoluteX),
+        0x1E => ("ASL", AddressingMode::AbsoluteX),
+        0x20 => ("JSR", AddressingMode::Absolute),
+        0x21 => ("AND", AddressingMode::IndirectX),
+        0x24 => ("BIT", AddressingMode::ZeroPage),
+        0x25 => ("AND", AddressingMode::ZeroPage),
+        0x26 => ("ROL", AddressingMode::ZeroPage),
+        0x28 => ("PLP", AddressingMode::Implied),
+        0x29 => ("AND", AddressingMode::Immediate),
+        0x2A => ("ROL", AddressingMode::Accumulator),
+        0x2C => ("BIT", AddressingMode::Absolute),
+        0x2D => ("AND", AddressingMode::Absolute),
+        0x2E => ("ROL", AddressingMode::Absolute),
+        0x30 => ("BMI", AddressingMode::Relative),
+        0x31 => ("AND", AddressingMode::IndirectY),
+        0x35 => ("AND", AddressingMode::ZeroPageX),
+        0x36 => ("ROL", AddressingMode::ZeroPageX),
+        0x38 => ("SEC", AddressingMode::Implied),
+        0x39 => ("AND", AddressingMode::AbsoluteY),
+        0x3D => ("AND", AddressingMode::AbsoluteX),
+        0x3E => ("ROL", AddressingMode::AbsoluteX),
+        0x40 => ("RTI", AddressingMode::Implied),
+        0x41 => ("EOR", AddressingMode::IndirectX),
+        0x45 => ("EOR", AddressingMode::ZeroPage),
+        0x46 => ("LSR", AddressingMode::ZeroPage),
+        0x48 => ("PHA", AddressingMode::Implied),
+        0x49 => ("EOR", AddressingMode::Immediate),
+        0x4A => ("LSR", AddressingMode::Accumulator),
+        0x4C => ("JMP", AddressingMode::Absolute),
+        0x4D => ("EOR", AddressingMode::Absolute),
+        0x4E => ("LSR", AddressingMode::Absolute),
+        0x50 => ("BVC", AddressingMode::Relative),
+        0x51 => ("EOR", AddressingMode::IndirectY),
+        0x55 => ("EOR", AddressingMode::ZeroPageX),
+        0x56 => ("LSR", AddressingMode::ZeroPageX),
+        0x58 => ("CLI", AddressingMode::Implied),
+        0x59 => ("EOR", AddressingMode::AbsoluteY),
+        0x5D => ("EOR", AddressingMode::AbsoluteX),
+        0x5E => ("LSR", AddressingMode::AbsoluteX),
+        0x60 => ("RTS", AddressingMode::Implied),
+        0x61 => ("ADC", AddressingMode::IndirectX),
+        0x65 => ("ADC", AddressingMode::ZeroPage),
+        0x66 => ("ROR", AddressingMode::ZeroPage),
+        0x68 => ("PLA", AddressingMode::Implied),
+        0x69 => ("ADC", AddressingMode::Immediate),
+        0x6A => ("ROR", AddressingMode::Accumulator),
+        0x6C => ("JMP", AddressingMode::Indirect),
+        0x6D => ("ADC", AddressingMode::Absolute),
+        0x6E => ("ROR", AddressingMode::Absolute),
+        0x70 => ("BVS", AddressingMode::Relative),
+        0x71 => ("ADC", AddressingMode::IndirectY),
+        0x75 => ("ADC", AddressingMode::ZeroPageX),
+        0x76 => ("ROR", AddressingMode::ZeroPageX),
+        0x78 => ("SEI", AddressingMode::Implied),
+        0x79 => ("ADC", AddressingMode::AbsoluteY),
+        0x7D => ("ADC", AddressingMode::AbsoluteX),
+        0x7E => ("ROR", AddressingMode::AbsoluteX),
+        0x81 => ("STA", AddressingMode::IndirectX),
+        0x84 => ("STY", AddressingMode::ZeroPage),
+        0x85 => ("STA", AddressingMode::ZeroPage),
+        0x86 => ("STX", AddressingMode::ZeroPage),
+        0x88 => ("DEY", AddressingMode::Implied),
+        0x8A => ("TXA", AddressingMode::Implied),
+        0x8C => ("STY", AddressingMode::Absolute),
+        0x8D => ("STA", AddressingMode::Absolute),
+        0x8E => ("STX", AddressingMode::Absolute),
+        0x90 => ("BCC", AddressingMode::Relative),
+        0x91 => ("STA", AddressingMode::IndirectY),
+        0x94 => ("STY", AddressingMode::ZeroPageX),
+        0x95 => ("STA", AddressingMode::ZeroPageX),
+        0x96 => ("STX", AddressingMode::ZeroPageY),
+        0x98 => ("TYA", AddressingMode::Implied),
+        0x99 => ("STA", AddressingMode::AbsoluteY),
+        0x9A => ("TXS", AddressingMode::Implied),
+        0x9D => ("STA", AddressingMode::AbsoluteX),
+        0xA0 => ("LDY", AddressingMode::Immediate),
+        0xA1 => ("LDA", AddressingMode::IndirectX),
+        0xA2 => ("LDX", AddressingMode::Immediate),
+        0xA4 => ("LDY", AddressingMode::ZeroPage),
+        0xA5 => ("LDA", AddressingMode::ZeroPage),
+        0xA6 => ("LDX", AddressingMode::ZeroPage),
+        0xA8 => ("TAY", AddressingMode::Implied),
+        0xA9 => ("LDA", AddressingMode::Immediate),
+        0xAA => ("TAX", AddressingMode::Implied),
+        0xAC => ("LDY", AddressingMode::Absolute),
+        0xAD => ("LDA", AddressingMode::Absolute),
+        0xAE => ("LDX", AddressingMode::Absolute),
+        0xB0 => ("BCS", AddressingMode::Relative),
+        0xB1 => ("LDA", AddressingMode::IndirectY),
+        0xB4 => ("LDY", AddressingMode::ZeroPageX),
+        0xB5 => ("LDA", AddressingMode::ZeroPageX),
+        0xB6 => ("LDX", AddressingMode::ZeroPageY),
+        0xB8 => ("CLV", AddressingMode::Implied),
+        0xB9 => ("LDA", AddressingMode::AbsoluteY),
+        0xBA => ("TSX", AddressingMode::Implied),
+        0xBC => ("LDY", AddressingMode::AbsoluteX),
+        0xBD => ("LDA", AddressingMode::AbsoluteX),
+        0xBE => ("LDX", AddressingMode::AbsoluteY),
+        0xC0 => ("CPY", AddressingMode::Immediate),
+        0xC1 => ("CMP", AddressingMode::IndirectX),
+        0xC4 => ("CPY", AddressingMode::ZeroPage),
+        0xC5 => ("CMP", AddressingMode::ZeroPage),
+        0xC6 => ("DEC", AddressingMode::ZeroPage),
+        0xC8 => ("INY", AddressingMode::Implied),
+        0xC9 => ("CMP", AddressingMode::Immediate),
+        0xCA => ("DEX", AddressingMode::Implied),
+        0xCC => ("CPY", AddressingMode::Absolute),
+        0xCD => ("CMP", AddressingMode::Absolute),
+        0xCE => ("DEC", AddressingMode::Absolute),
+        0xD0 => ("BNE", AddressingMode::Relative),
+        0xD1 => ("CMP", AddressingMode::IndirectY),
+        0xD5 => ("CMP", AddressingMode::ZeroPageX),
+        0xD6 => ("DEC", AddressingMode::ZeroPageX),
+        0xD8 => ("CLD", AddressingMode::Implied),
+        0xD9 => ("CMP", AddressingMode::AbsoluteY),
+        0xDD => ("CMP", AddressingMode::AbsoluteX),
+        0xDE => ("DEC", AddressingMode::AbsoluteX),
+        0xE0 => ("CPX", AddressingMode::Immediate),
+        0xE1 => ("SBC", AddressingMode::IndirectX),
+        0xE4 => ("CPX", AddressingMode::ZeroPage),
+        0xE5 => ("SBC", AddressingMode::ZeroPage),
+        0xE6 => ("INC", AddressingMode::ZeroPage),
+        0xE8 => ("INX", AddressingMode::Implied),
+        0xE9 => ("SBC", AddressingMode::Immediate),
+        0xEA => ("NOP", AddressingMode::Implied),
+        0xEC => ("CPX", AddressingMode::Absolute),
+        0xED => ("SBC", AddressingMode::Absolute),
+        0xEE => ("INC", AddressingMode::Absolute),
+        0xF0 => ("BEQ", AddressingMode::Relative),
+        0xF1 => ("SBC", AddressingMode::IndirectY),
+        0xF5 => ("SBC", AddressingMode::ZeroPageX),
+        0xF6 => ("INC", AddressingMode::ZeroPageX),
+        0xF8 => ("SED", AddressingMode::Implied),
+        0xF9 => ("SBC", AddressingMode::AbsoluteY),
+        0xFD => ("SBC", AddressingMode::AbsoluteX),
+        0xFE => ("INC", AddressingMode::AbsoluteX),
+        _ => return None,
+    };
+
+    Some(OpcodeInfo { mnemonic, mode })
+}
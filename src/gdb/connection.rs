@@ -0,0 +1,25 @@
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+// byte stream GDB talks over, non blocking mode is used to poll for Ctrl-C while running
+pub trait GdbConnection: Read + Write {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+}
+
+impl GdbConnection for TcpStream {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        TcpStream::set_nonblocking(self, nonblocking)
+    }
+}
+
+#[cfg(unix)]
+impl GdbConnection for UnixStream {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        UnixStream::set_nonblocking(self, nonblocking)
+    }
+}
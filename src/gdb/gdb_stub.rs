@@ -0,0 +1,537 @@
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind},
+};
+
+use crate::{
+    cpu::{breakpoints::stop_reason::StopReason, cpu::CPU, step_result::StepResult},
+    memory::{bus_access::BusAccessKind, watchpoint::WatchKind},
+    shared::{
+        logger::LoggingHw,
+        types::{Byte, Word},
+    },
+    throttle::throttle::Throttle,
+};
+
+use super::{
+    connection::GdbConnection,
+    packet::{decode_hex, encode_hex, read_packet, write_packet, PacketEvent, INTERRUPT_BYTE},
+};
+
+// run is sliced so Ctrl-C from GDB is noticed while the program runs
+const RUN_SLICE_CYCLES: u64 = 100_000;
+// advertised in qSupported, an m reply is two hex digits per byte
+const PACKET_SIZE: usize = 0x4000;
+const MAX_MEMORY_READ: usize = PACKET_SIZE / 2;
+
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+const SIGBUS: u8 = 10;
+
+// register numbering used by g/G/p/P, 8 bit registers first, PC is 16 bit LE
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.cpu-emu.mos6502">
+    <reg name="a" bitsize="8" regnum="0"/>
+    <reg name="x" bitsize="8" regnum="1"/>
+    <reg name="y" bitsize="8" regnum="2"/>
+    <reg name="sp" bitsize="8" regnum="3"/>
+    <reg name="p" bitsize="8" regnum="4"/>
+    <reg name="pc" bitsize="16" type="code_ptr" regnum="5"/>
+  </feature>
+</target>"#;
+
+// Z packet types, 0/1 are execution breakpoints, 2-4 data watchpoints
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+enum PointKey {
+    Breakpoint(Word),
+    Watchpoint(WatchKind, Word, Word),
+}
+
+pub struct GdbStub {
+    cpu: CPU,
+    // GDB addresses points by location, core by id
+    points: HashMap<PointKey, u32>,
+    no_ack_mode: bool,
+    // continue runs at real-time speed while set
+    throttle: Option<Throttle>,
+}
+
+impl GdbStub {
+    pub fn new(cpu: CPU) -> Self {
+        Self {
+            cpu,
+            points: HashMap::new(),
+            no_ack_mode: false,
+            throttle: None,
+        }
+    }
+
+    pub fn cpu(&self) -> &CPU {
+        &self.cpu
+    }
+
+    pub fn set_throttle(&mut self, throttle: Option<Throttle>) {
+        self.throttle = throttle;
+    }
+
+    // serves single GDB session until detach, kill or disconnect
+    pub fn serve<C: GdbConnection>(&mut self, connection: &mut C) -> io::Result<()> {
+        self.no_ack_mode = false;
+
+        loop {
+            let packet = match read_packet(connection)? {
+                PacketEvent::Packet(packet) => packet,
+                PacketEvent::Corrupted => {
+                    connection.write_all(b"-")?;
+                    continue;
+                }
+                // nothing is running, stop reply tells GDB where we are
+                PacketEvent::Interrupt => {
+                    write_packet(connection, &format!("S{SIGINT:02x}"))?;
+                    continue;
+                }
+                PacketEvent::Disconnected => return Ok(()),
+            };
+
+            if !self.no_ack_mode {
+                connection.write_all(b"+")?;
+            }
+
+            let packet = String::from_utf8_lossy(&packet).into_owned();
+            self.log_debug("serve", &packet);
+
+            let reply = match packet.as_bytes().first() {
+                Some(b'c') => self.resume(connection, false)?,
+                Some(b's') => self.resume(connection, true)?,
+                Some(b'b') => self.reverse(&packet),
+                Some(b'k') => return Ok(()),
+                Some(b'D') => {
+                    write_packet(connection, "OK")?;
+                    return Ok(());
+                }
+                _ => self.handle_query(&packet),
+            };
+
+            write_packet(connection, &reply)?;
+        }
+    }
+
+    // empty packets and non ASCII commands get the unsupported reply
+    fn handle_query(&mut self, packet: &str) -> String {
+        let mut chars = packet.chars();
+        let Some(command) = chars.next() else {
+            return String::new();
+        };
+        let args = chars.as_str();
+
+        match command {
+            '?' => format!("S{SIGTRAP:02x}"),
+            'g' => self.read_registers(),
+            'G' => self.write_registers(args),
+            'p' => self.read_register(args),
+            'P' => self.write_register(args),
+            'm' => self.read_memory(args),
+            'M' => self.write_memory(args),
+            'Z' => self.insert_point(args),
+            'z' => self.remove_point(args),
+            'H' => "OK".to_string(),
+            'T' => "OK".to_string(),
+            'q' | 'Q' | 'v' => self.handle_general_query(packet),
+            // binary write (X) and everything else unsupported, GDB falls back
+            _ => String::new(),
+        }
+    }
+
+    fn handle_general_query(&mut self, packet: &str) -> String {
+        if packet.starts_with("qSupported") {
+            let mut features = format!(
+                "PacketSize={PACKET_SIZE:x};qXfer:features:read+;swbreak+;QStartNoAckMode+"
+            );
+            if self.cpu.rewind_history().is_some() {
+                features.push_str(";ReverseStep+;ReverseContinue+");
+            }
+            return features;
+        }
+
+        if let Some(args) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            return transfer_chunk(TARGET_XML, args);
+        }
+
+        match packet {
+            "QStartNoAckMode" => {
+                self.no_ack_mode = true;
+                "OK".to_string()
+            }
+            "qAttached" => "1".to_string(),
+            "qC" => "QC1".to_string(),
+            "qfThreadInfo" => "m1".to_string(),
+            "qsThreadInfo" => "l".to_string(),
+            _ => String::new(),
+        }
+    }
+
+    fn register_bytes(&self) -> Vec<Byte> {
+        let registers = self.cpu.registers();
+        let [pc_low, pc_high] = registers.program_counter.to_le_bytes();
+
+        vec![
+            registers.acc,
+            registers.x_reg,
+            registers.y_reg,
+            registers.stack_ptr,
+            registers.status,
+            pc_low,
+            pc_high,
+        ]
+    }
+
+    fn apply_register_bytes(&mut self, bytes: &[Byte]) {
+        let mut registers = self.cpu.registers();
+        registers.acc = bytes[0];
+        registers.x_reg = bytes[1];
+        registers.y_reg = bytes[2];
+        registers.stack_ptr = bytes[3];
+        registers.status = bytes[4];
+        registers.program_counter = Word::from_le_bytes([bytes[5], bytes[6]]);
+
+        self.cpu.set_registers(&registers);
+    }
+
+    fn read_registers(&self) -> String {
+        encode_hex(&self.register_bytes())
+    }
+
+    fn write_registers(&mut self, args: &str) -> String {
+        match decode_hex(args) {
+            Some(bytes) if bytes.len() == 7 => {
+                self.apply_register_bytes(&bytes);
+                "OK".to_string()
+            }
+            _ => "E01".to_string(),
+        }
+    }
+
+    fn read_register(&self, args: &str) -> String {
+        let bytes = self.register_bytes();
+        match usize::from_str_radix(args, 16) {
+            Ok(index @ 0..=4) => encode_hex(&bytes[index..=index]),
+            Ok(5) => encode_hex(&bytes[5..7]),
+            _ => "E01".to_string(),
+        }
+    }
+
+    fn write_register(&mut self, args: &str) -> String {
+        let Some((index, value)) = args.split_once('=') else {
+            return "E01".to_string();
+        };
+        let (Ok(index), Some(value)) = (usize::from_str_radix(index, 16), decode_hex(value)) else {
+            return "E01".to_string();
+        };
+
+        let mut bytes = self.register_bytes();
+        match (index, value.as_slice()) {
+            (0..=4, [byte]) => bytes[index] = *byte,
+            (5, [low, high]) => {
+                bytes[5] = *low;
+                bytes[6] = *high;
+            }
+            _ => return "E01".to_string(),
+        }
+
+        self.apply_register_bytes(&bytes);
+        "OK".to_string()
+    }
+
+    // GDB splits reads to fit PacketSize, longer ones are rejected instead of allocated
+    fn read_memory(&self, args: &str) -> String {
+        let Some((address, length)) = parse_address_length(args) else {
+            return "E01".to_string();
+        };
+        if length > MAX_MEMORY_READ {
+            return "E01".to_string();
+        }
+
+        let bytes: Vec<Byte> = (0..length)
+            .map(|offset| self.cpu.memory().peek(address.wrapping_add(offset as Word)))
+            .collect();
+
+        encode_hex(&bytes)
+    }
+
+    fn write_memory(&mut self, args: &str) -> String {
+        let Some((location, data)) = args.split_once(':') else {
+            return "E01".to_string();
+        };
+        let (Some((address, length)), Some(bytes)) =
+            (parse_address_length(location), decode_hex(data))
+        else {
+            return "E01".to_string();
+        };
+        if bytes.len() != length || address as usize + length > 0x10000 {
+            return "E01".to_string();
+        }
+
+        // program loading writes into ROM too
+        for (index, &value) in bytes.iter().enumerate() {
+            if let Err(error) = self.cpu.memory_mut().poke(address + index as Word, value) {
+                self.log_error_source("write_memory", &error);
+                return "E02".to_string();
+            }
+        }
+        "OK".to_string()
+    }
+
+    fn parse_point(args: &str) -> Option<PointKey> {
+        let mut fields = args.split([',', ';']);
+        let kind = fields.next()?;
+        let address = Word::from_str_radix(fields.next()?, 16).ok()?;
+        let length = u32::from_str_radix(fields.next()?, 16).ok()?.max(1);
+        let end = address.saturating_add((length - 1).min(0xFFFF) as Word);
+
+        match kind {
+            "0" | "1" => Some(PointKey::Breakpoint(address)),
+            "2" => Some(PointKey::Watchpoint(WatchKind::Write, address, end)),
+            "3" => Some(PointKey::Watchpoint(WatchKind::Read, address, end)),
+            "4" => Some(PointKey::Watchpoint(WatchKind::Access, address, end)),
+            _ => None,
+        }
+    }
+
+    fn insert_point(&mut self, args: &str) -> String {
+        let Some(key) = Self::parse_point(args) else {
+            return String::new();
+        };
+        if self.points.contains_key(&key) {
+            return "OK".to_string();
+        }
+
+        let id = match key {
+            PointKey::Breakpoint(address) => self.cpu.breakpoints_mut().add(Some(address), None),
+            PointKey::Watchpoint(kind, start, end) => {
+                self.cpu.memory_mut().add_watchpoint(start..=end, kind)
+            }
+        };
+        self.points.insert(key, id);
+
+        "OK".to_string()
+    }
+
+    fn remove_point(&mut self, args: &str) -> String {
+        let Some(key) = Self::parse_point(args) else {
+            return String::new();
+        };
+
+        if let Some(id) = self.points.remove(&key) {
+            match key {
+                PointKey::Breakpoint(_) => self.cpu.breakpoints_mut().remove(id),
+                PointKey::Watchpoint(..) => self.cpu.memory_mut().remove_watchpoint(id),
+            };
+        }
+
+        "OK".to_string()
+    }
+
+    // continue or single step, returns stop reply
+    fn resume<C: GdbConnection>(
+        &mut self,
+        connection: &mut C,
+        single_step: bool,
+    ) -> io::Result<String> {
+        if single_step {
+            self.cpu.memory_mut().take_watchpoint_hit();
+            let reply = match self.cpu.step() {
+                StepResult::UnimplementedOpcode { .. } => format!("S{SIGILL:02x}"),
+                StepResult::BusError(_) => format!("S{SIGBUS:02x}"),
+                StepResult::Executed(_) | StepResult::Trapped(_) => {
+                    match self.cpu.memory_mut().take_watchpoint_hit() {
+                        Some(hit) => {
+                            watch_stop_reply(hit.access, hit.id, hit.address, &self.points)
+                        }
+                        None => format!("S{SIGTRAP:02x}"),
+                    }
+                }
+            };
+            return Ok(reply);
+        }
+
+        connection.set_nonblocking(true)?;
+        if let Some(throttle) = self.throttle.as_mut() {
+            throttle.resync(self.cpu.cycles());
+        }
+        let reply = loop {
+            let slice_cycles = self
+                .throttle
+                .as_ref()
+                .map_or(RUN_SLICE_CYCLES, Throttle::batch_cycles);
+            let stop_reason = self.cpu.run(Some(slice_cycles));
+            if let Some(throttle) = self.throttle.as_mut() {
+                throttle.pace(self.cpu.cycles());
+            }
+
+            match stop_reason {
+                StopReason::CycleLimit => {}
+                StopReason::Breakpoint { .. } => break format!("T{SIGTRAP:02x}swbreak:;"),
+                StopReason::Watchpoint(hit) => {
+                    break watch_stop_reply(hit.access, hit.id, hit.address, &self.points)
+                }
+                // program parked itself, nothing left to run
+                StopReason::Trapped(_) => break format!("S{SIGTRAP:02x}"),
+                StopReason::UnimplementedOpcode { .. } => break format!("S{SIGILL:02x}"),
+                StopReason::BusError(_) => break format!("S{SIGBUS:02x}"),
+                StopReason::HistoryExhausted => break format!("S{SIGTRAP:02x}"),
+            }
+
+            if poll_interrupt(connection)? {
+                break format!("S{SIGINT:02x}");
+            }
+        };
+        connection.set_nonblocking(false)?;
+
+        Ok(reply)
+    }
+
+    // bs / bc, only offered in qSupported while rewind is recording
+    fn reverse(&mut self, packet: &str) -> String {
+        if self.cpu.rewind_history().is_none() {
+            return "E01".to_string();
+        }
+
+        let stop_reason = match packet {
+            "bs" => match self.cpu.step_back() {
+                Ok(true) => return format!("S{SIGTRAP:02x}"),
+                Ok(false) => Ok(StopReason::HistoryExhausted),
+                Err(error) => Err(error),
+            },
+            "bc" => self.cpu.reverse_continue(),
+            _ => return String::new(),
+        };
+        let stop_reason = match stop_reason {
+            Ok(stop_reason) => stop_reason,
+            Err(error) => {
+                self.log_error_source("reverse", &error);
+                return "E03".to_string();
+            }
+        };
+
+        match stop_reason {
+            StopReason::Breakpoint { .. } => format!("T{SIGTRAP:02x}swbreak:;"),
+            StopReason::Watchpoint(hit) => {
+                watch_stop_reply(hit.access, hit.id, hit.address, &self.points)
+            }
+            StopReason::HistoryExhausted => format!("T{SIGTRAP:02x}replaylog:begin;"),
+            _ => format!("S{SIGTRAP:02x}"),
+        }
+    }
+}
+
+impl LoggingHw for GdbStub {
+    fn hw_name(&self) -> &'static str {
+        "GDB"
+    }
+}
+
+fn poll_interrupt<C: GdbConnection>(connection: &mut C) -> io::Result<bool> {
+    let mut buffer = [0u8; 64];
+    match connection.read(&mut buffer) {
+        // closed connection ends the run too
+        Ok(0) => Ok(true),
+        Ok(count) => Ok(buffer[..count].contains(&INTERRUPT_BYTE)),
+        Err(error) if error.kind() == ErrorKind::WouldBlock => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+fn watch_stop_reply(
+    access: BusAccessKind,
+    id: u32,
+    address: Word,
+    points: &HashMap<PointKey, u32>,
+) -> String {
+    let watch_kind = points.iter().find_map(|(key, point_id)| match key {
+        PointKey::Watchpoint(kind, ..) if *point_id == id => Some(*kind),
+        _ => None,
+    });
+
+    let reason = match (watch_kind, access) {
+        (Some(WatchKind::Access), _) => "awatch",
+        (_, BusAccessKind::Read) => "rwatch",
+        (_, BusAccessKind::Write) => "watch",
+    };
+
+    format!("T{SIGTRAP:02x}{reason}:{address:x};")
+}
+
+// "addr,length" in hex
+fn parse_address_length(args: &str) -> Option<(Word, usize)> {
+    let (address, length) = args.split_once(',')?;
+
+    Some((
+        Word::from_str_radix(address, 16).ok()?,
+        usize::from_str_radix(length, 16).ok()?,
+    ))
+}
+
+// qXfer "offset,length" window over annex, 'l' marks the last chunk
+fn transfer_chunk(annex: &str, args: &str) -> String {
+    let Some((offset, length)) = args.split_once(',').and_then(|(offset, length)| {
+        Some((
+            usize::from_str_radix(offset, 16).ok()?,
+            usize::from_str_radix(length, 16).ok()?,
+        ))
+    }) else {
+        return "E01".to_string();
+    };
+
+    let start = offset.min(annex.len());
+    let end = offset.saturating_add(length).min(annex.len());
+    let marker = if end == annex.len() { 'l' } else { 'm' };
+
+    format!("{marker}{}", &annex[start..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stub() -> GdbStub {
+        let mut cpu = CPU::new();
+        cpu.memory_mut().load(0x0200, &[0xA9, 0x42]);
+        GdbStub::new(cpu)
+    }
+
+    #[test]
+    fn empty_and_non_ascii_packets_are_unsupported() {
+        let mut stub = stub();
+
+        assert_eq!(stub.handle_query(""), "");
+        assert_eq!(stub.handle_query(&String::from_utf8_lossy(b"\xFFm0,1")), "");
+        assert_eq!(stub.handle_query("\u{e9}"), "");
+    }
+
+    #[test]
+    fn reads_memory_up_to_what_a_reply_fits() {
+        let mut stub = stub();
+
+        assert_eq!(stub.handle_query("m200,2"), "a942");
+        assert_eq!(
+            stub.handle_query(&format!("m0,{MAX_MEMORY_READ:x}")).len(),
+            PACKET_SIZE
+        );
+        assert_eq!(stub.handle_query("m0,ffffffff"), "E01");
+        assert_eq!(stub.handle_query("m0,ffffffffffffffffff"), "E01");
+    }
+
+    #[test]
+    fn transfer_chunk_clamps_the_window() {
+        assert_eq!(transfer_chunk("target", "0,3"), "mtar");
+        assert_eq!(transfer_chunk("target", "3,100"), "lget");
+        assert_eq!(
+            transfer_chunk("target", "ffffffffffffffff,ffffffffffffffff"),
+            "l"
+        );
+        assert_eq!(transfer_chunk("target", "0"), "E01");
+    }
+}
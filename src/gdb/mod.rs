@@ -0,0 +1,3 @@
+pub mod connection;
+pub mod gdb_stub;
+pub mod packet;
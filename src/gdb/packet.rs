@@ -0,0 +1,106 @@
+use std::io::{self, Read, Write};
+
+pub const INTERRUPT_BYTE: u8 = 0x03;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PacketEvent {
+    Packet(Vec<u8>),
+    // Ctrl-C sent out of band
+    Interrupt,
+    // checksum mismatch, peer should retransmit after a NAK
+    Corrupted,
+    // peer closed connection
+    Disconnected,
+}
+
+fn read_one<R: Read>(reader: &mut R) -> io::Result<Option<u8>> {
+    let mut byte = [0u8; 1];
+    match reader.read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+// $<data>#<checksum>, acks and stray bytes before '$' are skipped
+pub fn read_packet<R: Read>(reader: &mut R) -> io::Result<PacketEvent> {
+    loop {
+        match read_one(reader)? {
+            None => return Ok(PacketEvent::Disconnected),
+            Some(INTERRUPT_BYTE) => return Ok(PacketEvent::Interrupt),
+            Some(b'$') => break,
+            Some(_) => {}
+        }
+    }
+
+    // checksum covers bytes as sent, before unescaping
+    let mut data = Vec::new();
+    let mut expected_checksum = 0u8;
+    loop {
+        let Some(byte) = read_one(reader)? else {
+            return Ok(PacketEvent::Disconnected);
+        };
+        if byte == b'#' {
+            break;
+        }
+        expected_checksum = expected_checksum.wrapping_add(byte);
+
+        // escaped byte follows, original is xor 0x20
+        if byte == b'}' {
+            let Some(escaped) = read_one(reader)? else {
+                return Ok(PacketEvent::Disconnected);
+            };
+            expected_checksum = expected_checksum.wrapping_add(escaped);
+            data.push(escaped ^ 0x20);
+        } else {
+            data.push(byte);
+        }
+    }
+
+    let mut received_checksum = [0u8; 2];
+    reader.read_exact(&mut received_checksum)?;
+    let received_checksum = std::str::from_utf8(&received_checksum)
+        .ok()
+        .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+    if received_checksum != Some(expected_checksum) {
+        return Ok(PacketEvent::Corrupted);
+    }
+
+    Ok(PacketEvent::Packet(data))
+}
+
+pub fn write_packet<W: Write>(writer: &mut W, data: &str) -> io::Result<()> {
+    let mut escaped = Vec::with_capacity(data.len() + 4);
+    for &byte in data.as_bytes() {
+        if matches!(byte, b'$' | b'#' | b'}' | b'*') {
+            escaped.push(b'}');
+            escaped.push(byte ^ 0x20);
+        } else {
+            escaped.push(byte);
+        }
+    }
+
+    writer.write_all(b"$")?;
+    writer.write_all(&escaped)?;
+    write!(writer, "#{:02x}", checksum(&escaped))?;
+    writer.flush()
+}
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+pub fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,493 @@
+use std::{fmt, ops::RangeInclusive};
+
+use serde_json::{json, Value};
+
+use crate::{
+    cpu::{
+        cpu::CPU, cpu_error::CpuError, instruction_set::opcodes::BRK_OPCODE, registers::Registers,
+        step_result::StepResult,
+    },
+    memory::watchpoint::WatchKind,
+    shared::{
+        error_chain::error_chain,
+        types::{Byte, Word},
+    },
+};
+
+// stops runaway programs when no limit was given, about a minute of 1 MHz time
+pub const DEFAULT_BATCH_MAX_CYCLES: u64 = 60_000_000;
+
+// process exit codes owned by the emulator, 2 is a usage error, a magic write exits
+// with the written byte unless it falls in this range
+pub const RESERVED_EXIT_CODES: RangeInclusive<u8> = 2..=15;
+pub const EXIT_TIMEOUT: u8 = 3;
+pub const EXIT_UNIMPLEMENTED_OPCODE: u8 = 4;
+pub const EXIT_TRAPPED: u8 = 5;
+pub const EXIT_BUS_ERROR: u8 = 6;
+pub const EXIT_RESERVED_MAGIC_VALUE: u8 = 7;
+
+const DUMP_ROW_LENGTH: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchConfig {
+    pub max_cycles: Option<u64>,
+    pub max_instructions: Option<u64>,
+    // checked before every instruction, the first one included
+    pub exit_addresses: Vec<Word>,
+    // stop when the next instruction is BRK, it is not executed
+    pub exit_on_brk: bool,
+    // a write here ends the run, the value becomes the exit code
+    pub magic_address: Option<Word>,
+    // (start, length) ranges copied into the report
+    pub dumps: Vec<(Word, Word)>,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_cycles: Some(DEFAULT_BATCH_MAX_CYCLES),
+            max_instructions: None,
+            exit_addresses: Vec::new(),
+            exit_on_brk: false,
+            magic_address: None,
+            dumps: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum BatchOutcome {
+    ExitAddress(Word),
+    Brk(Word),
+    MagicWrite { address: Word, value: Byte },
+    Trapped(Word),
+    UnimplementedOpcode { opcode: Byte, address: Word },
+    BusError(CpuError),
+    CycleLimit,
+    InstructionLimit,
+}
+
+impl BatchOutcome {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            BatchOutcome::ExitAddress(_) | BatchOutcome::Brk(_) => 0,
+            BatchOutcome::MagicWrite { value, .. } if RESERVED_EXIT_CODES.contains(value) => {
+                EXIT_RESERVED_MAGIC_VALUE
+            }
+            BatchOutcome::MagicWrite { value, .. } => *value,
+            BatchOutcome::Trapped(_) => EXIT_TRAPPED,
+            BatchOutcome::UnimplementedOpcode { .. } => EXIT_UNIMPLEMENTED_OPCODE,
+            BatchOutcome::BusError(_) => EXIT_BUS_ERROR,
+            BatchOutcome::CycleLimit | BatchOutcome::InstructionLimit => EXIT_TIMEOUT,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            BatchOutcome::ExitAddress(_) => "exit_address",
+            BatchOutcome::Brk(_) => "brk",
+            BatchOutcome::MagicWrite { .. } => "magic_write",
+            BatchOutcome::Trapped(_) => "trapped",
+            BatchOutcome::UnimplementedOpcode { .. } => "unimplemented_opcode",
+            BatchOutcome::BusError(_) => "bus_error",
+            BatchOutcome::CycleLimit => "cycle_limit",
+            BatchOutcome::InstructionLimit => "instruction_limit",
+        }
+    }
+}
+
+impl fmt::Display for BatchOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchOutcome::ExitAddress(address) => write!(f, "reached exit address ${address:04X}"),
+            BatchOutcome::Brk(address) => write!(f, "BRK at ${address:04X}"),
+            BatchOutcome::MagicWrite { address, value } if RESERVED_EXIT_CODES.contains(value) => {
+                write!(
+                    f,
+                    "wrote ${value:02X} to ${address:04X}, value is a reserved exit code"
+                )
+            }
+            BatchOutcome::MagicWrite { address, value } => {
+                write!(f, "wrote ${value:02X} to ${address:04X}")
+            }
+            BatchOutcome::Trapped(address) => write!(f, "trapped at ${address:04X}"),
+            BatchOutcome::UnimplementedOpcode { opcode, address } => {
+                write!(f, "unimplemented opcode ${opcode:02X} at ${address:04X}")
+            }
+            BatchOutcome::BusError(error) => write!(f, "{}", error_chain(error)),
+            BatchOutcome::CycleLimit => write!(f, "cycle limit reached"),
+            BatchOutcome::InstructionLimit => write!(f, "instruction limit reached"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BatchReport {
+    pub outcome: BatchOutcome,
+    pub registers: Registers,
+    pub cycles: u64,
+    pub instructions: u64,
+    pub memory: Vec<(Word, Vec<Byte>)>,
+}
+
+impl BatchReport {
+    pub fn exit_code(&self) -> u8 {
+        self.outcome.exit_code()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let registers = &self.registers;
+
+        json!({
+            "outcome": self.outcome.name(),
+            "message": self.outcome.to_string(),
+            "error": match &self.outcome {
+                BatchOutcome::BusError(error) => error.to_json(),
+                _ => Value::Null,
+            },
+            "exitCode": self.exit_code(),
+            "cycles": self.cycles,
+            "instructions": self.instructions,
+            "registers": {
+                "a": registers.acc,
+                "x": registers.x_reg,
+                "y": registers.y_reg,
+                "sp": registers.stack_ptr,
+                "pc": registers.program_counter,
+                "p": registers.status,
+            },
+            "memory": self
+                .memory
+                .iter()
+                .map(|(address, bytes)| json!({ "address": address, "bytes": bytes }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} after {} cycles, {} instructions (exit code {})",
+            self.outcome,
+            self.cycles,
+            self.instructions,
+            self.exit_code()
+        )?;
+        writeln!(f, "{}", self.registers)?;
+
+        for (address, bytes) in &self.memory {
+            for (row, chunk) in bytes.chunks(DUMP_ROW_LENGTH).enumerate() {
+                let hex = chunk
+                    .iter()
+                    .map(|byte| format!("{byte:02X}"))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let row_address = address.wrapping_add((row * DUMP_ROW_LENGTH) as Word);
+                writeln!(f, "${row_address:04X}: {hex}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// runs a loaded CPU until an exit condition or limit, for unit tests of 6502 code in CI
+pub fn run_batch(cpu: &mut CPU, config: &BatchConfig) -> BatchReport {
+    let magic_watchpoint = config.magic_address.map(|address| {
+        cpu.memory_mut()
+            .add_watchpoint(address..=address, WatchKind::Write)
+    });
+    cpu.memory_mut().take_watchpoint_hit();
+
+    let cycle_limit = config
+        .max_cycles
+        .map(|max_cycles| cpu.cycles().saturating_add(max_cycles));
+    let mut instructions = 0;
+
+    let outcome = loop {
+        let pc = cpu.program_counter();
+        if config.exit_addresses.contains(&pc) {
+            break BatchOutcome::ExitAddress(pc);
+        }
+        if config.exit_on_brk && cpu.memory().peek(pc) == BRK_OPCODE {
+            break BatchOutcome::Brk(pc);
+        }
+        if cycle_limit.is_some_and(|cycle_limit| cpu.cycles() >= cycle_limit) {
+            break BatchOutcome::CycleLimit;
+        }
+        if config
+            .max_instructions
+            .is_some_and(|max_instructions| instructions >= max_instructions)
+        {
+            break BatchOutcome::InstructionLimit;
+        }
+
+        let step_result = cpu.step();
+        instructions += 1;
+
+        if let Some(hit) = cpu.memory_mut().take_watchpoint_hit() {
+            if Some(hit.id) == magic_watchpoint {
+                break BatchOutcome::MagicWrite {
+                    address: hit.address,
+                    value: hit.value,
+                };
+            }
+        }
+
+        match step_result {
+            StepResult::Executed(_) => {}
+            StepResult::Trapped(address) => break BatchOutcome::Trapped(address),
+            StepResult::UnimplementedOpcode { opcode, address } => {
+                instructions -= 1;
+                break BatchOutcome::UnimplementedOpcode { opcode, address };
+            }
+            StepResult::BusError(error) => break BatchOutcome::BusError(error),
+        }
+    };
+
+    if let Some(id) = magic_watchpoint {
+        cpu.memory_mut().remove_watchpoint(id);
+    }
+
+    BatchReport {
+        outcome,
+        registers: cpu.registers(),
+        cycles: cpu.cycles(),
+        instructions,
+        memory: config
+            .dumps
+            .iter()
+            .map(|&(address, length)| {
+                let bytes = (0..length)
+                    .map(|offset| cpu.memory().peek(address.wrapping_add(offset)))
+                    .collect();
+                (address, bytes)
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        memory::{device::Device, device_error::DeviceError},
+        shared::constants::NMI_VECTOR,
+    };
+
+    use super::*;
+
+    // LDA #$01, LDA #$02, LDA #$03, then BRK (unimplemented) at $0206
+    const PROGRAM: [Byte; 6] = [0xA9, 0x01, 0xA9, 0x02, 0xA9, 0x03];
+
+    // every read fails, LDA from it is a bus error
+    struct Broken;
+
+    impl Device for Broken {
+        fn name(&self) -> &'static str {
+            "broken"
+        }
+
+        fn read(&mut self, offset: Word) -> Result<Byte, DeviceError> {
+            Err(DeviceError::UnmappedRegister {
+                device: self.name(),
+                offset,
+            })
+        }
+
+        fn write(&mut self, offset: Word, _value: Byte) -> Result<(), DeviceError> {
+            Err(DeviceError::ReadOnlyRegister {
+                device: self.name(),
+                offset,
+            })
+        }
+
+        fn peek(&self, _offset: Word) -> Byte {
+            0x00
+        }
+    }
+
+    fn cpu_at(address: Word, program: &[Byte]) -> CPU {
+        let mut cpu = CPU::new();
+        cpu.memory_mut().load(address, program);
+        cpu.set_registers(&Registers {
+            stack_ptr: 0xFF,
+            program_counter: address,
+            ..Registers::default()
+        });
+        cpu
+    }
+
+    #[test]
+    fn stops_at_exit_address_before_executing_it() {
+        let mut cpu = cpu_at(0x0200, &PROGRAM);
+
+        let report = run_batch(
+            &mut cpu,
+            &BatchConfig {
+                exit_addresses: vec![0x0204],
+                ..BatchConfig::default()
+            },
+        );
+
+        assert!(matches!(report.outcome, BatchOutcome::ExitAddress(0x0204)));
+        assert_eq!(report.instructions, 2);
+        assert_eq!(report.registers.acc, 0x02);
+        assert_eq!(report.exit_code(), 0);
+    }
+
+    #[test]
+    fn stops_on_brk_when_asked_and_fails_on_it_otherwise() {
+        let mut cpu = cpu_at(0x0200, &PROGRAM);
+        let report = run_batch(
+            &mut cpu,
+            &BatchConfig {
+                exit_on_brk: true,
+                ..BatchConfig::default()
+            },
+        );
+        assert!(matches!(report.outcome, BatchOutcome::Brk(0x0206)));
+        assert_eq!(report.exit_code(), 0);
+
+        let mut cpu = cpu_at(0x0200, &PROGRAM);
+        let report = run_batch(&mut cpu, &BatchConfig::default());
+        assert!(matches!(
+            report.outcome,
+            BatchOutcome::UnimplementedOpcode {
+                opcode: BRK_OPCODE,
+                address: 0x0206
+            }
+        ));
+        assert_eq!(report.instructions, 3);
+        assert_eq!(report.exit_code(), EXIT_UNIMPLEMENTED_OPCODE);
+    }
+
+    #[test]
+    fn limits_end_the_run_with_timeout() {
+        let mut cpu = cpu_at(0x0200, &PROGRAM);
+        let report = run_batch(
+            &mut cpu,
+            &BatchConfig {
+                max_cycles: Some(3),
+                ..BatchConfig::default()
+            },
+        );
+        assert!(matches!(report.outcome, BatchOutcome::CycleLimit));
+        assert_eq!(report.cycles, 4);
+        assert_eq!(report.exit_code(), EXIT_TIMEOUT);
+
+        let mut cpu = cpu_at(0x0200, &PROGRAM);
+        let report = run_batch(
+            &mut cpu,
+            &BatchConfig {
+                max_instructions: Some(1),
+                ..BatchConfig::default()
+            },
+        );
+        assert!(matches!(report.outcome, BatchOutcome::InstructionLimit));
+        assert_eq!(report.instructions, 1);
+        assert_eq!(report.exit_code(), EXIT_TIMEOUT);
+    }
+
+    // the NMI pushes PC high then low, a push onto the magic address ends the run
+    fn magic_run(program_counter: Word, magic_address: Word) -> BatchReport {
+        let mut cpu = cpu_at(program_counter, &[0xA9, 0x01]);
+        cpu.memory_mut().load(NMI_VECTOR, &[0x00, 0x03]);
+        cpu.trigger_nmi();
+
+        run_batch(
+            &mut cpu,
+            &BatchConfig {
+                magic_address: Some(magic_address),
+                ..BatchConfig::default()
+            },
+        )
+    }
+
+    #[test]
+    fn magic_write_value_becomes_the_exit_code() {
+        let report = magic_run(0x0241, 0x01FE);
+
+        assert!(matches!(
+            report.outcome,
+            BatchOutcome::MagicWrite {
+                address: 0x01FE,
+                value: 0x41
+            }
+        ));
+        assert_eq!(report.exit_code(), 0x41);
+    }
+
+    #[test]
+    fn magic_write_of_a_reserved_code_exits_with_7() {
+        let report = magic_run(0x0241, 0x01FF);
+
+        assert!(matches!(
+            report.outcome,
+            BatchOutcome::MagicWrite { value: 0x02, .. }
+        ));
+        assert_eq!(report.exit_code(), EXIT_RESERVED_MAGIC_VALUE);
+        assert!(report.outcome.to_string().contains("reserved exit code"));
+        assert!(report.to_json()["outcome"] == "magic_write");
+    }
+
+    #[test]
+    fn bus_errors_are_reported_with_their_cause() {
+        let mut cpu = cpu_at(0x0200, &[0xAD, 0x00, 0xD0]);
+        cpu.memory_mut().map_device(0xD0, 1, Broken).unwrap();
+
+        let report = run_batch(&mut cpu, &BatchConfig::default());
+
+        assert!(matches!(report.outcome, BatchOutcome::BusError(_)));
+        assert_eq!(report.exit_code(), EXIT_BUS_ERROR);
+        let json = report.to_json();
+        assert_eq!(json["outcome"], "bus_error");
+        assert_eq!(json["error"]["error"], "memory");
+    }
+
+    #[test]
+    fn exit_codes_stay_clear_of_the_reserved_range_only_for_magic_values() {
+        let magic = |value| BatchOutcome::MagicWrite {
+            address: 0x6000,
+            value,
+        };
+
+        assert_eq!(magic(0x00).exit_code(), 0);
+        assert_eq!(magic(0x01).exit_code(), 1);
+        assert_eq!(magic(0x02).exit_code(), EXIT_RESERVED_MAGIC_VALUE);
+        assert_eq!(magic(0x0F).exit_code(), EXIT_RESERVED_MAGIC_VALUE);
+        assert_eq!(magic(0x10).exit_code(), 0x10);
+        assert_eq!(magic(0xFF).exit_code(), 0xFF);
+        assert_eq!(BatchOutcome::Trapped(0x0200).exit_code(), EXIT_TRAPPED);
+        for code in [
+            EXIT_TIMEOUT,
+            EXIT_UNIMPLEMENTED_OPCODE,
+            EXIT_TRAPPED,
+            EXIT_BUS_ERROR,
+            EXIT_RESERVED_MAGIC_VALUE,
+        ] {
+            assert!(RESERVED_EXIT_CODES.contains(&code));
+        }
+    }
+
+    #[test]
+    fn report_carries_memory_dumps() {
+        let mut cpu = cpu_at(0x0200, &PROGRAM);
+
+        let report = run_batch(
+            &mut cpu,
+            &BatchConfig {
+                max_instructions: Some(0),
+                dumps: vec![(0x0200, 18)],
+                ..BatchConfig::default()
+            },
+        );
+
+        assert_eq!(report.memory[0].1[..2], [0xA9, 0x01]);
+        let text = report.to_string();
+        assert!(text.contains("$0200: A9 01 A9 02 A9 03 00"));
+        assert!(text.contains("$0210: 00 00\n"));
+        assert_eq!(report.to_json()["memory"][0]["address"], 0x0200);
+    }
+}
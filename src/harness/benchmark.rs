@@ -0,0 +1,292 @@
+use std::{
+    fmt, fs, io,
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    cpu::{
+        breakpoints::stop_reason::StopReason, cpu::CPU, cpu_error::CpuError, registers::Registers,
+    },
+    memory::{memory::Memory, page_table::MEMORY_SIZE},
+    shared::{
+        constants::NMI_VECTOR,
+        types::{Byte, Word},
+    },
+};
+
+pub const DEFAULT_BENCHMARK_INSTRUCTIONS: u64 = 10_000_000;
+
+// workloads loop over this region, the runner jumps back to the start when PC leaves it,
+// page 0 and the stack page stay free for pointers and interrupt frames
+const CODE_START: Word = 0x0200;
+const CODE_END: Word = 0x8000;
+// target of absolute reads, above the code
+const DATA_START: Word = 0x8000;
+const NMI_INTERVAL: u64 = 16;
+const LDA_IMMEDIATE_CYCLES: u64 = 2;
+
+// only LDA is implemented so every workload is built from its addressing modes,
+// memory copy is represented by its read half until stores exist
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Workload {
+    // LDA #imm back to back, pure dispatch cost
+    TightLoop,
+    // LDA abs,X walking the upper half of the address space
+    MemoryScan,
+    // LDA (zp),Y through a table of pointers, some crossing pages
+    LdaIndirectY,
+    // tight loop with an NMI every 16 instructions
+    InterruptHeavy,
+    // LDA #imm filling the whole address space, driven by run() with PC wrapping at $FFFF
+    RunLoop,
+}
+
+impl Workload {
+    pub const ALL: [Workload; 5] = [
+        Workload::TightLoop,
+        Workload::MemoryScan,
+        Workload::LdaIndirectY,
+        Workload::InterruptHeavy,
+        Workload::RunLoop,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Workload::TightLoop => "tight_loop",
+            Workload::MemoryScan => "memory_scan",
+            Workload::LdaIndirectY => "lda_indirect_y",
+            Workload::InterruptHeavy => "interrupt_heavy",
+            Workload::RunLoop => "run_loop",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|workload| workload.name() == name)
+    }
+
+    fn nmi_interval(&self) -> Option<u64> {
+        match self {
+            Workload::InterruptHeavy => Some(NMI_INTERVAL),
+            _ => None,
+        }
+    }
+
+    fn code(&self) -> Vec<Byte> {
+        let code_length = (CODE_END - CODE_START) as usize;
+
+        match self {
+            Workload::TightLoop | Workload::InterruptHeavy => [0xA9, 0x00].repeat(code_length / 2),
+            Workload::RunLoop => [0xA9, 0x00].repeat(MEMORY_SIZE / 2),
+            Workload::MemoryScan => (0..code_length / 3)
+                .flat_map(|index| {
+                    let [low, high] = (DATA_START | ((index * 16) as Word & 0x7FFF)).to_le_bytes();
+                    [0xBD, low, high]
+                })
+                .collect(),
+            Workload::LdaIndirectY => (0..code_length / 2)
+                .flat_map(|index| [0xB1, (index as Byte).wrapping_mul(2)])
+                .collect(),
+        }
+    }
+
+    // CPU positioned at the start of the workload
+    pub fn build_cpu(&self) -> CPU {
+        let mut memory = Memory::new();
+        if *self == Workload::RunLoop {
+            memory.load(0x0000, &self.code());
+            let mut cpu = CPU::with_memory(memory);
+            cpu.set_program_counter(0x0000);
+            return cpu;
+        }
+
+        memory.load(CODE_START, &self.code());
+
+        // 128 zero page pointers into the data area, low bytes spread so Y=$80 crosses pages
+        let pointers: Vec<Byte> = (0..128u16)
+            .flat_map(|index| (DATA_START + index * 0x100 + (index * 37) % 0x100).to_le_bytes())
+            .collect();
+        memory.load(0x0000, &pointers);
+        memory.load(NMI_VECTOR, &CODE_START.to_le_bytes());
+
+        let mut cpu = CPU::with_memory(memory);
+        cpu.set_registers(&Registers {
+            x_reg: 0x0F,
+            y_reg: 0x80,
+            stack_ptr: 0xFF,
+            program_counter: CODE_START,
+            ..Registers::default()
+        });
+
+        cpu
+    }
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BenchmarkResult {
+    pub workload: Workload,
+    // interrupt entries count as one instruction each
+    pub instructions: u64,
+    pub cycles: u64,
+    pub elapsed: Duration,
+}
+
+impl BenchmarkResult {
+    pub fn instructions_per_second(&self) -> f64 {
+        self.instructions as f64 / self.elapsed.as_secs_f64()
+    }
+
+    // emulated clock the host could sustain
+    pub fn effective_mhz(&self) -> f64 {
+        self.cycles as f64 / self.elapsed.as_secs_f64() / 1_000_000.0
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "workload": self.workload.name(),
+            "instructions": self.instructions,
+            "cycles": self.cycles,
+            "seconds": self.elapsed.as_secs_f64(),
+            "ips": self.instructions_per_second(),
+            "mhz": self.effective_mhz(),
+        })
+    }
+}
+
+impl fmt::Display for BenchmarkResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<16} {:>12.0} instr/s {:>9.2} MHz  ({} instructions, {} cycles, {:.3}s)",
+            self.workload.name(),
+            self.instructions_per_second(),
+            self.effective_mhz(),
+            self.instructions,
+            self.cycles,
+            self.elapsed.as_secs_f64()
+        )
+    }
+}
+
+// executes given number of steps, timing covers the interpreter only
+pub fn run_workload(workload: Workload, instructions: u64) -> Result<BenchmarkResult, CpuError> {
+    let mut cpu = workload.build_cpu();
+    let nmi_interval = workload.nmi_interval();
+
+    if workload == Workload::RunLoop {
+        return run_loop(cpu, instructions);
+    }
+
+    let start = Instant::now();
+    for executed in 0..instructions {
+        if nmi_interval.is_some_and(|interval| executed % interval == 0) {
+            cpu.trigger_nmi();
+        }
+
+        if let Some(error) = cpu.step().error() {
+            return Err(error);
+        }
+
+        // stands in for the JMP closing the loop
+        if cpu.program_counter() >= CODE_END {
+            cpu.set_program_counter(CODE_START);
+        }
+    }
+    let elapsed = start.elapsed();
+
+    Ok(BenchmarkResult {
+        workload,
+        instructions,
+        cycles: cpu.cycles(),
+        elapsed,
+    })
+}
+
+// one run() call covering every instruction, no per step overhead in the harness
+fn run_loop(mut cpu: CPU, instructions: u64) -> Result<BenchmarkResult, CpuError> {
+    let start = Instant::now();
+    let stop_reason = cpu.run(Some(instructions * LDA_IMMEDIATE_CYCLES));
+    let elapsed = start.elapsed();
+
+    match stop_reason {
+        StopReason::UnimplementedOpcode { opcode, address } => {
+            return Err(CpuError::UnimplementedOpcode { opcode, address });
+        }
+        StopReason::BusError(error) => return Err(error),
+        _ => {}
+    }
+
+    Ok(BenchmarkResult {
+        workload: Workload::RunLoop,
+        instructions: cpu.cycles() / LDA_IMMEDIATE_CYCLES,
+        cycles: cpu.cycles(),
+        elapsed,
+    })
+}
+
+// best of several runs, the fastest one has the least host noise
+pub fn run_best_of(
+    workload: Workload,
+    instructions: u64,
+    runs: usize,
+) -> Result<BenchmarkResult, CpuError> {
+    let mut best = run_workload(workload, instructions)?;
+    for _ in 1..runs {
+        let result = run_workload(workload, instructions)?;
+        if result.elapsed < best.elapsed {
+            best = result;
+        }
+    }
+
+    Ok(best)
+}
+
+pub fn results_to_json(results: &[BenchmarkResult]) -> Value {
+    json!({
+        "results": results.iter().map(BenchmarkResult::to_json).collect::<Vec<_>>(),
+    })
+}
+
+// instructions per second of a saved run, keyed by workload name
+#[derive(Debug, Deserialize)]
+pub struct BaselineEntry {
+    pub workload: String,
+    pub ips: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Baseline {
+    pub results: Vec<BaselineEntry>,
+}
+
+impl Baseline {
+    pub fn load(path: &str) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        serde_json::from_str(&contents)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    pub fn save(path: &str, results: &[BenchmarkResult]) -> io::Result<()> {
+        let contents = serde_json::to_string_pretty(&results_to_json(results))
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        fs::write(path, contents + "\n")
+    }
+
+    // relative change in percent, positive is faster
+    pub fn change_percent(&self, result: &BenchmarkResult) -> Option<f64> {
+        self.results
+            .iter()
+            .find(|entry| entry.workload == result.workload.name())
+            .map(|entry| (result.instructions_per_second() / entry.ips - 1.0) * 100.0)
+    }
+}
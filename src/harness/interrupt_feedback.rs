@@ -0,0 +1,52 @@
+use crate::{
+    cpu::cpu::CPU,
+    shared::types::{Byte, Word},
+};
+
+// I/O port used by 6502_interrupt_test to drive IRQ/NMI from software,
+// defaults match I_port/IRQ_bit/NMI_bit of the prebuilt test binary
+pub struct InterruptFeedbackRegister {
+    address: Word,
+    irq_bit: Byte,
+    nmi_bit: Byte,
+    last_value: Byte,
+}
+
+impl InterruptFeedbackRegister {
+    pub const DEFAULT_ADDRESS: Word = 0xBFFC;
+
+    pub fn new(address: Word, irq_bit: Byte, nmi_bit: Byte) -> Self {
+        Self {
+            address,
+            irq_bit,
+            nmi_bit,
+            last_value: 0x00,
+        }
+    }
+
+    pub fn address(&self) -> Word {
+        self.address
+    }
+
+    // called after every step, mirrors port bits onto CPU interrupt inputs
+    pub fn update(&mut self, cpu: &mut CPU) {
+        let value = cpu.memory().peek(self.address);
+
+        let irq_asserted = (value >> self.irq_bit) & 1 == 1;
+        cpu.set_irq_line(irq_asserted);
+
+        let nmi_was_high = (self.last_value >> self.nmi_bit) & 1 == 1;
+        let nmi_is_high = (value >> self.nmi_bit) & 1 == 1;
+        if nmi_is_high && !nmi_was_high {
+            cpu.trigger_nmi();
+        }
+
+        self.last_value = value;
+    }
+}
+
+impl Default for InterruptFeedbackRegister {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_ADDRESS, 0, 1)
+    }
+}
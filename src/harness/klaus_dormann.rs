@@ -0,0 +1,170 @@
+use std::fmt;
+
+use crate::{
+    cpu::{cpu::CPU, cpu_error::CpuError, step_result::StepResult},
+    memory::{loader_error::LoaderError, memory::Memory},
+    shared::{
+        error_chain::error_chain,
+        types::{Byte, Word},
+    },
+};
+
+use super::interrupt_feedback::InterruptFeedbackRegister;
+
+// addresses below match the prebuilt binaries from Klaus2m5/6502_65C02_functional_tests,
+// reassembled suites should pass their own values
+pub struct KlausDormannConfig {
+    pub load_address: Word,
+    pub start_address: Word,
+    pub success_address: Word,
+    // zero page/RAM cell where the suite keeps current test number
+    pub test_case_address: Option<Word>,
+    pub feedback_register: Option<InterruptFeedbackRegister>,
+    pub max_cycles: u64,
+}
+
+impl KlausDormannConfig {
+    pub fn functional_test() -> Self {
+        Self {
+            load_address: 0x0000,
+            start_address: 0x0400,
+            success_address: 0x3469,
+            test_case_address: Some(0x0200),
+            feedback_register: None,
+            max_cycles: 200_000_000,
+        }
+    }
+
+    pub fn interrupt_test() -> Self {
+        Self {
+            load_address: 0x0000,
+            start_address: 0x0400,
+            success_address: 0x06F5,
+            test_case_address: Some(0x0200),
+            feedback_register: Some(InterruptFeedbackRegister::default()),
+            max_cycles: 10_000_000,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum KlausDormannOutcome {
+    Passed {
+        cycles: u64,
+    },
+    Failed {
+        test_number: Option<Byte>,
+        pc: Word,
+        cycles: u64,
+    },
+    UnimplementedOpcode {
+        opcode: Byte,
+        pc: Word,
+    },
+    CycleLimitReached {
+        pc: Word,
+    },
+    BusError(CpuError),
+}
+
+impl KlausDormannOutcome {
+    pub fn is_success(&self) -> bool {
+        matches!(self, KlausDormannOutcome::Passed { .. })
+    }
+}
+
+impl fmt::Display for KlausDormannOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KlausDormannOutcome::Passed { cycles } => {
+                write!(f, "PASSED after {cycles} cycles")
+            }
+            KlausDormannOutcome::Failed {
+                test_number: Some(test_number),
+                pc,
+                cycles,
+            } => write!(
+                f,
+                "FAILED test {test_number:#04X}, trapped at {pc:#06X} after {cycles} cycles"
+            ),
+            KlausDormannOutcome::Failed {
+                test_number: None,
+                pc,
+                cycles,
+            } => write!(f, "FAILED, trapped at {pc:#06X} after {cycles} cycles"),
+            KlausDormannOutcome::UnimplementedOpcode { opcode, pc } => {
+                write!(
+                    f,
+                    "ABORTED, unimplemented opcode {opcode:#04X} at {pc:#06X}"
+                )
+            }
+            KlausDormannOutcome::CycleLimitReached { pc } => {
+                write!(f, "ABORTED, cycle limit reached at {pc:#06X}")
+            }
+            KlausDormannOutcome::BusError(error) => write!(f, "ABORTED, {}", error_chain(error)),
+        }
+    }
+}
+
+pub struct KlausDormannRunner {
+    config: KlausDormannConfig,
+}
+
+impl KlausDormannRunner {
+    pub fn new(config: KlausDormannConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn run_file(&mut self, path: &str) -> Result<KlausDormannOutcome, LoaderError> {
+        let mut memory = Memory::new();
+        // suites write all over the address space, feedback port included
+        memory.set_rom_write_protected(false);
+        memory.load_bin(path, self.config.load_address)?;
+
+        Ok(self.run(CPU::with_memory(memory)))
+    }
+
+    pub fn run(&mut self, mut cpu: CPU) -> KlausDormannOutcome {
+        cpu.set_program_counter(self.config.start_address);
+
+        while cpu.cycles() < self.config.max_cycles {
+            let result = cpu.step();
+
+            if let Some(feedback_register) = self.config.feedback_register.as_mut() {
+                feedback_register.update(&mut cpu);
+            }
+
+            match result {
+                StepResult::Executed(_) => {}
+                StepResult::Trapped(pc) if pc == self.config.success_address => {
+                    return KlausDormannOutcome::Passed {
+                        cycles: cpu.cycles(),
+                    };
+                }
+                StepResult::Trapped(pc) => {
+                    let test_number = self
+                        .config
+                        .test_case_address
+                        .map(|address| cpu.memory().peek(address));
+
+                    return KlausDormannOutcome::Failed {
+                        test_number,
+                        pc,
+                        cycles: cpu.cycles(),
+                    };
+                }
+                StepResult::UnimplementedOpcode { opcode, address } => {
+                    return KlausDormannOutcome::UnimplementedOpcode {
+                        opcode,
+                        pc: address,
+                    };
+                }
+                StepResult::BusError(error) => return KlausDormannOutcome::BusError(error),
+            }
+        }
+
+        KlausDormannOutcome::CycleLimitReached {
+            pc: cpu.program_counter(),
+        }
+    }
+}
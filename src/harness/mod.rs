@@ -0,0 +1,5 @@
+pub mod batch_runner;
+pub mod benchmark;
+pub mod interrupt_feedback;
+pub mod klaus_dormann;
+pub mod processor_tests;
@@ -0,0 +1,521 @@
+use std::{fmt, fs, io, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    cpu::{cpu::CPU, registers::Registers, step_result::StepResult},
+    memory::{
+        bus_access::{BusAccess, BusAccessKind},
+        memory::Memory,
+    },
+    shared::types::{Byte, Word},
+};
+
+// mirrors SingleStepTests/ProcessorTests 6502 JSON layout, one file per opcode
+#[derive(Debug, Deserialize)]
+pub struct ProcessorTestCase {
+    pub name: String,
+    pub initial: ProcessorTestState,
+    #[serde(rename = "final")]
+    pub final_state: ProcessorTestState,
+    // (address, value, "read" | "write") per cycle
+    pub cycles: Vec<(Word, Byte, String)>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProcessorTestState {
+    pub pc: Word,
+    pub s: Byte,
+    pub a: Byte,
+    pub x: Byte,
+    pub y: Byte,
+    pub p: Byte,
+    pub ram: Vec<(Word, Byte)>,
+}
+
+impl ProcessorTestState {
+    fn registers(&self) -> Registers {
+        Registers {
+            acc: self.a,
+            x_reg: self.x,
+            y_reg: self.y,
+            stack_ptr: self.s,
+            program_counter: self.pc,
+            status: self.p,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Mismatch {
+    Register {
+        name: &'static str,
+        expected: Word,
+        actual: Word,
+    },
+    Ram {
+        address: Word,
+        expected: Byte,
+        actual: Byte,
+    },
+    CycleCount {
+        expected: usize,
+        actual: usize,
+    },
+    BusCycle {
+        cycle: usize,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::Register {
+                name,
+                expected,
+                actual,
+            } => write!(f, "{name}: expected {expected:#04X}, got {actual:#04X}"),
+            Mismatch::Ram {
+                address,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "RAM[{address:#06X}]: expected {expected:#04X}, got {actual:#04X}"
+            ),
+            Mismatch::CycleCount { expected, actual } => {
+                write!(f, "cycles: expected {expected}, got {actual}")
+            }
+            Mismatch::BusCycle {
+                cycle,
+                expected,
+                actual,
+            } => write!(f, "bus cycle {cycle}: expected {expected}, got {actual}"),
+        }
+    }
+}
+
+pub enum CaseResult {
+    Passed,
+    Failed(Vec<Mismatch>),
+    UnimplementedOpcode(Byte),
+}
+
+pub struct OpcodeReport {
+    pub file_name: String,
+    pub passed: usize,
+    pub failed: usize,
+    // opcode not implemented by the core, remaining cases were skipped
+    pub unimplemented: bool,
+    // expected bus reads the core never performs, e.g. indexed dummy reads, not compared
+    pub skipped_dummy_reads: usize,
+    // first failing case with its mismatches, enough to start debugging
+    pub first_failure: Option<(String, Vec<Mismatch>)>,
+}
+
+impl OpcodeReport {
+    pub fn is_success(&self) -> bool {
+        self.failed == 0 && !self.unimplemented
+    }
+}
+
+impl fmt::Display for OpcodeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.unimplemented {
+            return write!(f, "{}: UNIMPLEMENTED", self.file_name);
+        }
+
+        write!(
+            f,
+            "{}: {} passed, {} failed",
+            self.file_name, self.passed, self.failed
+        )?;
+        if self.skipped_dummy_reads > 0 {
+            write!(f, ", {} dummy reads skipped", self.skipped_dummy_reads)?;
+        }
+
+        if let Some((name, mismatches)) = &self.first_failure {
+            write!(f, "\n  first failure \"{name}\"")?;
+            for mismatch in mismatches {
+                write!(f, "\n    {mismatch}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub struct ProcessorTestRunner {
+    cpu: CPU,
+    compare_bus_cycles: bool,
+    // dummy reads skipped since the current file started
+    skipped_dummy_reads: usize,
+}
+
+impl ProcessorTestRunner {
+    pub fn new(compare_bus_cycles: bool) -> Self {
+        let mut memory = Memory::new();
+        // suite puts code and data anywhere in 64K
+        memory.set_rom_write_protected(false);
+        memory.set_bus_logging(compare_bus_cycles);
+
+        Self {
+            cpu: CPU::with_memory(memory),
+            compare_bus_cycles,
+            skipped_dummy_reads: 0,
+        }
+    }
+
+    // runs every *.json file in directory, sorted by name
+    pub fn run_directory(&mut self, dir: &Path) -> io::Result<Vec<OpcodeReport>> {
+        let mut paths = fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        paths.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+        paths.sort();
+
+        paths.iter().map(|path| self.run_file(path)).collect()
+    }
+
+    pub fn run_file(&mut self, path: &Path) -> io::Result<OpcodeReport> {
+        let json = fs::read_to_string(path)?;
+        let cases: Vec<ProcessorTestCase> = serde_json::from_str(&json)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        let mut report = OpcodeReport {
+            file_name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            passed: 0,
+            failed: 0,
+            unimplemented: false,
+            skipped_dummy_reads: 0,
+            first_failure: None,
+        };
+        self.skipped_dummy_reads = 0;
+
+        for case in &cases {
+            match self.run_case(case) {
+                CaseResult::Passed => report.passed += 1,
+                CaseResult::Failed(mismatches) => {
+                    report.failed += 1;
+                    if report.first_failure.is_none() {
+                        report.first_failure = Some((case.name.clone(), mismatches));
+                    }
+                }
+                CaseResult::UnimplementedOpcode(_) => {
+                    report.unimplemented = true;
+                    break;
+                }
+            }
+        }
+        report.skipped_dummy_reads = self.skipped_dummy_reads;
+
+        Ok(report)
+    }
+
+    pub fn run_case(&mut self, case: &ProcessorTestCase) -> CaseResult {
+        self.cpu.set_registers(&case.initial.registers());
+        self.cpu.set_irq_line(false);
+        for &(address, value) in &case.initial.ram {
+            self.cpu.memory_mut().load(address, &[value]);
+        }
+        self.cpu.memory_mut().take_bus_log();
+
+        let cycles_before = self.cpu.cycles();
+        let step_result = self.cpu.step();
+        let consumed_cycles = (self.cpu.cycles() - cycles_before) as usize;
+        let bus_log = self.cpu.memory_mut().take_bus_log();
+
+        let result = match step_result {
+            StepResult::UnimplementedOpcode { opcode, .. } => {
+                CaseResult::UnimplementedOpcode(opcode)
+            }
+            // failed accesses show up as mismatches
+            StepResult::Executed(_) | StepResult::Trapped(_) | StepResult::BusError(_) => {
+                let mismatches = self.compare(case, consumed_cycles, &bus_log);
+                if mismatches.is_empty() {
+                    CaseResult::Passed
+                } else {
+                    CaseResult::Failed(mismatches)
+                }
+            }
+        };
+
+        // leave memory clean for the next case
+        for &(address, _) in case.initial.ram.iter().chain(&case.final_state.ram) {
+            self.cpu.memory_mut().load(address, &[0x00]);
+        }
+
+        result
+    }
+
+    fn compare(
+        &mut self,
+        case: &ProcessorTestCase,
+        consumed_cycles: usize,
+        bus_log: &[BusAccess],
+    ) -> Vec<Mismatch> {
+        let mut mismatches = Vec::new();
+
+        let expected = case.final_state.registers();
+        let actual = self.cpu.registers();
+        let register_pairs = [
+            ("PC", expected.program_counter, actual.program_counter),
+            ("S", expected.stack_ptr.into(), actual.stack_ptr.into()),
+            ("A", expected.acc.into(), actual.acc.into()),
+            ("X", expected.x_reg.into(), actual.x_reg.into()),
+            ("Y", expected.y_reg.into(), actual.y_reg.into()),
+            ("P", expected.status.into(), actual.status.into()),
+        ];
+        for (name, expected, actual) in register_pairs {
+            if expected != actual {
+                mismatches.push(Mismatch::Register {
+                    name,
+                    expected,
+                    actual,
+                });
+            }
+        }
+
+        for &(address, expected) in &case.final_state.ram {
+            let actual = self.cpu.memory().peek(address);
+            if expected != actual {
+                mismatches.push(Mismatch::Ram {
+                    address,
+                    expected,
+                    actual,
+                });
+            }
+        }
+
+        if consumed_cycles != case.cycles.len() {
+            mismatches.push(Mismatch::CycleCount {
+                expected: case.cycles.len(),
+                actual: consumed_cycles,
+            });
+        }
+
+        if self.compare_bus_cycles {
+            self.compare_bus_log(case, bus_log, &mut mismatches);
+        }
+
+        mismatches
+    }
+
+    // the core does not perform dummy reads, an expected read it has no access for is
+    // skipped when it is one, anything else left over on either side is a mismatch
+    fn compare_bus_log(
+        &mut self,
+        case: &ProcessorTestCase,
+        bus_log: &[BusAccess],
+        mismatches: &mut Vec<Mismatch>,
+    ) {
+        let describe = |access: &BusAccess| {
+            format!(
+                "{} {:#06X}={:#04X}",
+                access.kind.as_str(),
+                access.address,
+                access.value
+            )
+        };
+
+        let mut accesses = bus_log.iter().peekable();
+        for (cycle, (address, value, kind)) in case.cycles.iter().enumerate() {
+            let expected = format!("{kind} {address:#06X}={value:#04X}");
+            let actual = accesses.peek().map(|access| describe(access));
+
+            if actual.as_ref() == Some(&expected) {
+                accesses.next();
+            } else if is_dummy_read(&case.cycles, cycle) {
+                self.skipped_dummy_reads += 1;
+            } else {
+                accesses.next();
+                mismatches.push(Mismatch::BusCycle {
+                    cycle,
+                    expected,
+                    actual: actual.unwrap_or_else(|| "nothing".to_string()),
+                });
+            }
+        }
+
+        for (extra, access) in accesses.enumerate() {
+            mismatches.push(Mismatch::BusCycle {
+                cycle: case.cycles.len() + extra,
+                expected: "nothing".to_string(),
+                actual: describe(access),
+            });
+        }
+    }
+}
+
+// reads the 6502 makes while it computes an address: the previous address again, the
+// zero page base before indexing, or the indexed address before the page carry is added
+fn is_dummy_read(cycles: &[(Word, Byte, String)], cycle: usize) -> bool {
+    let (address, _, kind) = &cycles[cycle];
+    if kind != BusAccessKind::Read.as_str() || cycle == 0 {
+        return false;
+    }
+
+    let (previous_address, previous_value, previous_kind) = &cycles[cycle - 1];
+    let is_repeat = previous_address == address;
+    let is_zero_page_base =
+        previous_kind == BusAccessKind::Read.as_str() && Word::from(*previous_value) == *address;
+    let is_unfixed_address = cycles
+        .get(cycle + 1)
+        .is_some_and(|(next_address, ..)| next_address.wrapping_sub(*address) == 0x0100);
+
+    is_repeat || is_zero_page_base || is_unfixed_address
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(pc: Word, a: Byte, ram: Vec<(Word, Byte)>) -> ProcessorTestState {
+        ProcessorTestState {
+            pc,
+            s: 0xFD,
+            a,
+            x: 0x02,
+            y: 0x00,
+            p: 0x24,
+            ram,
+        }
+    }
+
+    // LDA $10,X reads the unindexed zero page address before the real one
+    fn lda_zero_page_x(cycles: Vec<(Word, Byte, &str)>) -> ProcessorTestCase {
+        let ram = vec![(0x0200, 0xB5), (0x0201, 0x10), (0x0012, 0x42)];
+
+        ProcessorTestCase {
+            name: "b5 10".to_string(),
+            initial: state(0x0200, 0x00, ram.clone()),
+            final_state: state(0x0202, 0x42, ram),
+            cycles: cycles
+                .into_iter()
+                .map(|(address, value, kind)| (address, value, kind.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn skips_dummy_reads_the_core_does_not_perform() {
+        let mut runner = ProcessorTestRunner::new(true);
+        let case = lda_zero_page_x(vec![
+            (0x0200, 0xB5, "read"),
+            (0x0201, 0x10, "read"),
+            (0x0010, 0x00, "read"),
+            (0x0012, 0x42, "read"),
+        ]);
+
+        assert!(matches!(runner.run_case(&case), CaseResult::Passed));
+        assert_eq!(runner.skipped_dummy_reads, 1);
+    }
+
+    #[test]
+    fn reports_bus_cycles_that_are_not_dummy_reads() {
+        let mut runner = ProcessorTestRunner::new(true);
+        let case = lda_zero_page_x(vec![
+            (0x0200, 0xB5, "read"),
+            (0x0201, 0x10, "read"),
+            (0x0010, 0x00, "write"),
+            (0x0012, 0x42, "read"),
+        ]);
+
+        let CaseResult::Failed(mismatches) = runner.run_case(&case) else {
+            panic!("expected a bus cycle mismatch");
+        };
+        assert_eq!(
+            mismatches,
+            [
+                Mismatch::BusCycle {
+                    cycle: 2,
+                    expected: "write 0x0010=0x00".to_string(),
+                    actual: "read 0x0012=0x42".to_string(),
+                },
+                Mismatch::BusCycle {
+                    cycle: 3,
+                    expected: "read 0x0012=0x42".to_string(),
+                    actual: "nothing".to_string(),
+                },
+            ]
+        );
+    }
+
+    fn read(address: Word, value: Byte) -> BusAccess {
+        BusAccess {
+            address,
+            value,
+            kind: BusAccessKind::Read,
+        }
+    }
+
+    #[test]
+    fn skips_page_cross_and_repeated_reads() {
+        let mut runner = ProcessorTestRunner::new(true);
+        // LDA $12F0,X with X=$20 reads $1210 before the carry reaches the high byte
+        let case = lda_zero_page_x(vec![
+            (0x0200, 0xBD, "read"),
+            (0x0201, 0xF0, "read"),
+            (0x0202, 0x12, "read"),
+            (0x0202, 0x12, "read"),
+            (0x1210, 0x00, "read"),
+            (0x1310, 0x42, "read"),
+        ]);
+        let bus_log = [
+            read(0x0200, 0xBD),
+            read(0x0201, 0xF0),
+            read(0x0202, 0x12),
+            read(0x1310, 0x42),
+        ];
+
+        let mut mismatches = Vec::new();
+        runner.compare_bus_log(&case, &bus_log, &mut mismatches);
+
+        assert_eq!(mismatches, []);
+        assert_eq!(runner.skipped_dummy_reads, 2);
+    }
+
+    #[test]
+    fn reports_a_dropped_real_read() {
+        let mut runner = ProcessorTestRunner::new(true);
+        // LDA ($10),Y without reading the pointer high byte at $11
+        let case = lda_zero_page_x(vec![
+            (0x0200, 0xB1, "read"),
+            (0x0201, 0x10, "read"),
+            (0x0010, 0x00, "read"),
+            (0x0011, 0x03, "read"),
+            (0x0300, 0x42, "read"),
+        ]);
+        let bus_log = [
+            read(0x0200, 0xB1),
+            read(0x0201, 0x10),
+            read(0x0010, 0x00),
+            read(0x0300, 0x42),
+        ];
+
+        let mut mismatches = Vec::new();
+        runner.compare_bus_log(&case, &bus_log, &mut mismatches);
+
+        assert_eq!(runner.skipped_dummy_reads, 0);
+        assert_eq!(
+            mismatches,
+            [
+                Mismatch::BusCycle {
+                    cycle: 3,
+                    expected: "read 0x0011=0x03".to_string(),
+                    actual: "read 0x0300=0x42".to_string(),
+                },
+                Mismatch::BusCycle {
+                    cycle: 4,
+                    expected: "read 0x0300=0x42".to_string(),
+                    actual: "nothing".to_string(),
+                },
+            ]
+        );
+    }
+}
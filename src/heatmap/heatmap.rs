@@ -0,0 +1,105 @@
+use crate::{memory::page_table::MEMORY_SIZE, shared::types::Word};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AccessType {
+    Read,
+    Write,
+    // opcode and operand fetches
+    Execute,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct AddressAccess {
+    pub reads: u64,
+    pub writes: u64,
+    pub executes: u64,
+    // instruction that touched the address most recently, None before any access
+    pub last_pc: Option<Word>,
+}
+
+impl AddressAccess {
+    pub fn total(&self) -> u64 {
+        self.reads + self.writes + self.executes
+    }
+
+    pub fn count(&self, access: AccessType) -> u64 {
+        match access {
+            AccessType::Read => self.reads,
+            AccessType::Write => self.writes,
+            AccessType::Execute => self.executes,
+        }
+    }
+
+    pub fn is_touched(&self) -> bool {
+        self.total() > 0
+    }
+}
+
+// per address access counts, fed by Memory::fetch/read/write while enabled
+pub struct Heatmap {
+    accesses: Vec<AddressAccess>,
+    // set by the CPU before every instruction, interrupt entry accesses
+    // are charged to the interrupted instruction
+    current_pc: Word,
+}
+
+impl Default for Heatmap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Heatmap {
+    pub fn new() -> Self {
+        Self {
+            accesses: vec![AddressAccess::default(); MEMORY_SIZE],
+            current_pc: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.accesses.fill(AddressAccess::default());
+    }
+
+    pub fn access(&self, address: Word) -> AddressAccess {
+        self.accesses[usize::from(address)]
+    }
+
+    // touched addresses in address order
+    pub fn touched(&self) -> impl Iterator<Item = (Word, AddressAccess)> + '_ {
+        self.accesses
+            .iter()
+            .enumerate()
+            .filter(|(_, access)| access.is_touched())
+            .map(|(address, access)| (address as Word, *access))
+    }
+
+    // most accessed addresses of one type, or of all with None
+    pub fn hottest(&self, access: Option<AccessType>, limit: usize) -> Vec<(Word, AddressAccess)> {
+        let count =
+            |entry: &AddressAccess| access.map_or(entry.total(), |access| entry.count(access));
+
+        let mut hottest: Vec<_> = self
+            .touched()
+            .filter(|(_, entry)| count(entry) > 0)
+            .collect();
+        hottest.sort_by(|a, b| count(&b.1).cmp(&count(&a.1)).then(a.0.cmp(&b.0)));
+        hottest.truncate(limit);
+
+        hottest
+    }
+
+    pub(crate) fn set_current_pc(&mut self, pc: Word) {
+        self.current_pc = pc;
+    }
+
+    pub(crate) fn record(&mut self, address: Word, access: AccessType) {
+        let entry = &mut self.accesses[usize::from(address)];
+        match access {
+            AccessType::Read => entry.reads += 1,
+            AccessType::Write => entry.writes += 1,
+            AccessType::Execute => entry.executes += 1,
+        }
+        entry.last_pc = Some(self.current_pc);
+    }
+}
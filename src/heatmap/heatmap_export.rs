@@ -0,0 +1,183 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use super::heatmap::{AccessType, AddressAccess, Heatmap};
+
+// one pixel per address, one 256 byte page per row
+const IMAGE_SIZE: usize = 256;
+
+impl Heatmap {
+    // touched addresses only, counts in decimal, addresses in hex
+    pub fn write_csv<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        writeln!(output, "address,reads,writes,executes,last_pc")?;
+        for (address, access) in self.touched() {
+            let last_pc = access
+                .last_pc
+                .map(|pc| format!("${pc:04X}"))
+                .unwrap_or_default();
+            writeln!(
+                output,
+                "${address:04X},{},{},{},{last_pc}",
+                access.reads, access.writes, access.executes
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // binary greyscale PGM, brightness is log scaled total access count
+    pub fn write_pgm<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        let scale = LogScale::new(self.touched().map(|(_, access)| access.total()).max());
+
+        write!(output, "P5\n{IMAGE_SIZE} {IMAGE_SIZE}\n255\n")?;
+        let pixels: Vec<u8> = (0..=u16::MAX)
+            .map(|address| scale.apply(self.access(address).total()))
+            .collect();
+        output.write_all(&pixels)
+    }
+
+    // binary PPM, red writes, green reads, blue executes, each channel log scaled on its own
+    pub fn write_ppm<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        let channel_scale = |access: AccessType| {
+            LogScale::new(self.touched().map(|(_, entry)| entry.count(access)).max())
+        };
+        let scales = [
+            (AccessType::Write, channel_scale(AccessType::Write)),
+            (AccessType::Read, channel_scale(AccessType::Read)),
+            (AccessType::Execute, channel_scale(AccessType::Execute)),
+        ];
+
+        write!(output, "P6\n{IMAGE_SIZE} {IMAGE_SIZE}\n255\n")?;
+        let mut pixels = Vec::with_capacity(IMAGE_SIZE * IMAGE_SIZE * 3);
+        for address in 0..=u16::MAX {
+            let access: AddressAccess = self.access(address);
+            for (access_type, scale) in &scales {
+                pixels.push(scale.apply(access.count(*access_type)));
+            }
+        }
+        output.write_all(&pixels)
+    }
+
+    pub fn save_csv<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        save_with(path, |output| self.write_csv(output))
+    }
+
+    pub fn save_pgm<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        save_with(path, |output| self.write_pgm(output))
+    }
+
+    pub fn save_ppm<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        save_with(path, |output| self.write_ppm(output))
+    }
+}
+
+fn save_with<P, F>(path: P, write: F) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+{
+    let mut output = BufWriter::new(File::create(path)?);
+    write(&mut output)?;
+    output.flush()
+}
+
+// counts span many magnitudes (a loop body vs an init routine), linear scale would show only the loop
+struct LogScale {
+    max_log: f64,
+}
+
+impl LogScale {
+    fn new(max_count: Option<u64>) -> Self {
+        Self {
+            max_log: (max_count.unwrap_or(0) as f64).ln_1p(),
+        }
+    }
+
+    // untouched stays black, a single access is still visible
+    fn apply(&self, count: u64) -> u8 {
+        if count == 0 || self.max_log == 0.0 {
+            return 0;
+        }
+
+        (32.0 + 223.0 * (count as f64).ln_1p() / self.max_log).round() as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER_LENGTH: usize = "P5\n256 256\n255\n".len();
+
+    // $0200 executed 9 times from $0200, $0300 read once and written twice from $0210
+    fn heatmap() -> Heatmap {
+        let mut heatmap = Heatmap::new();
+        heatmap.set_current_pc(0x0200);
+        for _ in 0..9 {
+            heatmap.record(0x0200, AccessType::Execute);
+        }
+        heatmap.set_current_pc(0x0210);
+        heatmap.record(0x0300, AccessType::Read);
+        heatmap.record(0x0300, AccessType::Write);
+        heatmap.record(0x0300, AccessType::Write);
+        heatmap
+    }
+
+    fn written(write: impl FnOnce(&mut Vec<u8>) -> io::Result<()>) -> Vec<u8> {
+        let mut output = Vec::new();
+        write(&mut output).unwrap();
+        output
+    }
+
+    #[test]
+    fn csv_lists_touched_addresses_only() {
+        let csv = String::from_utf8(written(|output| heatmap().write_csv(output))).unwrap();
+
+        assert_eq!(
+            csv,
+            "address,reads,writes,executes,last_pc\n$0200,0,0,9,$0200\n$0300,1,2,0,$0210\n"
+        );
+    }
+
+    #[test]
+    fn pgm_scales_total_counts_logarithmically() {
+        let pgm = written(|output| heatmap().write_pgm(output));
+
+        assert!(pgm.starts_with(b"P5\n256 256\n255\n"));
+        assert_eq!(pgm.len(), HEADER_LENGTH + IMAGE_SIZE * IMAGE_SIZE);
+        let pixel = |address: usize| pgm[HEADER_LENGTH + address];
+        assert_eq!(pixel(0x0200), 255);
+        // 3 of at most 9 accesses, log scale keeps it well above a linear third
+        assert_eq!(pixel(0x0300), 166);
+        assert_eq!(pixel(0x0201), 0);
+    }
+
+    #[test]
+    fn ppm_scales_every_channel_on_its_own() {
+        let ppm = written(|output| heatmap().write_ppm(output));
+
+        assert!(ppm.starts_with(b"P6\n256 256\n255\n"));
+        assert_eq!(ppm.len(), HEADER_LENGTH + IMAGE_SIZE * IMAGE_SIZE * 3);
+        let pixel = |address: usize| {
+            let start = HEADER_LENGTH + address * 3;
+            &ppm[start..start + 3]
+        };
+        assert_eq!(pixel(0x0200), [0, 0, 255]);
+        assert_eq!(pixel(0x0300), [255, 255, 0]);
+        assert_eq!(pixel(0xFFFF), [0, 0, 0]);
+    }
+
+    #[test]
+    fn empty_heatmap_exports_black_images() {
+        let heatmap = Heatmap::new();
+
+        let pgm = written(|output| heatmap.write_pgm(output));
+        let csv = written(|output| heatmap.write_csv(output));
+
+        assert!(pgm[HEADER_LENGTH..].iter().all(|pixel| *pixel == 0));
+        assert_eq!(csv, b"address,reads,writes,executes,last_pc\n");
+    }
+}
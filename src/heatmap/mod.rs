@@ -0,0 +1,2 @@
+pub mod heatmap;
+pub mod heatmap_export;
@@ -0,0 +1,18 @@
+// component folders keep a same-named file for the main type (cpu/cpu.rs, memory/memory.rs)
+#![allow(clippy::module_inception)]
+
+pub mod coverage;
+pub mod cpu;
+pub mod dap;
+pub mod disassembler;
+pub mod gdb;
+pub mod harness;
+pub mod heatmap;
+pub mod memory;
+pub mod monitor;
+pub mod profiler;
+pub mod replay;
+pub mod save_state;
+pub mod shared;
+pub mod symbols;
+pub mod throttle;
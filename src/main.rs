@@ -1,7 +1,402 @@
-pub mod cpu;
-pub mod memory;
-pub mod shared;
+use std::{
+    env,
+    io::{self, BufWriter},
+    net::TcpListener,
+    process::ExitCode,
+};
 
-fn main() {
-    todo!()
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+
+use cpu_emu::{
+    cpu::{
+        cpu::CPU,
+        rewind::rewind_history::{RewindHistory, BYTES_PER_MEGABYTE, DEFAULT_SNAPSHOT_INTERVAL},
+    },
+    dap::dap_server::DapServer,
+    gdb::gdb_stub::GdbStub,
+    harness::batch_runner::{run_batch, BatchConfig},
+    memory::{memory::Memory, power_on_pattern::PowerOnPattern},
+    monitor::monitor::Monitor,
+    replay::event_log::EventLog,
+    save_state::save_state::SaveState,
+    shared::{
+        error_chain::error_chain,
+        logger::{
+            logger,
+            rotating_file_sink::{
+                RotatingFileSink, DEFAULT_LOG_FILE_COUNT, DEFAULT_LOG_FILE_MAX_BYTES,
+            },
+            LogLevel,
+        },
+        parsing::parse_word,
+    },
+    symbols::loaders::load_symbols,
+    throttle::{clock_preset::ClockPreset, throttle::Throttle},
+};
+
+const USAGE: &str = "usage: cpu-emu <program.bin> [--load $addr] [--start $addr] \
+[--symbols path] [--state path] [--replay name] [--rewind mb]
+       [--log filters] [--log-file path] [--log-json] [--ram-pattern name] [--unmap $start-$end]...
+       [--gdb host:port | --gdb-unix path] [--clock ntsc|pal|c64|1mhz|hz] [--speed x]
+       [--headless [--max-cycles n] [--max-instructions n] [--exit-pc $addr]... [--exit-on-brk]
+                   [--magic $addr] [--dump $addr:len]... [--json]]
+       cpu-emu --dap
+  without --load the image is mapped as ROM ending at $FFFF and started from reset vector
+  --headless runs until an exit condition instead of starting the monitor and prints registers
+  and --dump ranges (--json for JSON), exit code is 0 for --exit-pc or --exit-on-brk, the byte
+  written to --magic, 3 for a cycle or instruction limit (default 60000000 cycles),
+  4 for unimplemented opcode, 5 for a trap, 6 for a bus error, codes 2 to 15 are reserved,
+  a --magic byte in that range exits with 7
+  --symbols loads ca65 .dbg, VICE label or \"name = $addr\" files for disassembly and logs
+  --state restores a save state written by the monitor save command after loading
+  --replay restores name.state and feeds back external events from name.events
+  --rewind records execution for reverse stepping within the given memory budget
+  --ram-pattern fills RAM before loading: zeros, ones, c64, nes, random:<seed> or random,
+  which logs the seed it picked
+  --unmap leaves the pages covering the range unmapped, reads return the last data bus value
+  --clock/--speed pace continue to real time, speed multiplies the clock (default NTSC)
+  --log sets log levels like CPU_EMU_LOG does, e.g. \"info,CPU=debug,MEM::read*=off\"
+  --log-json writes log records as JSON lines with registers as fields
+  --log-file appends log lines to path, rotated at 10 MB keeping 3 old files, stderr keeps logging
+  --gdb/--gdb-unix wait for a GDB remote connection instead of starting the monitor,
+  Unix sockets are only available on Unix
+  --dap speaks Debug Adapter Protocol on stdin/stdout, program comes from the launch request";
+
+const BATCH_OPTIONS: [&str; 7] = [
+    "--max-cycles",
+    "--max-instructions",
+    "--exit-pc",
+    "--exit-on-brk",
+    "--magic",
+    "--dump",
+    "--json",
+];
+
+enum GdbListen {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(String),
+}
+
+// file sinks buffer, the global logger is never dropped
+fn main() -> ExitCode {
+    let exit_code = run();
+    logger().flush();
+
+    exit_code
+}
+
+fn run() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.first().is_some_and(|arg| arg == "--dap") {
+        return serve_dap();
+    }
+
+    let Some(path) = args.first() else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+
+    let mut load_address = None;
+    let mut start_address = None;
+    let mut symbols_path = None;
+    let mut state_path = None;
+    let mut replay_name = None;
+    let mut rewind_budget = None;
+    let mut ram_pattern = PowerOnPattern::default();
+    let mut unmapped_ranges = Vec::new();
+    let mut clock = None;
+    let mut speed = None;
+    let mut gdb_listen = None;
+    let mut is_headless = false;
+    // first option that only makes sense with --headless
+    let mut batch_option = None;
+    let mut json_output = false;
+    let mut batch_config = BatchConfig::default();
+    let mut options = args[1..].iter();
+    while let Some(name) = options.next() {
+        let is_valid = match name.as_str() {
+            "--headless" => {
+                is_headless = true;
+                true
+            }
+            "--json" => {
+                json_output = true;
+                true
+            }
+            "--log-json" => {
+                logger().set_use_json(true);
+                true
+            }
+            "--exit-on-brk" => {
+                batch_config.exit_on_brk = true;
+                true
+            }
+            _ => match options.next() {
+                Some(value) => match name.as_str() {
+                    "--load" => parse_word(value)
+                        .map(|address| load_address = Some(address))
+                        .is_some(),
+                    "--start" => parse_word(value)
+                        .map(|address| start_address = Some(address))
+                        .is_some(),
+                    "--symbols" => {
+                        symbols_path = Some(value.clone());
+                        true
+                    }
+                    "--state" => {
+                        state_path = Some(value.clone());
+                        true
+                    }
+                    "--replay" => {
+                        replay_name = Some(value.clone());
+                        true
+                    }
+                    "--rewind" => value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|megabytes| *megabytes > 0)
+                        .and_then(|megabytes| megabytes.checked_mul(BYTES_PER_MEGABYTE))
+                        .map(|budget_bytes| rewind_budget = Some(budget_bytes))
+                        .is_some(),
+                    "--clock" => ClockPreset::parse(value)
+                        .map(|preset| clock = Some(preset))
+                        .is_some(),
+                    "--speed" => value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|speed| *speed > 0.0 && speed.is_finite())
+                        .map(|multiplier| speed = Some(multiplier))
+                        .is_some(),
+                    "--max-cycles" => value
+                        .parse::<u64>()
+                        .map(|cycles| batch_config.max_cycles = Some(cycles))
+                        .is_ok(),
+                    "--max-instructions" => value
+                        .parse::<u64>()
+                        .map(|instructions| batch_config.max_instructions = Some(instructions))
+                        .is_ok(),
+                    "--exit-pc" => parse_word(value)
+                        .map(|address| batch_config.exit_addresses.push(address))
+                        .is_some(),
+                    "--magic" => parse_word(value)
+                        .map(|address| batch_config.magic_address = Some(address))
+                        .is_some(),
+                    "--ram-pattern" => PowerOnPattern::parse(value)
+                        .map(|pattern| ram_pattern = pattern)
+                        .is_some(),
+                    "--unmap" => value
+                        .split_once('-')
+                        .and_then(|(start, end)| Some((parse_word(start)?, parse_word(end)?)))
+                        .filter(|(start, end)| start <= end)
+                        .map(|range| unmapped_ranges.push(range))
+                        .is_some(),
+                    "--dump" => value
+                        .split_once(':')
+                        .and_then(|(address, length)| {
+                            Some((parse_word(address)?, parse_word(length)?))
+                        })
+                        .map(|dump| batch_config.dumps.push(dump))
+                        .is_some(),
+                    "--log" => match logger().configure(value) {
+                        Ok(()) => true,
+                        Err(error) => {
+                            eprintln!("invalid log filters {value}: {error}");
+                            return ExitCode::from(2);
+                        }
+                    },
+                    "--log-file" => {
+                        match RotatingFileSink::new(
+                            value,
+                            DEFAULT_LOG_FILE_MAX_BYTES,
+                            DEFAULT_LOG_FILE_COUNT,
+                        ) {
+                            Ok(sink) => {
+                                logger().add_sink(sink, LogLevel::Verbose);
+                                true
+                            }
+                            Err(error) => {
+                                eprintln!("failed to open log file {value}: {error}");
+                                return ExitCode::from(2);
+                            }
+                        }
+                    }
+                    "--gdb" => {
+                        gdb_listen = Some(GdbListen::Tcp(value.clone()));
+                        true
+                    }
+                    #[cfg(unix)]
+                    "--gdb-unix" => {
+                        gdb_listen = Some(GdbListen::Unix(value.clone()));
+                        true
+                    }
+                    _ => false,
+                },
+                None => false,
+            },
+        };
+
+        if !is_valid {
+            eprintln!("invalid option {name}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+        if BATCH_OPTIONS.contains(&name.as_str()) {
+            batch_option.get_or_insert(name);
+        }
+    }
+
+    if let (false, Some(name)) = (is_headless, batch_option) {
+        eprintln!("{name} needs --headless\n{USAGE}");
+        return ExitCode::from(2);
+    }
+
+    let mut memory = Memory::new();
+    memory.fill_ram(ram_pattern);
+    let load_result = match load_address {
+        Some(address) => memory.load_bin(path, address),
+        None => memory.load_rom(path),
+    };
+    if let Err(error) = load_result {
+        eprintln!("{}", error_chain(&error));
+        return ExitCode::from(2);
+    }
+    for (start, end) in unmapped_ranges {
+        let [_, first_page] = start.to_le_bytes();
+        let [_, last_page] = end.to_le_bytes();
+        if let Err(error) = memory.unmap_pages(first_page, usize::from(last_page - first_page) + 1)
+        {
+            eprintln!("{error}");
+            return ExitCode::from(2);
+        }
+    }
+
+    let mut cpu = CPU::with_memory(memory);
+    if let Some(symbols_path) = symbols_path {
+        match load_symbols(&symbols_path) {
+            Ok(symbols) => cpu.set_symbols(symbols),
+            Err(error) => {
+                eprintln!("failed to load symbols {symbols_path}: {error}");
+                return ExitCode::from(2);
+            }
+        }
+    }
+    cpu.reset();
+    if let Some(address) = start_address.or(load_address) {
+        cpu.set_program_counter(address);
+    }
+
+    if let Some(state_path) = state_path {
+        if let Err(error) = SaveState::load(&state_path).and_then(|state| cpu.restore_state(&state))
+        {
+            eprintln!("failed to restore state {state_path}: {error}");
+            return ExitCode::from(2);
+        }
+    }
+
+    if let Some(name) = replay_name {
+        let state =
+            SaveState::load(format!("{name}.state")).and_then(|state| cpu.restore_state(&state));
+        let log = EventLog::load(format!("{name}.events"));
+        match (state, log) {
+            (Ok(()), Ok(log)) => cpu.start_replay(log),
+            (Err(error), _) => {
+                eprintln!("failed to restore {name}.state: {error}");
+                return ExitCode::from(2);
+            }
+            (_, Err(error)) => {
+                eprintln!("failed to load {name}.events: {error}");
+                return ExitCode::from(2);
+            }
+        }
+    }
+
+    if let Some(budget_bytes) = rewind_budget {
+        cpu.enable_rewind(RewindHistory::new(budget_bytes, DEFAULT_SNAPSHOT_INTERVAL));
+    }
+
+    let throttle = (clock.is_some() || speed.is_some()).then(|| {
+        let mut throttle = Throttle::new(clock.unwrap_or_default());
+        if let Some(speed) = speed {
+            throttle.set_speed(speed);
+        }
+        throttle
+    });
+
+    if let Some(gdb_listen) = gdb_listen {
+        return match serve_gdb(cpu, throttle, gdb_listen) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(error) => {
+                eprintln!("gdb stub error: {error}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    if is_headless {
+        return run_headless(cpu, &batch_config, json_output);
+    }
+
+    let mut output = BufWriter::new(io::stdout());
+    let mut monitor = Monitor::new(cpu);
+    monitor.set_throttle(throttle);
+    if let Err(error) = monitor.run(io::stdin().lock(), &mut output) {
+        eprintln!("monitor I/O error: {error}");
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
+
+// log lines go to stderr, stdout only carries the report
+fn run_headless(mut cpu: CPU, config: &BatchConfig, json_output: bool) -> ExitCode {
+    let report = run_batch(&mut cpu, config);
+    if json_output {
+        println!("{}", report.to_json());
+    } else {
+        print!("{report}");
+    }
+
+    ExitCode::from(report.exit_code())
+}
+
+// stdout carries protocol messages, so logging is silenced for the session
+fn serve_dap() -> ExitCode {
+    logger().set_enabled(false);
+
+    let mut server = DapServer::new(BufWriter::new(io::stdout()));
+    match server.serve(io::BufReader::new(io::stdin())) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("debug adapter error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// single debug session, emulator exits when GDB detaches or kills
+fn serve_gdb(cpu: CPU, throttle: Option<Throttle>, gdb_listen: GdbListen) -> io::Result<()> {
+    let mut stub = GdbStub::new(cpu);
+    stub.set_throttle(throttle);
+
+    match gdb_listen {
+        GdbListen::Tcp(address) => {
+            let listener = TcpListener::bind(&address)?;
+            eprintln!("waiting for GDB on {address}");
+            let (mut stream, peer) = listener.accept()?;
+            stream.set_nodelay(true)?;
+            eprintln!("GDB connected from {peer}");
+            stub.serve(&mut stream)
+        }
+        #[cfg(unix)]
+        GdbListen::Unix(path) => {
+            let listener = UnixListener::bind(&path)?;
+            eprintln!("waiting for GDB on {path}");
+            let (mut stream, _) = listener.accept()?;
+            let result = stub.serve(&mut stream);
+            std::fs::remove_file(&path)?;
+            result
+        }
+    }
 }
@@ -0,0 +1,24 @@
+use crate::shared::types::{Byte, Word};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BusAccessKind {
+    Read,
+    Write,
+}
+
+impl BusAccessKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BusAccessKind::Read => "read",
+            BusAccessKind::Write => "write",
+        }
+    }
+}
+
+// single data bus transaction, one per cycle on real hardware
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BusAccess {
+    pub address: Word,
+    pub value: Byte,
+    pub kind: BusAccessKind,
+}
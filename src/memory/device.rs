@@ -0,0 +1,39 @@
+use crate::shared::types::{Byte, Word};
+
+use super::{device_error::DeviceError, page_table::BankSwitch};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct DeviceId(pub(crate) u16);
+
+// memory mapped hardware, offsets are relative to the first mapped byte
+pub trait Device: Send {
+    fn name(&self) -> &'static str;
+
+    fn read(&mut self, offset: Word) -> Result<Byte, DeviceError>;
+
+    fn write(&mut self, offset: Word, value: Byte) -> Result<(), DeviceError>;
+
+    // side effect free read for debuggers, real reads may acknowledge interrupts etc.
+    fn peek(&self, offset: Word) -> Byte;
+
+    // polled after every write, lets mapper registers switch banks
+    fn take_bank_switch(&mut self) -> Option<BankSwitch> {
+        None
+    }
+
+    // registers and latches a save state has to bring back, empty for stateless devices
+    fn save_state(&self) -> Vec<Byte> {
+        Vec::new()
+    }
+
+    // gets what save_state returned, possibly from an earlier session
+    fn restore_state(&mut self, state: &[Byte]) -> Result<(), DeviceError> {
+        match state.is_empty() {
+            true => Ok(()),
+            false => Err(DeviceError::InvalidState {
+                device: self.name(),
+                length: state.len(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::{error::Error, fmt, io};
+
+use serde_json::{json, Value};
+
+use crate::shared::types::Word;
+
+// raised by memory mapped devices, offset is relative to the device base
+#[derive(Debug)]
+pub enum DeviceError {
+    UnmappedRegister {
+        device: &'static str,
+        offset: Word,
+    },
+    ReadOnlyRegister {
+        device: &'static str,
+        offset: Word,
+    },
+    Io {
+        device: &'static str,
+        source: io::Error,
+    },
+    // saved state the device can not take back
+    InvalidState {
+        device: &'static str,
+        length: usize,
+    },
+}
+
+impl DeviceError {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeviceError::UnmappedRegister { .. } => "unmappedRegister",
+            DeviceError::ReadOnlyRegister { .. } => "readOnlyRegister",
+            DeviceError::Io { .. } => "io",
+            DeviceError::InvalidState { .. } => "invalidState",
+        }
+    }
+
+    pub fn device(&self) -> &'static str {
+        match self {
+            DeviceError::UnmappedRegister { device, .. }
+            | DeviceError::ReadOnlyRegister { device, .. }
+            | DeviceError::Io { device, .. }
+            | DeviceError::InvalidState { device, .. } => device,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "error": self.name(),
+            "message": self.to_string(),
+            "device": self.device(),
+            "source": self.source().map(|source| source.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for DeviceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceError::UnmappedRegister { device, offset } => {
+                write!(f, "{device} has no register at offset {offset:#04X}")
+            }
+            DeviceError::ReadOnlyRegister { device, offset } => {
+                write!(f, "{device} register at offset {offset:#04X} is read only")
+            }
+            DeviceError::Io { device, .. } => write!(f, "{device} I/O failed"),
+            DeviceError::InvalidState { device, length } => {
+                write!(f, "{device} can not restore a state of {length} bytes")
+            }
+        }
+    }
+}
+
+impl Error for DeviceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DeviceError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
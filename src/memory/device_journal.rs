@@ -0,0 +1,34 @@
+use std::mem;
+
+use crate::shared::types::Byte;
+
+use super::{device::DeviceId, page_table::Page};
+
+// device side of the write journal, what one instruction changed outside of RAM
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DeviceJournal {
+    // save_state of every device the instruction read or wrote, taken before the first access
+    pub states: Vec<(DeviceId, Vec<Byte>)>,
+    // page table before the first bank switch
+    pub pages: Option<Vec<Page>>,
+}
+
+impl DeviceJournal {
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty() && self.pages.is_none()
+    }
+
+    // approximate heap footprint, used for the rewind budget
+    pub fn size_bytes(&self) -> usize {
+        self.states.capacity() * mem::size_of::<(DeviceId, Vec<Byte>)>()
+            + self
+                .states
+                .iter()
+                .map(|(_, state)| state.capacity())
+                .sum::<usize>()
+            + self
+                .pages
+                .as_ref()
+                .map_or(0, |pages| pages.capacity() * mem::size_of::<Page>())
+    }
+}
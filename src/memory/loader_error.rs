@@ -0,0 +1,69 @@
+use std::{error::Error, fmt, io};
+
+use serde_json::{json, Value};
+
+use crate::shared::types::Word;
+
+#[derive(Debug)]
+pub enum LoaderError {
+    Io {
+        path: String,
+        source: io::Error,
+    },
+    ImageTooLarge {
+        path: String,
+        size: usize,
+        available: usize,
+        address: Word,
+    },
+}
+
+impl LoaderError {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LoaderError::Io { .. } => "io",
+            LoaderError::ImageTooLarge { .. } => "imageTooLarge",
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        match self {
+            LoaderError::Io { path, .. } | LoaderError::ImageTooLarge { path, .. } => path,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "error": self.name(),
+            "message": self.to_string(),
+            "path": self.path(),
+            "source": self.source().map(|source| source.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for LoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoaderError::Io { path, .. } => write!(f, "failed to read image {path}"),
+            LoaderError::ImageTooLarge {
+                path,
+                size,
+                available,
+                address,
+            } => write!(
+                f,
+                "image {path} is {size} bytes, only {available} fit from {address:#06X}"
+            ),
+        }
+    }
+}
+
+impl Error for LoaderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoaderError::Io { source, .. } => Some(source),
+            LoaderError::ImageTooLarge { .. } => None,
+        }
+    }
+}
@@ -1,56 +1,871 @@
-use crate::shared::types::{Byte, Word};
+use std::{fs, ops::RangeInclusive};
 
-use super::memory_errors::MemoryError;
+use crate::{
+    coverage::coverage::{Coverage, CoverageFlags},
+    heatmap::heatmap::{AccessType, Heatmap},
+    shared::{
+        logger::{LogLevel, LoggingHw},
+        types::{Byte, Word},
+    },
+};
 
-const MEMORY_SIZE: usize = 1024 * 64;
-// map ROM address space as in NES - 0x8000 - 0xFFFF
-const ROM_ADDRESS_RANGE_START: usize = 0x8000;
-const ROM_ADDRESS_RANGE_END: usize = 0xFFFF;
+use super::{
+    bus_access::{BusAccess, BusAccessKind},
+    device::{Device, DeviceId},
+    device_journal::DeviceJournal,
+    loader_error::LoaderError,
+    memory_errors::MemoryError,
+    memory_snapshot::{DeviceSnapshot, MemorySnapshot},
+    page_table::{
+        page_of, power_on_page, Bank, Page, PageKind, MEMORY_SIZE, PAGE_COUNT, PAGE_SIZE,
+    },
+    power_on_pattern::PowerOnPattern,
+    watchpoint::{WatchKind, Watchpoint, WatchpointHit},
+};
 
 pub struct Memory {
-    // 6502 has 16 bit address bus, which gives it 64K(65536B) address space
-    data: [Byte; MEMORY_SIZE],
+    // every bank back to back, the first 64K back the 16 bit address space after power on
+    pub(super) storage: Vec<Byte>,
+    pub(super) banks: Vec<Bank>,
+    // 256 byte pages of the address space, reads and writes go through one lookup
+    pub(super) pages: [Page; PAGE_COUNT],
+    pub(super) devices: Vec<Box<dyn Device>>,
+    // test images (e.g. Klaus Dormann suites) expect the whole space to be RAM
+    rom_write_protected: bool,
+    // value of the last read or write, unmapped pages read it back
+    data_bus: Byte,
+    // any of the recorders below is active, accesses leave the fast path
+    is_observed: bool,
+    // filled only while conformance tests compare per-cycle activity
+    bus_log: Option<Vec<BusAccess>>,
+    // (address, previous value) of CPU writes, kept while rewind is recording
+    write_journal: Option<Vec<(Word, Byte)>>,
+    // device states and page table an instruction changed, kept next to write_journal
+    pub(super) device_journal: Option<DeviceJournal>,
+    // data accesses only, instruction fetches never trigger them
+    watchpoints: Vec<Watchpoint>,
+    next_watchpoint_id: u32,
+    watchpoint_hit: Option<WatchpointHit>,
+    // how every address was used, kept while coverage is on
+    coverage: Option<Coverage>,
+    // access counts per address, kept while the heatmap is on
+    heatmap: Option<Heatmap>,
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Memory {
-    pub fn new(path_to_rom: Option<&str>) -> Self {
-        let memory = Memory {
-            data: [0x0000; MEMORY_SIZE],
+    pub fn new() -> Self {
+        let mut memory = Memory {
+            storage: vec![0x00; MEMORY_SIZE],
+            banks: vec![Bank {
+                offset: 0,
+                pages: PAGE_COUNT,
+            }],
+            pages: [0; PAGE_COUNT].map(|_| Page {
+                kind: PageKind::Ram,
+                offset: 0,
+            }),
+            devices: Vec::new(),
+            rom_write_protected: true,
+            data_bus: 0x00,
+            is_observed: false,
+            bus_log: None,
+            write_journal: None,
+            device_journal: None,
+            watchpoints: Vec::new(),
+            next_watchpoint_id: 1,
+            watchpoint_hit: None,
+            coverage: None,
+            heatmap: None,
         };
+        memory.reset_page_table();
+        memory
+    }
+
+    // ROM image is aligned to the end of address space, so it brings its own vectors
+    pub fn with_rom(path_to_rom: &str) -> Result<Self, MemoryError> {
+        let mut memory = Memory::new();
+        memory.load_rom(path_to_rom).map_err(MemoryError::Rom)?;
+        Ok(memory)
+    }
 
-        // TODO: optionally load rom file on initialization, will decide loading flow later
-        match path_to_rom {
-            Some(str) => memory.load_bin(str),
-            None => (),
+    // load rom file from OS fs, image ends at 0xFFFF
+    pub fn load_rom(&mut self, path: &str) -> Result<usize, LoaderError> {
+        let image = read_image(path)?;
+
+        if image.len() > MEMORY_SIZE {
+            return Err(LoaderError::ImageTooLarge {
+                path: path.to_string(),
+                size: image.len(),
+                available: MEMORY_SIZE,
+                address: 0x0000,
+            });
         }
 
-        memory
+        let load_address = (MEMORY_SIZE - image.len()) as Word;
+        self.load(load_address, &image);
+
+        Ok(image.len())
+    }
+
+    // load raw binary from OS fs at given address
+    pub fn load_bin(&mut self, path: &str, address: Word) -> Result<usize, LoaderError> {
+        let image = read_image(path)?;
+        let available = MEMORY_SIZE - address as usize;
+
+        if image.len() > available {
+            return Err(LoaderError::ImageTooLarge {
+                path: path.to_string(),
+                size: image.len(),
+                available,
+                address,
+            });
+        }
+
+        self.load(address, &image);
+
+        Ok(image.len())
+    }
+
+    // power on contents of every page currently mapped as RAM
+    pub fn fill_ram(&mut self, pattern: PowerOnPattern) {
+        if let PowerOnPattern::Random { .. } = pattern {
+            self.log_fmt(
+                LogLevel::Info,
+                "fill_ram",
+                format_args!("RAM filled with {pattern}"),
+            );
+        }
+
+        let contents = pattern.generate(MEMORY_SIZE);
+        for (index, page) in self.pages.iter().enumerate() {
+            if page.kind == PageKind::Ram {
+                let offset = page.offset as usize;
+                self.storage[offset..offset + PAGE_SIZE]
+                    .copy_from_slice(&contents[index * PAGE_SIZE..(index + 1) * PAGE_SIZE]);
+            }
+        }
+    }
+
+    // copies an image into whatever RAM/ROM is mapped, ignoring ROM protection,
+    // device and unmapped pages are skipped, use poke to reach devices, caller guarantees bytes fit
+    pub fn load(&mut self, address: Word, bytes: &[Byte]) {
+        for (index, &value) in bytes.iter().enumerate() {
+            let address = address.wrapping_add(index as Word);
+            if let Some(offset) = self.storage_offset(address) {
+                self.storage[offset] = value;
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> MemorySnapshot {
+        MemorySnapshot {
+            data: self.storage.clone(),
+            bank_pages: self.banks.iter().map(|bank| bank.pages).collect(),
+            pages: self.pages.to_vec(),
+            rom_write_protected: self.rom_write_protected,
+            data_bus: self.data_bus,
+            devices: self
+                .devices
+                .iter()
+                .map(|device| DeviceSnapshot {
+                    name: device.name().to_string(),
+                    state: device.save_state(),
+                })
+                .collect(),
+        }
+    }
+
+    // watchpoints, bus log and devices belong to the session and are kept, every device
+    // the snapshot saw has to be mapped again in the same order, nothing changes otherwise
+    pub fn restore(&mut self, snapshot: &MemorySnapshot) -> Result<(), MemoryError> {
+        for (index, saved) in snapshot.devices.iter().enumerate() {
+            if self.devices.get(index).map(|device| device.name()) != Some(saved.name.as_str()) {
+                return Err(MemoryError::MissingDevice {
+                    id: DeviceId(index as u16),
+                    name: Some(saved.name.clone()),
+                });
+            }
+        }
+        // a page can only point at a device the snapshot carries
+        let unknown_device = snapshot.pages.iter().find_map(|page| match page.kind {
+            PageKind::Device(id) if id.0 as usize >= snapshot.devices.len() => Some(id),
+            _ => None,
+        });
+        if let Some(id) = unknown_device {
+            return Err(MemoryError::MissingDevice { id, name: None });
+        }
+
+        // a device can reject its state halfway through, the ones restored before it go back
+        let previous: Vec<Vec<Byte>> = self
+            .devices
+            .iter()
+            .map(|device| device.save_state())
+            .collect();
+        for (index, saved) in snapshot.devices.iter().enumerate() {
+            if let Err(source) = self.devices[index].restore_state(&saved.state) {
+                for (device, state) in self.devices.iter_mut().zip(&previous).take(index) {
+                    // states a device saved itself always load back
+                    let _ = device.restore_state(state);
+                }
+                return Err(MemoryError::DeviceState {
+                    id: DeviceId(index as u16),
+                    source: Box::new(source),
+                });
+            }
+        }
+
+        self.storage.clone_from(&snapshot.data);
+        self.banks.clear();
+        let mut offset = 0;
+        for &pages in &snapshot.bank_pages {
+            self.banks.push(Bank { offset, pages });
+            offset += pages * PAGE_SIZE;
+        }
+
+        for (index, &page) in snapshot.pages.iter().enumerate().take(PAGE_COUNT) {
+            self.pages[index] = page;
+        }
+        self.rom_write_protected = snapshot.rom_write_protected;
+        self.data_bus = snapshot.data_bus;
+        self.watchpoint_hit = None;
+
+        Ok(())
+    }
+
+    // when off, ROM pages take writes like RAM
+    pub fn set_rom_write_protected(&mut self, protected: bool) {
+        self.rom_write_protected = protected;
+    }
+
+    // power on mapping, base bank identity mapped with ROM in the upper half
+    pub fn reset_page_table(&mut self) {
+        for (index, page) in self.pages.iter_mut().enumerate() {
+            *page = power_on_page(index);
+        }
+    }
+
+    // CPU runs batched only while nothing watches the bus
+    pub fn is_observed(&self) -> bool {
+        self.is_observed
+    }
+
+    // called whenever one of the recorders is switched
+    fn refresh_observed(&mut self) {
+        self.is_observed = self.bus_log.is_some()
+            || self.write_journal.is_some()
+            || !self.watchpoints.is_empty()
+            || self.coverage.is_some()
+            || self.heatmap.is_some();
+    }
+
+    pub fn set_bus_logging(&mut self, enabled: bool) {
+        self.bus_log = enabled.then(Vec::new);
+        self.refresh_observed();
+    }
+
+    // returns accesses recorded since previous call
+    pub fn take_bus_log(&mut self) -> Vec<BusAccess> {
+        self.bus_log
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    // devices touched by CPU accesses are journaled too, host pokes are not
+    pub fn set_write_journaling(&mut self, enabled: bool) {
+        self.write_journal = enabled.then(Vec::new);
+        self.device_journal = enabled.then(DeviceJournal::default);
+        self.refresh_observed();
     }
 
-    // load rom file from OS fs
-    fn load_bin(&self, path: &str) {
-        todo!()
+    // returns writes recorded since previous call
+    pub fn take_write_journal(&mut self) -> Vec<(Word, Byte)> {
+        self.write_journal
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
     }
 
-    pub fn read(&self, address: Word) -> Result<Byte, MemoryError> {
-        if (address as usize) >= MEMORY_SIZE {
-            return Err(MemoryError::AddressOutOfBounds(address));
+    // returns device changes recorded since previous call
+    pub fn take_device_journal(&mut self) -> DeviceJournal {
+        self.device_journal
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    // puts devices and page table back to where the journal was taken
+    pub(crate) fn undo_device_journal(
+        &mut self,
+        journal: &DeviceJournal,
+    ) -> Result<(), MemoryError> {
+        for (id, state) in &journal.states {
+            let device = self
+                .devices
+                .get_mut(id.0 as usize)
+                .ok_or(MemoryError::MissingDevice {
+                    id: *id,
+                    name: None,
+                })?;
+            device
+                .restore_state(state)
+                .map_err(|source| MemoryError::DeviceState {
+                    id: *id,
+                    source: Box::new(source),
+                })?;
+        }
+        if let Some(pages) = &journal.pages {
+            self.pages.copy_from_slice(pages);
+        }
+
+        Ok(())
+    }
+
+    // coverage survives restore, it belongs to the session like watchpoints
+    pub fn set_coverage(&mut self, enabled: bool) {
+        if !enabled {
+            self.coverage = None;
+        } else if self.coverage.is_none() {
+            self.coverage = Some(Coverage::new());
+        }
+        self.refresh_observed();
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    pub fn coverage_mut(&mut self) -> Option<&mut Coverage> {
+        self.coverage.as_mut()
+    }
+
+    pub fn set_heatmap(&mut self, enabled: bool) {
+        if !enabled {
+            self.heatmap = None;
+        } else if self.heatmap.is_none() {
+            self.heatmap = Some(Heatmap::new());
+        }
+        self.refresh_observed();
+    }
+
+    pub fn heatmap(&self) -> Option<&Heatmap> {
+        self.heatmap.as_ref()
+    }
+
+    pub fn heatmap_mut(&mut self) -> Option<&mut Heatmap> {
+        self.heatmap.as_mut()
+    }
+
+    // instruction the following accesses are charged to in the heatmap
+    pub fn set_current_pc(&mut self, pc: Word) {
+        if let Some(heatmap) = self.heatmap.as_mut() {
+            heatmap.set_current_pc(pc);
+        }
+    }
+
+    pub fn add_watchpoint(&mut self, range: RangeInclusive<Word>, kind: WatchKind) -> u32 {
+        let id = self.next_watchpoint_id;
+        self.next_watchpoint_id += 1;
+        self.watchpoints.push(Watchpoint { id, range, kind });
+        self.refresh_observed();
+
+        id
+    }
+
+    pub fn remove_watchpoint(&mut self, id: u32) -> bool {
+        let count_before = self.watchpoints.len();
+        self.watchpoints.retain(|watchpoint| watchpoint.id != id);
+        self.refresh_observed();
+
+        self.watchpoints.len() != count_before
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    // CPU polls it after every instruction
+    pub fn take_watchpoint_hit(&mut self) -> Option<WatchpointHit> {
+        self.watchpoint_hit.take()
+    }
+
+    // side effect free read for debuggers and test harnesses
+    pub fn peek(&self, address: Word) -> Byte {
+        let page = self.pages[page_of(address)];
+        let offset = page.offset_of(address);
+
+        match page.kind {
+            PageKind::Ram | PageKind::Rom => self.storage[offset],
+            PageKind::Device(id) => self.devices[id.0 as usize].peek(offset as Word),
+            PageKind::Unmapped => self.data_bus,
         }
+    }
+
+    // host side write for debuggers and external input, ignores ROM protection like load,
+    // devices get a regular write, not a bus cycle so recorders and data bus are left alone
+    pub fn poke(&mut self, address: Word, value: Byte) -> Result<(), MemoryError> {
+        let page = self.pages[page_of(address)];
+        let offset = page.offset_of(address);
 
-        Ok(self.data[address as usize])
+        match page.kind {
+            PageKind::Ram | PageKind::Rom => self.storage[offset] = value,
+            PageKind::Device(id) => self.write_device(id, address, offset as Word, value)?,
+            // nothing there to hold the value, as for CPU writes
+            PageKind::Unmapped => {}
+        }
+
+        Ok(())
+    }
+
+    // last value driven on the data bus, what open bus reads return
+    pub fn data_bus(&self) -> Byte {
+        self.data_bus
+    }
+
+    pub(crate) fn set_data_bus(&mut self, value: Byte) {
+        self.data_bus = value;
+    }
+
+    // constant time whatever is mapped, RAM and ROM are a single index
+    #[inline(always)]
+    fn read_mapped(&mut self, address: Word) -> Result<Byte, MemoryError> {
+        let page = self.pages[page_of(address)];
+        let offset = page.offset_of(address);
+
+        let value = match page.kind {
+            PageKind::Ram | PageKind::Rom => self.storage[offset],
+            PageKind::Device(id) => self.read_device(id, address, offset as Word)?,
+            PageKind::Unmapped => self.data_bus,
+        };
+        self.data_bus = value;
+
+        Ok(value)
+    }
+
+    fn storage_offset(&self, address: Word) -> Option<usize> {
+        let page = self.pages[page_of(address)];
+
+        match page.kind {
+            PageKind::Ram | PageKind::Rom => Some(page.offset_of(address)),
+            PageKind::Device(_) | PageKind::Unmapped => None,
+        }
     }
 
+    // instruction stream read, same bus cycle as read but invisible to watchpoints
+    #[inline(always)]
+    pub fn fetch(&mut self, address: Word) -> Result<Byte, MemoryError> {
+        self.fetch_marked(address, CoverageFlags::OPERAND)
+    }
+
+    // first byte of an instruction, differs from fetch only for coverage
+    #[inline(always)]
+    pub fn fetch_opcode(&mut self, address: Word) -> Result<Byte, MemoryError> {
+        self.fetch_marked(address, CoverageFlags::OPCODE)
+    }
+
+    #[inline(always)]
+    fn fetch_marked(&mut self, address: Word, flags: CoverageFlags) -> Result<Byte, MemoryError> {
+        let value = self.read_mapped(address)?;
+        if !self.is_observed {
+            return Ok(value);
+        }
+
+        self.record_bus_access(address, value, BusAccessKind::Read);
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.mark(address, flags);
+        }
+        if let Some(heatmap) = self.heatmap.as_mut() {
+            heatmap.record(address, AccessType::Execute);
+        }
+
+        Ok(value)
+    }
+
+    #[inline(always)]
+    pub fn read(&mut self, address: Word) -> Result<Byte, MemoryError> {
+        let value = self.read_mapped(address)?;
+        if !self.is_observed {
+            return Ok(value);
+        }
+
+        self.record_bus_access(address, value, BusAccessKind::Read);
+        self.check_watchpoints(address, value, BusAccessKind::Read);
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.mark(address, CoverageFlags::DATA_READ);
+        }
+        if let Some(heatmap) = self.heatmap.as_mut() {
+            heatmap.record(address, AccessType::Read);
+        }
+
+        Ok(value)
+    }
+
+    #[inline(always)]
     pub fn write(&mut self, address: Word, value: Byte) -> Result<(), MemoryError> {
-        if (address as usize) >= MEMORY_SIZE {
-            return Err(MemoryError::AddressOutOfBounds(address));
+        let page = self.pages[page_of(address)];
+        let offset = page.offset_of(address);
+
+        match page.kind {
+            PageKind::Rom if self.rom_write_protected => self.ignore_rom_write(address),
+            PageKind::Ram | PageKind::Rom => {
+                if let Some(write_journal) = self.write_journal.as_mut() {
+                    write_journal.push((address, self.storage[offset]));
+                }
+                self.storage[offset] = value;
+            }
+            PageKind::Device(id) => {
+                self.journal_device(id);
+                self.write_device(id, address, offset as Word, value)?
+            }
+            PageKind::Unmapped => {}
         }
+        self.data_bus = value;
 
-        if (address as usize) >= ROM_ADDRESS_RANGE_START {
-            return Err(MemoryError::RomWriteAttempt(address));
+        if !self.is_observed {
+            return Ok(());
         }
 
-        self.data[address as usize] = value;
+        self.record_bus_access(address, value, BusAccessKind::Write);
+        self.check_watchpoints(address, value, BusAccessKind::Write);
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.mark(address, CoverageFlags::DATA_WRITE);
+        }
+        if let Some(heatmap) = self.heatmap.as_mut() {
+            heatmap.record(address, AccessType::Write);
+        }
 
         Ok(())
     }
+
+    // the bus cycle still happens, ROM just does not latch the value
+    #[cold]
+    fn ignore_rom_write(&self, address: Word) {
+        self.log_fmt(
+            LogLevel::Warn,
+            "write",
+            format_args!("write to {address:#06X} ignored, address belongs to ROM"),
+        );
+    }
+
+    fn check_watchpoints(&mut self, address: Word, value: Byte, access: BusAccessKind) {
+        if self.watchpoint_hit.is_some() {
+            return;
+        }
+
+        self.watchpoint_hit = self
+            .watchpoints
+            .iter()
+            .find(|watchpoint| {
+                watchpoint.kind.matches(access) && watchpoint.range.contains(&address)
+            })
+            .map(|watchpoint| WatchpointHit {
+                id: watchpoint.id,
+                address,
+                value,
+                access,
+            });
+    }
+
+    fn record_bus_access(&mut self, address: Word, value: Byte, kind: BusAccessKind) {
+        if let Some(bus_log) = self.bus_log.as_mut() {
+            bus_log.push(BusAccess {
+                address,
+                value,
+                kind,
+            });
+        }
+    }
+}
+
+impl LoggingHw for Memory {
+    fn hw_name(&self) -> &'static str {
+        "MEM"
+    }
+}
+
+fn read_image(path: &str) -> Result<Vec<Byte>, LoaderError> {
+    fs::read(path).map_err(|source| LoaderError::Io {
+        path: path.to_string(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::memory::{device_error::DeviceError, page_table::BankId, page_table::BankSwitch};
+
+    use super::*;
+
+    // one register, any write switches bank `bank` in at $C000 on write, takes 1 byte states only
+    struct Latch {
+        value: Byte,
+        switch: Option<BankSwitch>,
+        bank: Option<BankId>,
+    }
+
+    impl Latch {
+        fn new(value: Byte) -> Self {
+            Latch {
+                value,
+                switch: None,
+                bank: None,
+            }
+        }
+    }
+
+    impl Device for Latch {
+        fn name(&self) -> &'static str {
+            "latch"
+        }
+
+        fn read(&mut self, _offset: Word) -> Result<Byte, DeviceError> {
+            Ok(self.value)
+        }
+
+        fn write(&mut self, _offset: Word, value: Byte) -> Result<(), DeviceError> {
+            self.value = value;
+            self.switch = self.bank.map(|bank| BankSwitch {
+                first_page: 0xC0,
+                page_count: 1,
+                bank,
+                bank_page: 0,
+                is_writable: true,
+            });
+            Ok(())
+        }
+
+        fn peek(&self, _offset: Word) -> Byte {
+            self.value
+        }
+
+        fn take_bank_switch(&mut self) -> Option<BankSwitch> {
+            self.switch.take()
+        }
+
+        fn save_state(&self) -> Vec<Byte> {
+            vec![self.value]
+        }
+
+        fn restore_state(&mut self, state: &[Byte]) -> Result<(), DeviceError> {
+            match state {
+                [value] => {
+                    self.value = *value;
+                    Ok(())
+                }
+                _ => Err(DeviceError::InvalidState {
+                    device: self.name(),
+                    length: state.len(),
+                }),
+            }
+        }
+    }
+
+    fn rom_path(name: &str, bytes: &[Byte]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("cpu_emu_{name}_{}.bin", std::process::id()));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn with_rom_aligns_image_to_end_of_address_space() {
+        let path = rom_path("rom_end", &[0x11, 0x22, 0x33]);
+
+        let memory = Memory::with_rom(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(memory.peek(0xFFFD), 0x11);
+        assert_eq!(memory.peek(0xFFFF), 0x33);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn with_rom_returns_error_for_missing_image() {
+        let error = Memory::with_rom("/nonexistent/cpu_emu.rom")
+            .err()
+            .expect("missing image must fail");
+
+        assert_eq!(error.name(), "rom");
+        assert!(matches!(error, MemoryError::Rom(LoaderError::Io { .. })));
+    }
+
+    #[test]
+    fn power_on_page_table_maps_ram_below_rom() {
+        let mut memory = Memory::new();
+
+        assert_eq!(memory.page_kind(0x7FFF), PageKind::Ram);
+        assert_eq!(memory.page_kind(0x8000), PageKind::Rom);
+        assert_eq!(memory.page(0x1234).offset_of(0x1234), 0x1234);
+
+        memory.write(0x8000, 0x55).unwrap();
+        assert_eq!(memory.peek(0x8000), 0x00);
+        memory.set_rom_write_protected(false);
+        memory.write(0x8000, 0x55).unwrap();
+        assert_eq!(memory.peek(0x8000), 0x55);
+    }
+
+    #[test]
+    fn switch_bank_repoints_pages_without_copying() {
+        let mut memory = Memory::new();
+        let bank = memory.add_bank(&[0xAA; 0x180]);
+
+        assert_eq!(memory.bank_count(), 2);
+        memory
+            .switch_bank(BankSwitch {
+                first_page: 0x40,
+                page_count: 2,
+                bank,
+                bank_page: 0,
+                is_writable: true,
+            })
+            .unwrap();
+
+        assert_eq!(memory.read(0x4000).unwrap(), 0xAA);
+        // the bank is padded to whole pages
+        assert_eq!(memory.read(0x4180).unwrap(), 0x00);
+        memory.write(0x4000, 0x11).unwrap();
+        assert_eq!(memory.peek(0x4000), 0x11);
+
+        memory.reset_page_table();
+        assert_eq!(memory.peek(0x4000), 0x00);
+    }
+
+    #[test]
+    fn switch_bank_rejects_ranges_outside_bank_or_address_space() {
+        let mut memory = Memory::new();
+        let bank = memory.add_bank(&[0xAA; PAGE_SIZE]);
+        let switch = BankSwitch {
+            first_page: 0x40,
+            page_count: 2,
+            bank,
+            bank_page: 0,
+            is_writable: false,
+        };
+
+        assert!(matches!(
+            memory.switch_bank(switch),
+            Err(MemoryError::InvalidMapping {
+                first_page: 0x40,
+                page_count: 2
+            })
+        ));
+        assert!(memory
+            .switch_bank(BankSwitch {
+                first_page: 0xFF,
+                page_count: 2,
+                bank: BankId::BASE,
+                ..switch
+            })
+            .is_err());
+        assert_eq!(memory.page_kind(0x4000), PageKind::Ram);
+    }
+
+    #[test]
+    fn map_device_routes_accesses_to_the_device() {
+        let mut memory = Memory::new();
+
+        let id = memory.map_device(0xD0, 2, Latch::new(0x42)).unwrap();
+
+        assert_eq!(memory.page_kind(0xD1FF), PageKind::Device(id));
+        assert_eq!(memory.read(0xD105).unwrap(), 0x42);
+        memory.write(0xD010, 0x99).unwrap();
+        assert_eq!(memory.peek(0xD000), 0x99);
+        assert!(memory.device(id).is_some());
+        assert!(memory
+            .map_device(0xFF, 2, Latch::new(0x00))
+            .is_err_and(|error| error.name() == "invalidMapping"));
+    }
+
+    #[test]
+    fn device_write_switches_banks() {
+        let mut memory = Memory::new();
+        let bank = memory.add_bank(&[0xBB; PAGE_SIZE]);
+        let mut latch = Latch::new(0x00);
+        latch.bank = Some(bank);
+        memory.map_device(0xD0, 1, latch).unwrap();
+
+        memory.write(0xD000, 0x01).unwrap();
+
+        assert_eq!(memory.page_kind(0xC000), PageKind::Ram);
+        assert_eq!(memory.read(0xC000).unwrap(), 0xBB);
+    }
+
+    #[test]
+    fn unmapped_pages_read_open_bus_and_drop_writes() {
+        let mut memory = Memory::new();
+        memory.load(0x3000, &[0x77]);
+
+        memory.unmap_pages(0x30, 1).unwrap();
+        memory.write(0x3000, 0x12).unwrap();
+
+        assert_eq!(memory.page_kind(0x3000), PageKind::Unmapped);
+        assert_eq!(memory.read(0x3000).unwrap(), 0x12);
+        assert!(memory.unmap_pages(0xF0, 0x20).is_err());
+
+        memory.reset_page_table();
+        assert_eq!(memory.peek(0x3000), 0x77);
+    }
+
+    #[test]
+    fn restore_brings_back_storage_pages_and_devices() {
+        let mut memory = Memory::new();
+        let bank = memory.add_bank(&[0xBB; PAGE_SIZE]);
+        let mut latch = Latch::new(0x10);
+        latch.bank = Some(bank);
+        memory.map_device(0xD0, 1, latch).unwrap();
+        memory.load(0x0200, &[0x01]);
+        let snapshot = memory.snapshot();
+
+        memory.write(0xD000, 0x20).unwrap();
+        memory.write(0x0200, 0x02).unwrap();
+        memory.restore(&snapshot).unwrap();
+
+        assert_eq!(memory.peek(0xD000), 0x10);
+        assert_eq!(memory.peek(0x0200), 0x01);
+        assert_eq!(memory.page_kind(0xC000), PageKind::Rom);
+        assert!(memory.snapshot() == snapshot);
+    }
+
+    #[test]
+    fn restore_rejects_snapshots_with_other_devices() {
+        let mut saved = Memory::new();
+        saved.map_device(0xD0, 1, Latch::new(0x10)).unwrap();
+        let snapshot = saved.snapshot();
+
+        let result = Memory::new().restore(&snapshot);
+
+        assert!(matches!(
+            result,
+            Err(MemoryError::MissingDevice { id: DeviceId(0), name: Some(name) }) if name == "latch"
+        ));
+    }
+
+    #[test]
+    fn failed_device_restore_leaves_memory_unchanged() {
+        let mut memory = Memory::new();
+        memory.map_device(0xD0, 1, Latch::new(0x10)).unwrap();
+        memory.map_device(0xD1, 1, Latch::new(0x20)).unwrap();
+        let mut snapshot = memory.snapshot();
+        snapshot.devices[0].state = vec![0x11];
+        snapshot.devices[1].state = vec![0x21, 0x00];
+        snapshot.data[0x0200] = 0x33;
+        let before = memory.snapshot();
+
+        let result = memory.restore(&snapshot);
+
+        assert!(matches!(
+            result,
+            Err(MemoryError::DeviceState {
+                id: DeviceId(1),
+                ..
+            })
+        ));
+        assert!(memory.snapshot() == before);
+    }
 }
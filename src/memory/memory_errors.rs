@@ -1,26 +1,102 @@
-use chrono::Utc;
-use std::fmt;
+use std::{error::Error, fmt};
 
+use serde_json::{json, Value};
+
+use crate::shared::types::{Byte, Word};
+
+use super::{device::DeviceId, device_error::DeviceError, loader_error::LoaderError};
+
+// plain messages, level colours and timestamps are added by the logger
 #[derive(Debug)]
 pub enum MemoryError {
-    AddressOutOfBounds(u16),
-    RomWriteAttempt(u16),
+    // boxed, keeps Result of every bus access small
+    Device {
+        address: Word,
+        source: Box<DeviceError>,
+    },
+    // bank switch or device range outside of address space or bank
+    InvalidMapping {
+        first_page: Byte,
+        page_count: usize,
+    },
+    // snapshot refers to a device this memory has not mapped, name is the saved one
+    MissingDevice {
+        id: DeviceId,
+        name: Option<String>,
+    },
+    DeviceState {
+        id: DeviceId,
+        source: Box<DeviceError>,
+    },
+    // ROM image given at construction could not be loaded
+    Rom(LoaderError),
+}
+
+impl MemoryError {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MemoryError::Device { .. } => "device",
+            MemoryError::InvalidMapping { .. } => "invalidMapping",
+            MemoryError::MissingDevice { .. } => "missingDevice",
+            MemoryError::DeviceState { .. } => "deviceState",
+            MemoryError::Rom(_) => "rom",
+        }
+    }
+
+    // none for errors outside of bus accesses
+    pub fn address(&self) -> Option<Word> {
+        match self {
+            MemoryError::Device { address, .. } => Some(*address),
+            MemoryError::InvalidMapping { first_page, .. } => Some(Word::from(*first_page) << 8),
+            MemoryError::MissingDevice { .. }
+            | MemoryError::DeviceState { .. }
+            | MemoryError::Rom(_) => None,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "error": self.name(),
+            "message": self.to_string(),
+            "address": self.address(),
+            "source": self.source().map(|source| source.to_string()),
+        })
+    }
 }
 
 impl fmt::Display for MemoryError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let now_date = Utc::now()
-            .date_naive()
-            .format("%Y-%m-%d %H:%M:%S")
-            .to_string();
-
         match self {
-            MemoryError::AddressOutOfBounds(address) => {
-                writeln!(f, "\x1b[93m[ERROR]\x1b[0m [{:#}]: Tried to access memory at {:#06X}, address is out of bounds", now_date, address)
+            MemoryError::Device { address, .. } => {
+                write!(f, "device access at {address:#06X} failed")
+            }
+            MemoryError::InvalidMapping {
+                first_page,
+                page_count,
+            } => write!(
+                f,
+                "{page_count} pages from page {first_page:#04X} can not be mapped"
+            ),
+            MemoryError::MissingDevice { id, name } => match name {
+                Some(name) => write!(f, "saved device {name} (#{}) is not mapped", id.0),
+                None => write!(f, "saved device #{} is not mapped", id.0),
+            },
+            MemoryError::DeviceState { id, .. } => {
+                write!(f, "restoring state of device #{} failed", id.0)
             }
-            MemoryError::RomWriteAttempt(address) => {
-                writeln!(f, "\x1b[93m[ERROR]\x1b[0m [{:#}]: Tried to write memory at {:#06X}, address belongs to ROM", now_date, address)
+            MemoryError::Rom(_) => write!(f, "loading ROM failed"),
+        }
+    }
+}
+
+impl Error for MemoryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MemoryError::Device { source, .. } | MemoryError::DeviceState { source, .. } => {
+                Some(source.as_ref())
             }
+            MemoryError::Rom(source) => Some(source),
+            _ => None,
         }
     }
 }
@@ -0,0 +1,181 @@
+use crate::shared::types::{Byte, Word};
+
+use super::{
+    device::{Device, DeviceId},
+    memory::Memory,
+    memory_errors::MemoryError,
+    page_table::{page_of, Bank, BankId, BankSwitch, Page, PageKind, PAGE_COUNT, PAGE_SIZE},
+};
+
+impl Memory {
+    pub fn page(&self, address: Word) -> Page {
+        self.pages[page_of(address)]
+    }
+
+    pub fn page_kind(&self, address: Word) -> PageKind {
+        self.page(address).kind
+    }
+
+    // appends bank storage, padded to whole pages, it stays invisible until switched in
+    pub fn add_bank(&mut self, bytes: &[Byte]) -> BankId {
+        let pages = bytes.len().div_ceil(PAGE_SIZE);
+        let offset = self.storage.len();
+
+        self.storage.extend_from_slice(bytes);
+        self.storage.resize(offset + pages * PAGE_SIZE, 0x00);
+        self.banks.push(Bank { offset, pages });
+
+        BankId(self.banks.len() - 1)
+    }
+
+    pub fn bank_count(&self) -> usize {
+        self.banks.len()
+    }
+
+    // repoints pages at bank storage, contents are not copied
+    pub fn switch_bank(&mut self, switch: BankSwitch) -> Result<(), MemoryError> {
+        let invalid_mapping = MemoryError::InvalidMapping {
+            first_page: switch.first_page,
+            page_count: switch.page_count,
+        };
+        let first_page = switch.first_page as usize;
+        let Some(bank) = self.banks.get(switch.bank.0) else {
+            return Err(invalid_mapping);
+        };
+        if first_page + switch.page_count > PAGE_COUNT
+            || switch.bank_page + switch.page_count > bank.pages
+        {
+            return Err(invalid_mapping);
+        }
+
+        let kind = match switch.is_writable {
+            true => PageKind::Ram,
+            false => PageKind::Rom,
+        };
+        let bank_offset = bank.offset + switch.bank_page * PAGE_SIZE;
+        for index in 0..switch.page_count {
+            self.pages[first_page + index] = Page {
+                kind,
+                offset: (bank_offset + index * PAGE_SIZE) as u32,
+            };
+        }
+
+        Ok(())
+    }
+
+    // reads from these pages return the open bus value, writes are dropped
+    pub fn unmap_pages(&mut self, first_page: Byte, page_count: usize) -> Result<(), MemoryError> {
+        let first = first_page as usize;
+        if first + page_count > PAGE_COUNT {
+            return Err(MemoryError::InvalidMapping {
+                first_page,
+                page_count,
+            });
+        }
+
+        for page in &mut self.pages[first..first + page_count] {
+            *page = Page {
+                kind: PageKind::Unmapped,
+                offset: 0,
+            };
+        }
+
+        Ok(())
+    }
+
+    // device sees offsets from the start of its first page
+    pub fn map_device<D: Device + 'static>(
+        &mut self,
+        first_page: Byte,
+        page_count: usize,
+        device: D,
+    ) -> Result<DeviceId, MemoryError> {
+        if first_page as usize + page_count > PAGE_COUNT {
+            return Err(MemoryError::InvalidMapping {
+                first_page,
+                page_count,
+            });
+        }
+
+        let id = DeviceId(self.devices.len() as u16);
+        self.devices.push(Box::new(device));
+        for index in 0..page_count {
+            self.pages[first_page as usize + index] = Page {
+                kind: PageKind::Device(id),
+                offset: (index * PAGE_SIZE) as u32,
+            };
+        }
+
+        Ok(id)
+    }
+
+    pub fn device(&self, id: DeviceId) -> Option<&dyn Device> {
+        self.devices
+            .get(id.0 as usize)
+            .map(|device| device.as_ref())
+    }
+
+    pub fn device_mut(&mut self, id: DeviceId) -> Option<&mut (dyn Device + 'static)> {
+        self.devices
+            .get_mut(id.0 as usize)
+            .map(|device| device.as_mut())
+    }
+
+    // kept out of line so RAM accesses stay small enough to inline
+    #[cold]
+    pub(super) fn read_device(
+        &mut self,
+        id: DeviceId,
+        address: Word,
+        offset: Word,
+    ) -> Result<Byte, MemoryError> {
+        // reads may acknowledge or advance device state
+        self.journal_device(id);
+        self.devices[id.0 as usize]
+            .read(offset)
+            .map_err(|source| MemoryError::Device {
+                address,
+                source: Box::new(source),
+            })
+    }
+
+    #[cold]
+    pub(super) fn write_device(
+        &mut self,
+        id: DeviceId,
+        address: Word,
+        offset: Word,
+        value: Byte,
+    ) -> Result<(), MemoryError> {
+        let device = &mut self.devices[id.0 as usize];
+        device
+            .write(offset, value)
+            .map_err(|source| MemoryError::Device {
+                address,
+                source: Box::new(source),
+            })?;
+
+        match device.take_bank_switch() {
+            Some(switch) => {
+                if let Some(journal) = self.device_journal.as_mut() {
+                    journal.pages.get_or_insert_with(|| self.pages.to_vec());
+                }
+                self.switch_bank(switch)
+            }
+            None => Ok(()),
+        }
+    }
+
+    // state before the first access of the journaled instruction
+    pub(super) fn journal_device(&mut self, id: DeviceId) {
+        let Some(journal) = self.device_journal.as_mut() else {
+            return;
+        };
+
+        if journal.states.iter().all(|(journaled, _)| *journaled != id) {
+            journal
+                .states
+                .push((id, self.devices[id.0 as usize].save_state()));
+        }
+    }
+}
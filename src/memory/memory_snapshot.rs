@@ -0,0 +1,23 @@
+use crate::shared::types::Byte;
+
+use super::page_table::Page;
+
+// everything a save state needs to rebuild Memory, debugger state is left out
+#[derive(Clone, PartialEq, Eq)]
+pub struct MemorySnapshot {
+    // every bank back to back, base 64K first
+    pub data: Vec<Byte>,
+    // size of every bank in pages, base included
+    pub bank_pages: Vec<usize>,
+    pub pages: Vec<Page>,
+    pub rom_write_protected: bool,
+    pub data_bus: Byte,
+    // indexed by DeviceId, restore needs the same devices mapped in the same order
+    pub devices: Vec<DeviceSnapshot>,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct DeviceSnapshot {
+    pub name: String,
+    pub state: Vec<Byte>,
+}
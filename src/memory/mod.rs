@@ -1,2 +1,12 @@
+pub mod bus_access;
+pub mod device;
+pub mod device_error;
+pub mod device_journal;
+pub mod loader_error;
 pub mod memory;
-mod memory_errors;
+pub mod memory_errors;
+mod memory_map;
+pub mod memory_snapshot;
+pub mod page_table;
+pub mod power_on_pattern;
+pub mod watchpoint;
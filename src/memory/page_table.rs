@@ -0,0 +1,75 @@
+use crate::shared::types::{Byte, Word};
+
+use super::device::DeviceId;
+
+pub const PAGE_SIZE: usize = 0x100;
+pub const PAGE_COUNT: usize = 0x100;
+// 16 bit address space, what the base bank holds
+pub const MEMORY_SIZE: usize = PAGE_COUNT * PAGE_SIZE;
+// map ROM address space as in NES - 0x8000 - 0xFFFF
+const ROM_FIRST_PAGE: usize = 0x80;
+
+// what answers accesses to a 256 byte page
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PageKind {
+    Ram,
+    // reads as RAM, writes are ignored while ROM is write protected
+    Rom,
+    Device(DeviceId),
+    // nothing answers, reads return whatever is left on the data bus
+    Unmapped,
+}
+
+// one page table entry, offset points into bank storage for RAM/ROM
+// and into the device's range for devices, kept at 8 bytes for the lookup
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Page {
+    pub kind: PageKind,
+    pub offset: u32,
+}
+
+impl Page {
+    #[inline]
+    pub fn offset_of(&self, address: Word) -> usize {
+        self.offset as usize + (address as usize % PAGE_SIZE)
+    }
+}
+
+// bank 0 is the 64K the address space is mapped to after power on
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BankId(pub(super) usize);
+
+impl BankId {
+    pub const BASE: BankId = BankId(0);
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(super) struct Bank {
+    pub(super) offset: usize,
+    pub(super) pages: usize,
+}
+
+// points page_count pages from first_page at the bank, starting from its bank_page
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BankSwitch {
+    pub first_page: Byte,
+    pub page_count: usize,
+    pub bank: BankId,
+    pub bank_page: usize,
+    pub is_writable: bool,
+}
+
+pub fn page_of(address: Word) -> usize {
+    (address >> 8) as usize
+}
+
+// base bank identity mapped, upper half is ROM
+pub fn power_on_page(index: usize) -> Page {
+    Page {
+        kind: match index >= ROM_FIRST_PAGE {
+            true => PageKind::Rom,
+            false => PageKind::Ram,
+        },
+        offset: (index * PAGE_SIZE) as u32,
+    }
+}
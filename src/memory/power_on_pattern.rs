@@ -0,0 +1,181 @@
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::shared::types::Byte;
+
+const C64_STRIPE: usize = 64;
+const C64_PATTERN_INVERT: usize = 0x4000;
+const NES_STRIPE: usize = 4;
+// xorshift gets stuck on zero state
+const RANDOM_ZERO_SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+// RAM contents before the program writes anything, some software and test ROMs read them
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum PowerOnPattern {
+    #[default]
+    Zeros,
+    Ones,
+    // same seed gives the same contents, runs stay reproducible
+    Random {
+        seed: u64,
+    },
+    // 64 byte stripes of $00/$FF, inverted every 16K, VICE default
+    C64,
+    // four $00 then four $FF, common NES emulator default
+    Nes,
+}
+
+impl PowerOnPattern {
+    // "zeros", "ones", "c64", "nes", "random" or "random:<seed>", plain random picks a new seed
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_ascii_lowercase().as_str() {
+            "zeros" | "00" => Some(PowerOnPattern::Zeros),
+            "ones" | "ff" => Some(PowerOnPattern::Ones),
+            "c64" => Some(PowerOnPattern::C64),
+            "nes" => Some(PowerOnPattern::Nes),
+            "random" => Some(PowerOnPattern::Random {
+                seed: entropy_seed(),
+            }),
+            input => input
+                .strip_prefix("random:")
+                .and_then(|seed| seed.parse().ok())
+                .map(|seed| PowerOnPattern::Random { seed }),
+        }
+    }
+
+    // contents for the first length bytes of the address space
+    pub fn generate(&self, length: usize) -> Vec<Byte> {
+        match *self {
+            PowerOnPattern::Zeros => vec![0x00; length],
+            PowerOnPattern::Ones => vec![0xFF; length],
+            PowerOnPattern::Random { seed } => {
+                let mut state = if seed == 0 { RANDOM_ZERO_SEED } else { seed };
+                (0..length)
+                    .map(|_| {
+                        // xorshift64*
+                        state ^= state >> 12;
+                        state ^= state << 25;
+                        state ^= state >> 27;
+                        (state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as Byte
+                    })
+                    .collect()
+            }
+            PowerOnPattern::C64 => (0..length)
+                .map(|address| {
+                    let is_set = (address / C64_STRIPE) % 2 != (address / C64_PATTERN_INVERT) % 2;
+                    if is_set {
+                        0xFF
+                    } else {
+                        0x00
+                    }
+                })
+                .collect(),
+            PowerOnPattern::Nes => (0..length)
+                .map(|address| match (address / NES_STRIPE) % 2 {
+                    0 => 0x00,
+                    _ => 0xFF,
+                })
+                .collect(),
+        }
+    }
+}
+
+// same syntax parse takes, random shows its seed so a run can be repeated
+impl fmt::Display for PowerOnPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PowerOnPattern::Zeros => write!(f, "zeros"),
+            PowerOnPattern::Ones => write!(f, "ones"),
+            PowerOnPattern::Random { seed } => write!(f, "random:{seed}"),
+            PowerOnPattern::C64 => write!(f, "c64"),
+            PowerOnPattern::Nes => write!(f, "nes"),
+        }
+    }
+}
+
+// RandomState keys come from the OS, the clock mixes in more on every call
+fn entropy_seed() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    hasher.write_u128(nanos);
+
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_patterns() {
+        assert_eq!(PowerOnPattern::Zeros.generate(3), [0x00; 3]);
+        assert_eq!(PowerOnPattern::Ones.generate(3), [0xFF; 3]);
+        assert_eq!(
+            PowerOnPattern::Nes.generate(10),
+            [0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn c64_stripes_invert_every_16k() {
+        let ram = PowerOnPattern::C64.generate(0x10000);
+
+        assert_eq!(ram[0x0000], 0x00);
+        assert_eq!(ram[0x003F], 0x00);
+        assert_eq!(ram[0x0040], 0xFF);
+        assert_eq!(ram[0x3FFF], 0xFF);
+        assert_eq!(ram[0x4000], 0xFF);
+        assert_eq!(ram[0x4040], 0x00);
+        assert_eq!(ram[0x8000], 0x00);
+    }
+
+    #[test]
+    fn random_is_reproducible_from_its_seed() {
+        let ram = PowerOnPattern::Random { seed: 42 }.generate(256);
+
+        assert_eq!(ram, PowerOnPattern::Random { seed: 42 }.generate(256));
+        assert_ne!(ram, PowerOnPattern::Random { seed: 43 }.generate(256));
+        // a shorter fill is a prefix of a longer one
+        assert_eq!(PowerOnPattern::Random { seed: 42 }.generate(16), ram[..16]);
+        assert!(ram.iter().any(|&byte| byte != ram[0]));
+    }
+
+    #[test]
+    fn random_zero_seed_does_not_stick() {
+        let ram = PowerOnPattern::Random { seed: 0 }.generate(64);
+
+        assert!(ram.iter().any(|&byte| byte != 0x00));
+    }
+
+    #[test]
+    fn display_round_trips_through_parse() {
+        for pattern in [
+            PowerOnPattern::Zeros,
+            PowerOnPattern::Ones,
+            PowerOnPattern::C64,
+            PowerOnPattern::Nes,
+            PowerOnPattern::Random { seed: 1234 },
+        ] {
+            assert_eq!(PowerOnPattern::parse(&pattern.to_string()), Some(pattern));
+        }
+    }
+
+    #[test]
+    fn parse_accepts_aliases_and_rejects_bad_seeds() {
+        assert_eq!(PowerOnPattern::parse(" FF "), Some(PowerOnPattern::Ones));
+        assert_eq!(PowerOnPattern::parse("00"), Some(PowerOnPattern::Zeros));
+        assert!(matches!(
+            PowerOnPattern::parse("Random"),
+            Some(PowerOnPattern::Random { .. })
+        ));
+        assert_eq!(PowerOnPattern::parse("random:"), None);
+        assert_eq!(PowerOnPattern::parse("random:-1"), None);
+        assert_eq!(PowerOnPattern::parse("stripes"), None);
+    }
+}
@@ -0,0 +1,39 @@
+use std::ops::RangeInclusive;
+
+use crate::shared::types::{Byte, Word};
+
+use super::bus_access::BusAccessKind;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum WatchKind {
+    Read,
+    Write,
+    Access,
+}
+
+impl WatchKind {
+    pub fn matches(&self, access: BusAccessKind) -> bool {
+        matches!(
+            (self, access),
+            (WatchKind::Access, _)
+                | (WatchKind::Read, BusAccessKind::Read)
+                | (WatchKind::Write, BusAccessKind::Write)
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Watchpoint {
+    pub id: u32,
+    pub range: RangeInclusive<Word>,
+    pub kind: WatchKind,
+}
+
+// first data access that matched a watchpoint during current instruction
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct WatchpointHit {
+    pub id: u32,
+    pub address: Word,
+    pub value: Byte,
+    pub access: BusAccessKind,
+}
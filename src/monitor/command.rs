@@ -0,0 +1,515 @@
+use crate::{
+    cpu::{
+        breakpoints::condition::Condition,
+        rewind::rewind_history::{BYTES_PER_MEGABYTE, DEFAULT_REWIND_BUDGET_BYTES},
+    },
+    heatmap::heatmap::AccessType,
+    memory::watchpoint::WatchKind,
+    shared::{
+        parsing::{parse_hex_byte, parse_hex_word},
+        types::{Byte, Word},
+    },
+    symbols::symbol_table::SymbolTable,
+    throttle::clock_preset::ClockPreset,
+};
+
+pub const HELP_TEXT: &str = "\
+commands (addresses and values are hex, addresses may also be symbol names):
+  s, step [n]              execute n instructions (default 1)
+  n, next                  step over JSR
+  c, continue              run until breakpoint, watchpoint, trap or unimplemented opcode
+  clock [ntsc|pal|c64|1mhz|<hz>|<n>mhz|off]
+                           show or set real-time pacing for continue
+  speed <x>                run paced clock x times faster (0.5 = half speed)
+  turbo on|off             run continue unpaced, still measuring MHz
+  rewind [on [mb]|off]     show or switch recording for reverse execution (default 64 MB)
+  bs, back [n]             step n instructions backwards (default 1)
+  rc, rcontinue            run backwards until breakpoint or watched write
+  lastwrite <addr>         go back to the instruction that last wrote addr
+  profile [on|off|clear]   show or switch instruction profiling
+  profile report [n]       n hottest addresses, opcodes and subroutines (default 20)
+  profile folded <file>    write folded call stacks for flamegraph tools
+  cov [on|off|clear]       show or switch code coverage tracking
+  cov map [<s>-<e>] <file> write per address coverage map (default whole memory)
+  cov listing [<s>-<e>] <file>
+                           write disassembly annotated with coverage
+  heat [on|off|clear]      show or switch per address access counting
+  heat top [r|w|x] [n]     n most accessed addresses (default all types, 20)
+  heat at <addr>           access counts and last PC of addr
+  heat csv|pgm|ppm <file>  export counts as CSV or 256x256 image, one page per row
+  r, regs                  show registers
+  set <reg> <value>        set A, X, Y, SP, PC or P
+  m, mem <addr> [len]      dump memory (default 64 bytes)
+  w, write <addr> <b>...   write bytes to memory
+  d, disasm [addr] [n]     disassemble n instructions (default 10 from PC)
+  b, break <addr> [if <c>] add breakpoint, optionally conditional
+  b, break if <c>          break before any instruction where condition holds
+  bd, delete <id>          delete breakpoint
+  watch <addr>[-<end>] [r|w|rw]
+                           add data watchpoint (default rw)
+  wd <id>                  delete watchpoint
+  bl, breaks               list breakpoints and watchpoints
+  sym [text]               list symbols, optionally only names containing text
+  irq on|off               assert or release IRQ line
+  nmi                      trigger NMI
+  input <addr> <b>         external device puts byte into register at addr
+  record <name>            save state to <name>.state and log external events
+  record stop              write recorded events to <name>.events
+  replay <name>            restore <name>.state and feed back <name>.events
+  save <file>              write save state of CPU and memory
+  restore <file>           load save state written by save
+  reset                    reset CPU
+  h, help                  show this text
+  q, quit                  leave monitor
+empty line repeats last step/next
+conditions: A X Y SP PC P, [addr] memory byte, == != < <= > >= & | ^ ! && ||
+  e.g. b if A == $10 && [$0200] & $80 != 0";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RegisterName {
+    A,
+    X,
+    Y,
+    SP,
+    PC,
+    P,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RewindSetting {
+    Status,
+    // budget in bytes
+    On(usize),
+    Off,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ProfileCommand {
+    Status,
+    On,
+    Off,
+    Clear,
+    // rows per section
+    Report(usize),
+    Folded(String),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CoverageCommand {
+    Status,
+    On,
+    Off,
+    Clear,
+    Map { range: (Word, Word), path: String },
+    Listing { range: (Word, Word), path: String },
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum HeatmapCommand {
+    Status,
+    On,
+    Off,
+    Clear,
+    Top {
+        access: Option<AccessType>,
+        limit: usize,
+    },
+    At(Word),
+    Csv(String),
+    Pgm(String),
+    Ppm(String),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ThrottleSetting {
+    Status,
+    Clock(ClockPreset),
+    Off,
+    // multiplier of the clock, always positive
+    Speed(f64),
+    Turbo(bool),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Command {
+    Step(usize),
+    Next,
+    Continue,
+    Throttle(ThrottleSetting),
+    Rewind(RewindSetting),
+    StepBack(usize),
+    ReverseContinue,
+    LastWrite(Word),
+    Profile(ProfileCommand),
+    Coverage(CoverageCommand),
+    Heatmap(HeatmapCommand),
+    Registers,
+    SetRegister(RegisterName, Word),
+    MemoryDump {
+        address: Word,
+        length: Word,
+    },
+    MemoryWrite {
+        address: Word,
+        bytes: Vec<Byte>,
+    },
+    Disassemble {
+        address: Option<Word>,
+        count: usize,
+    },
+    BreakpointAdd {
+        address: Option<Word>,
+        condition: Option<Condition>,
+    },
+    BreakpointDelete(u32),
+    WatchpointAdd {
+        start: Word,
+        end: Word,
+        kind: WatchKind,
+    },
+    WatchpointDelete(u32),
+    BreakpointList,
+    Symbols(Option<String>),
+    Irq(bool),
+    Nmi,
+    Input {
+        address: Word,
+        value: Byte,
+    },
+    RecordStart(String),
+    RecordStop,
+    Replay(String),
+    SaveState(String),
+    RestoreState(String),
+    Reset,
+    Help,
+    Quit,
+}
+
+impl Command {
+    pub fn parse(line: &str, symbols: &SymbolTable) -> Result<Command, String> {
+        let mut tokens = line.split_whitespace();
+        let Some(name) = tokens.next() else {
+            return Err("empty command".to_string());
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        let command = match name.to_ascii_lowercase().as_str() {
+            "s" | "step" => Command::Step(match args.first() {
+                Some(count) => count
+                    .parse()
+                    .map_err(|_| format!("invalid count {count}"))?,
+                None => 1,
+            }),
+            "n" | "next" => Command::Next,
+            "c" | "continue" => Command::Continue,
+            "clock" => Command::Throttle(match args.as_slice() {
+                [] => ThrottleSetting::Status,
+                ["off"] => ThrottleSetting::Off,
+                [clock] => ThrottleSetting::Clock(
+                    ClockPreset::parse(clock).ok_or(format!("unknown clock {clock}"))?,
+                ),
+                _ => return Err("usage: clock [preset|hz|off]".to_string()),
+            }),
+            "speed" => {
+                let speed = args
+                    .first()
+                    .and_then(|speed| speed.parse::<f64>().ok())
+                    .filter(|speed| *speed > 0.0 && speed.is_finite())
+                    .ok_or("usage: speed <multiplier>")?;
+                Command::Throttle(ThrottleSetting::Speed(speed))
+            }
+            "turbo" => Command::Throttle(match args.as_slice() {
+                ["on"] => ThrottleSetting::Turbo(true),
+                ["off"] => ThrottleSetting::Turbo(false),
+                _ => return Err("usage: turbo on|off".to_string()),
+            }),
+            "rewind" => Command::Rewind(parse_rewind(&args)?),
+            "bs" | "back" => Command::StepBack(match args.first() {
+                Some(count) => count
+                    .parse()
+                    .map_err(|_| format!("invalid count {count}"))?,
+                None => 1,
+            }),
+            "rc" | "rcontinue" => Command::ReverseContinue,
+            "lastwrite" => Command::LastWrite(address_arg(
+                args.first().ok_or("usage: lastwrite <addr>")?,
+                symbols,
+            )?),
+            "profile" => Command::Profile(parse_profile(&args)?),
+            "cov" | "coverage" => Command::Coverage(parse_coverage(&args, symbols)?),
+            "heat" => Command::Heatmap(parse_heatmap(&args, symbols)?),
+            "r" | "regs" => Command::Registers,
+            "set" => {
+                let [register, value] = args.as_slice() else {
+                    return Err("usage: set <reg> <value>".to_string());
+                };
+                Command::SetRegister(parse_register(register)?, word_arg(value)?)
+            }
+            "m" | "mem" => Command::MemoryDump {
+                address: address_arg(args.first().ok_or("usage: mem <addr> [len]")?, symbols)?,
+                length: args.get(1).map_or(Ok(64), |length| word_arg(length))?,
+            },
+            "w" | "write" => {
+                let (address, bytes) = args
+                    .split_first()
+                    .filter(|(_, bytes)| !bytes.is_empty())
+                    .ok_or("usage: write <addr> <byte>...")?;
+                Command::MemoryWrite {
+                    address: address_arg(address, symbols)?,
+                    bytes: bytes
+                        .iter()
+                        .map(|byte| parse_hex_byte(byte).ok_or(format!("invalid byte {byte}")))
+                        .collect::<Result<_, _>>()?,
+                }
+            }
+            "d" | "disasm" => Command::Disassemble {
+                address: args
+                    .first()
+                    .map(|address| address_arg(address, symbols))
+                    .transpose()?,
+                count: match args.get(1) {
+                    Some(count) => count
+                        .parse()
+                        .map_err(|_| format!("invalid count {count}"))?,
+                    None => 10,
+                },
+            },
+            "b" | "break" => parse_breakpoint(&args, symbols)?,
+            "bd" | "delete" => Command::BreakpointDelete(id_arg(args.first())?),
+            "watch" => parse_watchpoint(&args, symbols)?,
+            "wd" => Command::WatchpointDelete(id_arg(args.first())?),
+            "bl" | "breaks" => Command::BreakpointList,
+            "sym" | "symbols" => Command::Symbols(args.first().map(|filter| filter.to_string())),
+            "irq" => match args.as_slice() {
+                ["on"] => Command::Irq(true),
+                ["off"] => Command::Irq(false),
+                _ => return Err("usage: irq on|off".to_string()),
+            },
+            "nmi" => Command::Nmi,
+            "input" => {
+                let [address, value] = args.as_slice() else {
+                    return Err("usage: input <addr> <byte>".to_string());
+                };
+                Command::Input {
+                    address: address_arg(address, symbols)?,
+                    value: parse_hex_byte(value).ok_or(format!("invalid byte {value}"))?,
+                }
+            }
+            "record" => match args.as_slice() {
+                ["stop"] => Command::RecordStop,
+                _ => Command::RecordStart(path_arg(&args, "usage: record <name> | record stop")?),
+            },
+            "replay" => Command::Replay(path_arg(&args, "usage: replay <name>")?),
+            "save" => Command::SaveState(path_arg(&args, "usage: save <file>")?),
+            "restore" => Command::RestoreState(path_arg(&args, "usage: restore <file>")?),
+            "reset" => Command::Reset,
+            "h" | "help" | "?" => Command::Help,
+            "q" | "quit" | "exit" => Command::Quit,
+            _ => return Err(format!("unknown command {name}, type help")),
+        };
+
+        Ok(command)
+    }
+
+    // commands that are repeated by an empty line
+    pub fn is_repeatable(&self) -> bool {
+        matches!(
+            self,
+            Command::Step(_) | Command::Next | Command::StepBack(_)
+        )
+    }
+}
+
+// break <addr> | break <addr> if <cond> | break if <cond>
+fn parse_breakpoint(args: &[&str], symbols: &SymbolTable) -> Result<Command, String> {
+    let (address_args, condition_args) = match args.iter().position(|arg| *arg == "if") {
+        Some(if_position) => (&args[..if_position], Some(&args[if_position + 1..])),
+        None => (args, None),
+    };
+
+    let address = match address_args {
+        [] => None,
+        [address] => Some(address_arg(address, symbols)?),
+        _ => return Err("usage: break <addr> [if <cond>] | break if <cond>".to_string()),
+    };
+
+    let condition = condition_args
+        .map(|condition| Condition::parse(&condition.join(" ")))
+        .transpose()
+        .map_err(|error| format!("invalid condition: {error}"))?;
+
+    if address.is_none() && condition.is_none() {
+        return Err("usage: break <addr> [if <cond>] | break if <cond>".to_string());
+    }
+
+    Ok(Command::BreakpointAdd { address, condition })
+}
+
+fn parse_rewind(args: &[&str]) -> Result<RewindSetting, String> {
+    match args {
+        [] => Ok(RewindSetting::Status),
+        ["on"] => Ok(RewindSetting::On(DEFAULT_REWIND_BUDGET_BYTES)),
+        ["on", megabytes] => megabytes
+            .parse::<usize>()
+            .ok()
+            .filter(|megabytes| *megabytes > 0)
+            .and_then(|megabytes| megabytes.checked_mul(BYTES_PER_MEGABYTE))
+            .map(RewindSetting::On)
+            .ok_or(format!("invalid budget {megabytes}")),
+        ["off"] => Ok(RewindSetting::Off),
+        _ => Err("usage: rewind [on [mb]|off]".to_string()),
+    }
+}
+
+fn parse_profile(args: &[&str]) -> Result<ProfileCommand, String> {
+    match args {
+        [] => Ok(ProfileCommand::Status),
+        ["on"] => Ok(ProfileCommand::On),
+        ["off"] => Ok(ProfileCommand::Off),
+        ["clear"] => Ok(ProfileCommand::Clear),
+        ["report"] => Ok(ProfileCommand::Report(20)),
+        ["report", rows] => rows
+            .parse()
+            .map(ProfileCommand::Report)
+            .map_err(|_| format!("invalid count {rows}")),
+        ["folded", path @ ..] => Ok(ProfileCommand::Folded(path_arg(
+            path,
+            "usage: profile folded <file>",
+        )?)),
+        _ => Err(
+            "usage: profile [on|off|clear] | profile report [n] | profile folded <file>"
+                .to_string(),
+        ),
+    }
+}
+
+fn parse_coverage(args: &[&str], symbols: &SymbolTable) -> Result<CoverageCommand, String> {
+    const USAGE: &str = "usage: cov [on|off|clear] | cov map|listing [<start>-<end>] <file>";
+
+    let (export, rest) = match args {
+        [] => return Ok(CoverageCommand::Status),
+        ["on"] => return Ok(CoverageCommand::On),
+        ["off"] => return Ok(CoverageCommand::Off),
+        ["clear"] => return Ok(CoverageCommand::Clear),
+        [export @ ("map" | "listing"), rest @ ..] => (*export, rest),
+        _ => return Err(USAGE.to_string()),
+    };
+
+    // leading range is optional, anything that is not one starts the path
+    let range = rest
+        .first()
+        .and_then(|range| range.split_once('-'))
+        .and_then(|(start, end)| {
+            Some((
+                address_arg(start, symbols).ok()?,
+                address_arg(end, symbols).ok()?,
+            ))
+        })
+        .filter(|_| rest.len() > 1);
+    let (range, path) = match range {
+        Some((start, end)) if end < start => {
+            return Err(format!("range end ${end:04X} is before start ${start:04X}"))
+        }
+        Some(range) => (range, path_arg(&rest[1..], USAGE)?),
+        None => ((0x0000, 0xFFFF), path_arg(rest, USAGE)?),
+    };
+
+    Ok(match export {
+        "map" => CoverageCommand::Map { range, path },
+        _ => CoverageCommand::Listing { range, path },
+    })
+}
+
+fn parse_heatmap(args: &[&str], symbols: &SymbolTable) -> Result<HeatmapCommand, String> {
+    const USAGE: &str = "usage: heat [on|off|clear] | heat top [r|w|x] [n] | heat at <addr> \
+| heat csv|pgm|ppm <file>";
+
+    match args {
+        [] => Ok(HeatmapCommand::Status),
+        ["on"] => Ok(HeatmapCommand::On),
+        ["off"] => Ok(HeatmapCommand::Off),
+        ["clear"] => Ok(HeatmapCommand::Clear),
+        ["top", rest @ ..] => {
+            let (access, rest) = match rest.split_first() {
+                Some((&"r", rest)) => (Some(AccessType::Read), rest),
+                Some((&"w", rest)) => (Some(AccessType::Write), rest),
+                Some((&"x", rest)) => (Some(AccessType::Execute), rest),
+                _ => (None, rest),
+            };
+            let limit = match rest {
+                [] => 20,
+                [limit] => limit
+                    .parse()
+                    .map_err(|_| format!("invalid count {limit}"))?,
+                _ => return Err(USAGE.to_string()),
+            };
+            Ok(HeatmapCommand::Top { access, limit })
+        }
+        ["at", address] => Ok(HeatmapCommand::At(address_arg(address, symbols)?)),
+        ["csv", path @ ..] => Ok(HeatmapCommand::Csv(path_arg(path, USAGE)?)),
+        ["pgm", path @ ..] => Ok(HeatmapCommand::Pgm(path_arg(path, USAGE)?)),
+        ["ppm", path @ ..] => Ok(HeatmapCommand::Ppm(path_arg(path, USAGE)?)),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn parse_watchpoint(args: &[&str], symbols: &SymbolTable) -> Result<Command, String> {
+    let Some(range) = args.first() else {
+        return Err("usage: watch <addr>[-<end>] [r|w|rw]".to_string());
+    };
+
+    let (start, end) = match range.split_once('-') {
+        Some((start, end)) => (address_arg(start, symbols)?, address_arg(end, symbols)?),
+        None => (address_arg(range, symbols)?, address_arg(range, symbols)?),
+    };
+    if end < start {
+        return Err(format!("range end ${end:04X} is before start ${start:04X}"));
+    }
+
+    let kind = match args.get(1).copied() {
+        None | Some("rw") => WatchKind::Access,
+        Some("r") => WatchKind::Read,
+        Some("w") => WatchKind::Write,
+        Some(kind) => return Err(format!("unknown watch kind {kind}, use r, w or rw")),
+    };
+
+    Ok(Command::WatchpointAdd { start, end, kind })
+}
+
+// rest of the line, so paths may contain spaces
+fn path_arg(args: &[&str], usage: &str) -> Result<String, String> {
+    if args.is_empty() {
+        return Err(usage.to_string());
+    }
+
+    Ok(args.join(" "))
+}
+
+fn id_arg(input: Option<&&str>) -> Result<u32, String> {
+    let input = input.ok_or("missing id")?;
+    input.parse().map_err(|_| format!("invalid id {input}"))
+}
+
+fn word_arg(input: &str) -> Result<Word, String> {
+    parse_hex_word(input).ok_or(format!("invalid value {input}"))
+}
+
+// symbol names win over hex, "$" forces a number
+fn address_arg(input: &str, symbols: &SymbolTable) -> Result<Word, String> {
+    symbols
+        .address_of(input)
+        .map_or_else(|| word_arg(input), Ok)
+}
+
+fn parse_register(input: &str) -> Result<RegisterName, String> {
+    match input.to_ascii_uppercase().as_str() {
+        "A" => Ok(RegisterName::A),
+        "X" => Ok(RegisterName::X),
+        "Y" => Ok(RegisterName::Y),
+        "SP" | "S" => Ok(RegisterName::SP),
+        "PC" => Ok(RegisterName::PC),
+        "P" | "SR" => Ok(RegisterName::P),
+        _ => Err(format!("unknown register {input}")),
+    }
+}
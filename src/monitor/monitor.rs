@@ -0,0 +1,690 @@
+use std::io::{self, BufRead, Write};
+
+use crate::{
+    cpu::{
+        breakpoints::stop_reason::StopReason,
+        cpu::CPU,
+        instruction_set::opcodes::{JSR_LENGTH, JSR_OPCODE},
+        rewind::rewind_history::{RewindHistory, DEFAULT_SNAPSHOT_INTERVAL},
+        step_result::StepResult,
+    },
+    disassembler::disassembler::{disassemble, disassemble_range},
+    heatmap::heatmap::AddressAccess,
+    replay::event_log::EventLog,
+    save_state::save_state::SaveState,
+    shared::{
+        error_chain::error_chain,
+        types::{Byte, Word},
+    },
+    symbols::symbol_table::SymbolTable,
+    throttle::{
+        clock_preset::ClockPreset,
+        throttle::{run_throttled, Throttle},
+    },
+};
+
+use super::command::{
+    Command, CoverageCommand, HeatmapCommand, ProfileCommand, RegisterName, RewindSetting,
+    ThrottleSetting, HELP_TEXT,
+};
+
+// keeps continue from hanging the monitor on a program that never stops
+const MAX_RUN_CYCLES: u64 = 500_000_000;
+
+pub struct Monitor {
+    cpu: CPU,
+    last_command: Option<Command>,
+    // base name of the running event recording
+    recording_name: Option<String>,
+    // continue runs at real-time speed while set
+    throttle: Option<Throttle>,
+}
+
+impl Monitor {
+    pub fn new(cpu: CPU) -> Self {
+        Self {
+            cpu,
+            last_command: None,
+            recording_name: None,
+            throttle: None,
+        }
+    }
+
+    pub fn set_throttle(&mut self, throttle: Option<Throttle>) {
+        self.throttle = throttle;
+    }
+
+    pub fn cpu(&self) -> &CPU {
+        &self.cpu
+    }
+
+    // reads commands until quit or end of input
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, output: &mut W) -> io::Result<()> {
+        writeln!(output, "{}", self.cpu.registers())?;
+        write!(output, "> ")?;
+        output.flush()?;
+
+        for line in input.lines() {
+            let line = line?;
+
+            let command = if line.trim().is_empty() {
+                self.last_command.clone()
+            } else {
+                match Command::parse(&line, self.cpu.symbols()) {
+                    Ok(command) => Some(command),
+                    Err(error) => {
+                        writeln!(output, "{error}")?;
+                        None
+                    }
+                }
+            };
+
+            if let Some(command) = command {
+                if command == Command::Quit {
+                    return self.finish_recording(output);
+                }
+
+                self.execute(&command, output)?;
+                self.last_command = command.is_repeatable().then_some(command);
+            }
+
+            write!(output, "> ")?;
+            output.flush()?;
+        }
+
+        self.finish_recording(output)
+    }
+
+    pub fn execute<W: Write>(&mut self, command: &Command, output: &mut W) -> io::Result<()> {
+        match command {
+            Command::Step(count) => {
+                for _ in 0..*count {
+                    let instruction = disassemble(self.cpu.memory(), self.cpu.program_counter());
+                    writeln!(
+                        output,
+                        "{}",
+                        instruction.line_with_symbols(self.cpu.symbols())
+                    )?;
+                    if !self.step_reporting(output)? {
+                        break;
+                    }
+                }
+                writeln!(output, "{}", self.cpu.registers())?;
+            }
+            Command::Next => {
+                let pc = self.cpu.program_counter();
+                if self.cpu.memory().peek(pc) == JSR_OPCODE {
+                    // temporary breakpoint on the return address
+                    let breakpoints = self.cpu.breakpoints_mut();
+                    let return_id = breakpoints.add(Some(pc.wrapping_add(JSR_LENGTH)), None);
+                    let stop_reason = self.cpu.run(Some(MAX_RUN_CYCLES));
+                    self.cpu.breakpoints_mut().remove(return_id);
+
+                    if !matches!(stop_reason, StopReason::Breakpoint { id, .. } if id == return_id)
+                    {
+                        self.report_stop(stop_reason, output)?;
+                    }
+                } else {
+                    self.step_reporting(output)?;
+                }
+                writeln!(output, "{}", self.cpu.registers())?;
+            }
+            Command::Continue => {
+                let stop_reason = match self.throttle.as_mut() {
+                    Some(throttle) => run_throttled(&mut self.cpu, throttle, Some(MAX_RUN_CYCLES)),
+                    None => self.cpu.run(Some(MAX_RUN_CYCLES)),
+                };
+                self.report_stop(stop_reason, output)?;
+                if let Some(throttle) = &self.throttle {
+                    writeln!(
+                        output,
+                        "effective {:.3} MHz",
+                        throttle.effective_mhz(self.cpu.cycles())
+                    )?;
+                }
+                writeln!(output, "{}", self.cpu.registers())?;
+            }
+            Command::Throttle(setting) => self.set_throttle_setting(*setting, output)?,
+            Command::Rewind(setting) => self.set_rewind(*setting, output)?,
+            Command::Profile(command) => self.profile(command, output)?,
+            Command::Coverage(command) => self.coverage(command, output)?,
+            Command::Heatmap(command) => self.heatmap(command, output)?,
+            Command::StepBack(count) => {
+                for _ in 0..*count {
+                    match self.cpu.step_back() {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            writeln!(output, "no more history")?;
+                            break;
+                        }
+                        Err(error) => {
+                            writeln!(output, "{}", error_chain(&error))?;
+                            break;
+                        }
+                    }
+                }
+                self.print_position(output)?;
+            }
+            Command::ReverseContinue => {
+                match self.cpu.reverse_continue() {
+                    Ok(stop_reason) => self.report_stop(stop_reason, output)?,
+                    Err(error) => writeln!(output, "{}", error_chain(&error))?,
+                }
+                self.print_position(output)?;
+            }
+            Command::LastWrite(address) => match self.cpu.step_back_to_write(*address) {
+                Ok(Some(_)) => self.print_position(output)?,
+                Ok(None) => writeln!(output, "no recorded write to ${address:04X}")?,
+                Err(error) => writeln!(output, "{}", error_chain(&error))?,
+            },
+            Command::Registers => {
+                writeln!(output, "{} CYC={}", self.cpu.registers(), self.cpu.cycles())?;
+            }
+            Command::SetRegister(register, value) => {
+                self.set_register(*register, *value, output)?
+            }
+            Command::MemoryDump { address, length } => {
+                self.dump_memory(*address, *length, output)?
+            }
+            Command::MemoryWrite { address, bytes } => {
+                if (*address as usize) + bytes.len() > 0x10000 {
+                    writeln!(output, "write past end of address space")?;
+                } else {
+                    // monitor edits bypass ROM protection on purpose
+                    for (index, &value) in bytes.iter().enumerate() {
+                        let address = *address + index as Word;
+                        if let Err(error) = self.cpu.memory_mut().poke(address, value) {
+                            writeln!(output, "{}", error_chain(&error))?;
+                            break;
+                        }
+                    }
+                }
+            }
+            Command::Disassemble { address, count } => {
+                let address = address.unwrap_or(self.cpu.program_counter());
+                let symbols = self.cpu.symbols();
+                for instruction in disassemble_range(self.cpu.memory(), address, *count) {
+                    if let Some(name) = symbols.name_for(instruction.address) {
+                        writeln!(output, "{name}:")?;
+                    }
+                    writeln!(output, "{}", instruction.line_with_symbols(symbols))?;
+                }
+            }
+            Command::BreakpointAdd { address, condition } => {
+                let id = self.cpu.breakpoints_mut().add(*address, condition.clone());
+                writeln!(output, "breakpoint {id} added")?;
+            }
+            Command::BreakpointDelete(id) => {
+                if !self.cpu.breakpoints_mut().remove(*id) {
+                    writeln!(output, "no breakpoint {id}")?;
+                }
+            }
+            Command::WatchpointAdd { start, end, kind } => {
+                let id = self.cpu.memory_mut().add_watchpoint(*start..=*end, *kind);
+                writeln!(output, "watchpoint {id} added")?;
+            }
+            Command::WatchpointDelete(id) => {
+                if !self.cpu.memory_mut().remove_watchpoint(*id) {
+                    writeln!(output, "no watchpoint {id}")?;
+                }
+            }
+            Command::BreakpointList => self.list_breakpoints(output)?,
+            Command::Symbols(filter) => {
+                let symbols = self.cpu.symbols().iter().filter(|(_, name)| {
+                    filter
+                        .as_ref()
+                        .is_none_or(|filter| name.contains(filter.as_str()))
+                });
+                for (address, name) in symbols {
+                    writeln!(output, "${address:04X}  {name}")?;
+                }
+            }
+            Command::Irq(asserted) => self.cpu.set_irq_line(*asserted),
+            Command::Nmi => self.cpu.trigger_nmi(),
+            Command::Input { address, value } => {
+                if let Err(error) = self.cpu.input_byte(*address, *value) {
+                    writeln!(output, "{}", error_chain(&error))?;
+                }
+            }
+            Command::RecordStart(name) => {
+                self.finish_recording(output)?;
+                match self.cpu.save_state().save(format!("{name}.state")) {
+                    Ok(()) => {
+                        self.cpu.start_recording();
+                        self.recording_name = Some(name.clone());
+                        writeln!(output, "recording external events")?;
+                    }
+                    Err(error) => writeln!(output, "failed to save state: {error}")?,
+                }
+            }
+            Command::RecordStop => {
+                if self.recording_name.is_none() {
+                    writeln!(output, "not recording")?;
+                }
+                self.finish_recording(output)?;
+            }
+            Command::Replay(name) => self.start_replay(name, output)?,
+            Command::SaveState(path) => match self.cpu.save_state().save(path) {
+                Ok(()) => writeln!(output, "state saved to {path}")?,
+                Err(error) => writeln!(output, "failed to save state: {error}")?,
+            },
+            Command::RestoreState(path) => {
+                match SaveState::load(path).and_then(|state| self.cpu.restore_state(&state)) {
+                    Ok(()) => writeln!(output, "{}", self.cpu.registers())?,
+                    Err(error) => writeln!(output, "failed to restore state: {error}")?,
+                }
+            }
+            Command::Reset => {
+                self.cpu.reset();
+                writeln!(output, "{}", self.cpu.registers())?;
+            }
+            Command::Help => writeln!(output, "{HELP_TEXT}")?,
+            Command::Quit => {}
+        }
+
+        Ok(())
+    }
+
+    // returns false when execution can not go on
+    fn step_reporting<W: Write>(&mut self, output: &mut W) -> io::Result<bool> {
+        match self.cpu.step() {
+            StepResult::Executed(_) => Ok(true),
+            StepResult::Trapped(address) => {
+                writeln!(output, "trapped at ${address:04X}")?;
+                Ok(false)
+            }
+            StepResult::UnimplementedOpcode { opcode, address } => {
+                writeln!(
+                    output,
+                    "unimplemented opcode ${opcode:02X} at ${address:04X}"
+                )?;
+                Ok(false)
+            }
+            StepResult::BusError(error) => {
+                writeln!(output, "{}", error_chain(&error))?;
+                Ok(false)
+            }
+        }
+    }
+
+    fn report_stop<W: Write>(&self, stop_reason: StopReason, output: &mut W) -> io::Result<()> {
+        let symbols = self.cpu.symbols();
+
+        match stop_reason {
+            StopReason::Breakpoint { id, address } => {
+                writeln!(output, "breakpoint {id} at {}", symbols.describe(address))
+            }
+            StopReason::Watchpoint(hit) => writeln!(
+                output,
+                "watchpoint {}: {} {} = ${:02X}",
+                hit.id,
+                hit.access.as_str(),
+                symbols.describe(hit.address),
+                hit.value
+            ),
+            StopReason::Trapped(address) => {
+                writeln!(output, "trapped at {}", symbols.describe(address))
+            }
+            StopReason::UnimplementedOpcode { opcode, address } => {
+                writeln!(
+                    output,
+                    "unimplemented opcode ${opcode:02X} at {}",
+                    symbols.describe(address)
+                )
+            }
+            StopReason::BusError(error) => writeln!(output, "{}", error_chain(&error)),
+            StopReason::CycleLimit => writeln!(output, "stopped after {MAX_RUN_CYCLES} cycles"),
+            StopReason::HistoryExhausted => writeln!(output, "reached start of history"),
+        }
+    }
+
+    fn finish_recording<W: Write>(&mut self, output: &mut W) -> io::Result<()> {
+        let (Some(name), Some(log)) = (self.recording_name.take(), self.cpu.stop_recording())
+        else {
+            return Ok(());
+        };
+
+        let path = format!("{name}.events");
+        match log.save(&path) {
+            Ok(()) => writeln!(output, "{} events written to {path}", log.events.len()),
+            Err(error) => writeln!(output, "failed to write {path}: {error}"),
+        }
+    }
+
+    fn start_replay<W: Write>(&mut self, name: &str, output: &mut W) -> io::Result<()> {
+        let state = match SaveState::load(format!("{name}.state")) {
+            Ok(state) => state,
+            Err(error) => return writeln!(output, "failed to restore {name}.state: {error}"),
+        };
+        let log = match EventLog::load(format!("{name}.events")) {
+            Ok(log) => log,
+            Err(error) => return writeln!(output, "failed to load {name}.events: {error}"),
+        };
+        if let Err(error) = self.cpu.restore_state(&state) {
+            return writeln!(output, "failed to restore {name}.state: {error}");
+        }
+
+        writeln!(output, "replaying {} events", log.events.len())?;
+        self.cpu.start_replay(log);
+        writeln!(output, "{}", self.cpu.registers())
+    }
+
+    fn set_throttle_setting<W: Write>(
+        &mut self,
+        setting: ThrottleSetting,
+        output: &mut W,
+    ) -> io::Result<()> {
+        match setting {
+            ThrottleSetting::Status => {}
+            ThrottleSetting::Off => self.throttle = None,
+            ThrottleSetting::Clock(clock) => match self.throttle.as_mut() {
+                Some(throttle) => throttle.set_clock(clock),
+                None => self.throttle = Some(Throttle::new(clock)),
+            },
+            ThrottleSetting::Speed(speed) => self
+                .throttle
+                .get_or_insert_with(|| Throttle::new(ClockPreset::default()))
+                .set_speed(speed),
+            ThrottleSetting::Turbo(turbo) => self
+                .throttle
+                .get_or_insert_with(|| Throttle::new(ClockPreset::default()))
+                .set_turbo(turbo),
+        }
+
+        match &self.throttle {
+            Some(throttle) if throttle.turbo() => writeln!(output, "clock turbo, unpaced"),
+            Some(throttle) => writeln!(output, "clock {} x{}", throttle.clock(), throttle.speed()),
+            None => writeln!(output, "clock off, continue runs unpaced"),
+        }
+    }
+
+    fn profile<W: Write>(&mut self, command: &ProfileCommand, output: &mut W) -> io::Result<()> {
+        match command {
+            ProfileCommand::On => self.cpu.enable_profiler(),
+            ProfileCommand::Off => {
+                self.cpu.disable_profiler();
+            }
+            ProfileCommand::Clear => {
+                if let Some(profiler) = self.cpu.profiler_mut() {
+                    profiler.clear();
+                }
+            }
+            ProfileCommand::Status => {}
+            ProfileCommand::Report(rows) => {
+                return match self.cpu.profiler() {
+                    Some(profiler) => profiler.write_report(output, self.cpu.symbols(), *rows),
+                    None => writeln!(output, "profiling is off, use profile on"),
+                };
+            }
+            ProfileCommand::Folded(path) => {
+                return match self.cpu.profiler() {
+                    Some(profiler) => match profiler.save_folded(path, self.cpu.symbols()) {
+                        Ok(()) => writeln!(output, "folded stacks written to {path}"),
+                        Err(error) => writeln!(output, "failed to write {path}: {error}"),
+                    },
+                    None => writeln!(output, "profiling is off, use profile on"),
+                };
+            }
+        }
+
+        match self.cpu.profiler() {
+            Some(profiler) => writeln!(
+                output,
+                "profiling on, {} instructions, {} cycles",
+                profiler.total().count,
+                profiler.total().cycles
+            ),
+            None => writeln!(output, "profiling off"),
+        }
+    }
+
+    fn coverage<W: Write>(&mut self, command: &CoverageCommand, output: &mut W) -> io::Result<()> {
+        match command {
+            CoverageCommand::On => self.cpu.memory_mut().set_coverage(true),
+            CoverageCommand::Off => self.cpu.memory_mut().set_coverage(false),
+            CoverageCommand::Clear => {
+                if let Some(coverage) = self.cpu.memory_mut().coverage_mut() {
+                    coverage.clear();
+                }
+            }
+            CoverageCommand::Status => {}
+            CoverageCommand::Map { range, path } | CoverageCommand::Listing { range, path } => {
+                let Some(coverage) = self.cpu.memory().coverage() else {
+                    return writeln!(output, "coverage is off, use cov on");
+                };
+                let result = match command {
+                    CoverageCommand::Map { .. } => coverage.save_map(path, range.0..=range.1),
+                    _ => coverage.save_listing(
+                        path,
+                        self.cpu.memory(),
+                        self.cpu.symbols(),
+                        range.0..=range.1,
+                    ),
+                };
+                return match result {
+                    Ok(()) => writeln!(output, "coverage written to {path}"),
+                    Err(error) => writeln!(output, "failed to write {path}: {error}"),
+                };
+            }
+        }
+
+        match self.cpu.memory().coverage() {
+            Some(coverage) => {
+                let summary = coverage.summary(0x0000..=0xFFFF);
+                writeln!(
+                    output,
+                    "coverage on, {} opcodes and {} code bytes executed, {} bytes read, {} written",
+                    summary.opcodes, summary.code, summary.data_read, summary.data_written
+                )
+            }
+            None => writeln!(output, "coverage off"),
+        }
+    }
+
+    fn heatmap<W: Write>(&mut self, command: &HeatmapCommand, output: &mut W) -> io::Result<()> {
+        match command {
+            HeatmapCommand::On => self.cpu.memory_mut().set_heatmap(true),
+            HeatmapCommand::Off => self.cpu.memory_mut().set_heatmap(false),
+            HeatmapCommand::Clear => {
+                if let Some(heatmap) = self.cpu.memory_mut().heatmap_mut() {
+                    heatmap.clear();
+                }
+            }
+            HeatmapCommand::Status => {}
+            _ => {
+                let Some(heatmap) = self.cpu.memory().heatmap() else {
+                    return writeln!(output, "heatmap is off, use heat on");
+                };
+                let symbols = self.cpu.symbols();
+
+                let (path, result) = match command {
+                    HeatmapCommand::Top { access, limit } => {
+                        writeln!(
+                            output,
+                            "{:<10} {:>10} {:>10} {:>10}  last PC",
+                            "address", "reads", "writes", "executes"
+                        )?;
+                        for (address, entry) in heatmap.hottest(*access, *limit) {
+                            print_address_access(address, &entry, symbols, output)?;
+                        }
+                        return Ok(());
+                    }
+                    HeatmapCommand::At(address) => {
+                        return print_address_access(
+                            *address,
+                            &heatmap.access(*address),
+                            symbols,
+                            output,
+                        );
+                    }
+                    HeatmapCommand::Csv(path) => (path, heatmap.save_csv(path)),
+                    HeatmapCommand::Pgm(path) => (path, heatmap.save_pgm(path)),
+                    HeatmapCommand::Ppm(path) => (path, heatmap.save_ppm(path)),
+                    _ => unreachable!("handled above"),
+                };
+                return match result {
+                    Ok(()) => writeln!(output, "heatmap written to {path}"),
+                    Err(error) => writeln!(output, "failed to write {path}: {error}"),
+                };
+            }
+        }
+
+        match self.cpu.memory().heatmap() {
+            Some(heatmap) => writeln!(
+                output,
+                "heatmap on, {} addresses touched",
+                heatmap.touched().count()
+            ),
+            None => writeln!(output, "heatmap off"),
+        }
+    }
+
+    fn set_rewind<W: Write>(&mut self, setting: RewindSetting, output: &mut W) -> io::Result<()> {
+        match setting {
+            RewindSetting::On(budget_bytes) => self
+                .cpu
+                .enable_rewind(RewindHistory::new(budget_bytes, DEFAULT_SNAPSHOT_INTERVAL)),
+            RewindSetting::Off => self.cpu.disable_rewind(),
+            RewindSetting::Status => {}
+        }
+
+        match self.cpu.rewind_history() {
+            Some(history) => writeln!(
+                output,
+                "rewind on, {} instructions recorded, {} of {} KB used",
+                history.len(),
+                history.used_bytes() / 1024,
+                history.budget_bytes() / 1024
+            ),
+            None => writeln!(output, "rewind off"),
+        }
+    }
+
+    // instruction about to execute and registers, after moving backwards
+    fn print_position<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        let instruction = disassemble(self.cpu.memory(), self.cpu.program_counter());
+        writeln!(
+            output,
+            "{}",
+            instruction.line_with_symbols(self.cpu.symbols())
+        )?;
+        writeln!(output, "{}", self.cpu.registers())
+    }
+
+    fn list_breakpoints<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        for breakpoint in self.cpu.breakpoints().iter() {
+            let address = breakpoint
+                .address
+                .map_or_else(|| "any".to_string(), |address| format!("${address:04X}"));
+            let condition = breakpoint
+                .condition
+                .as_ref()
+                .map_or_else(String::new, |condition| format!(" if {condition}"));
+            let state = if breakpoint.enabled {
+                ""
+            } else {
+                " (disabled)"
+            };
+
+            writeln!(
+                output,
+                "break {}: {address}{condition}{state}",
+                breakpoint.id
+            )?;
+        }
+
+        for watchpoint in self.cpu.memory().watchpoints() {
+            writeln!(
+                output,
+                "watch {}: ${:04X}-${:04X} {:?}",
+                watchpoint.id,
+                watchpoint.range.start(),
+                watchpoint.range.end(),
+                watchpoint.kind
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn set_register<W: Write>(
+        &mut self,
+        register: RegisterName,
+        value: Word,
+        output: &mut W,
+    ) -> io::Result<()> {
+        let mut registers = self.cpu.registers();
+
+        if register == RegisterName::PC {
+            registers.program_counter = value;
+        } else {
+            let Ok(value) = Byte::try_from(value) else {
+                return writeln!(output, "{register:?} is 8 bit, {value:#06X} does not fit");
+            };
+
+            match register {
+                RegisterName::A => registers.acc = value,
+                RegisterName::X => registers.x_reg = value,
+                RegisterName::Y => registers.y_reg = value,
+                RegisterName::SP => registers.stack_ptr = value,
+                RegisterName::P => registers.status = value,
+                RegisterName::PC => unreachable!(),
+            }
+        }
+
+        self.cpu.set_registers(&registers);
+        writeln!(output, "{}", self.cpu.registers())
+    }
+
+    fn dump_memory<W: Write>(&self, address: Word, length: Word, output: &mut W) -> io::Result<()> {
+        let memory = self.cpu.memory();
+        let end = (address as usize + length as usize).min(0x10000);
+
+        for row_start in (address as usize..end).step_by(16) {
+            let row: Vec<Byte> = (row_start..(row_start + 16).min(end))
+                .map(|row_address| memory.peek(row_address as Word))
+                .collect();
+
+            let hex = row
+                .iter()
+                .map(|byte| format!("{byte:02X}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let ascii: String = row
+                .iter()
+                .map(|&byte| {
+                    if byte.is_ascii_graphic() || byte == b' ' {
+                        byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+
+            writeln!(output, "${row_start:04X}  {hex:<47}  {ascii}")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn print_address_access<W: Write>(
+    address: Word,
+    access: &AddressAccess,
+    symbols: &SymbolTable,
+    output: &mut W,
+) -> io::Result<()> {
+    let last_pc = access
+        .last_pc
+        .map(|pc| symbols.describe(pc))
+        .unwrap_or_else(|| "-".to_string());
+
+    writeln!(
+        output,
+        "{:<10} {:>10} {:>10} {:>10}  {last_pc}",
+        format!("${address:04X}"),
+        access.reads,
+        access.writes,
+        access.executes
+    )
+}
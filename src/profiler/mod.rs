@@ -0,0 +1,2 @@
+pub mod profile_report;
+pub mod profiler;
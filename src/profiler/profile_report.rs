@@ -0,0 +1,140 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{
+    disassembler::opcode_table::decode_opcode, shared::types::Word,
+    symbols::symbol_table::SymbolTable,
+};
+
+use super::profiler::Profiler;
+
+// label of code running outside any tracked call in folded stacks
+const ROOT_FRAME: &str = "top";
+
+impl Profiler {
+    // hot spots, opcodes and subroutines, each sorted by cycles and cut to limit rows
+    pub fn write_report<W: Write>(
+        &self,
+        output: &mut W,
+        symbols: &SymbolTable,
+        limit: usize,
+    ) -> io::Result<()> {
+        let total = self.total();
+        writeln!(
+            output,
+            "{} instructions, {} cycles ({} in interrupt entry)",
+            total.count,
+            total.cycles,
+            self.interrupt_cycles()
+        )?;
+        if total.count == 0 {
+            return Ok(());
+        }
+
+        let mut addresses: Vec<_> = self.addresses().collect();
+        addresses.sort_by(|a, b| b.1.cycles.cmp(&a.1.cycles).then(a.0.cmp(&b.0)));
+        writeln!(
+            output,
+            "\n  {:<14} {:>10} {:>12} {:>6}",
+            "address", "count", "cycles", "%"
+        )?;
+        for (address, stats) in addresses.iter().take(limit) {
+            writeln!(
+                output,
+                "  {:<14} {:>10} {:>12} {:>6.2}  {}",
+                format!("${address:04X}"),
+                stats.count,
+                stats.cycles,
+                percent(stats.cycles, total.cycles),
+                symbols.name_for(*address).unwrap_or_default()
+            )?;
+        }
+
+        let mut opcodes: Vec<_> = self.opcodes().collect();
+        opcodes.sort_by(|a, b| b.1.cycles.cmp(&a.1.cycles).then(a.0.cmp(&b.0)));
+        writeln!(
+            output,
+            "\n  {:<14} {:>10} {:>12} {:>6}",
+            "opcode", "count", "cycles", "%"
+        )?;
+        for (opcode, stats) in opcodes.iter().take(limit) {
+            let name = decode_opcode(*opcode)
+                .map(|info| format!("{} {:?}", info.mnemonic, info.mode))
+                .unwrap_or_else(|| "???".to_string());
+            writeln!(
+                output,
+                "  {:<14} {:>10} {:>12} {:>6.2}  {name}",
+                format!("${opcode:02X}"),
+                stats.count,
+                stats.cycles,
+                percent(stats.cycles, total.cycles)
+            )?;
+        }
+
+        let mut subroutines: Vec<_> = self.subroutines().collect();
+        if subroutines.is_empty() {
+            return Ok(());
+        }
+        subroutines.sort_by(|a, b| b.1.total_cycles.cmp(&a.1.total_cycles).then(a.0.cmp(&b.0)));
+        writeln!(
+            output,
+            "\n  {:<14} {:>8} {:>12} {:>12} {:>6}",
+            "subroutine", "calls", "self cycles", "total cycles", "%"
+        )?;
+        for (target, stats) in subroutines.iter().take(limit) {
+            writeln!(
+                output,
+                "  {:<14} {:>8} {:>12} {:>12} {:>6.2}  {}",
+                format!("${target:04X}"),
+                stats.calls,
+                stats.self_cycles,
+                stats.total_cycles,
+                percent(stats.total_cycles, total.cycles),
+                symbols.name_for(*target).unwrap_or_default()
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // one "top;outer;inner cycles" line per call path, input for flamegraph.pl and inferno
+    pub fn write_folded<W: Write>(&self, output: &mut W, symbols: &SymbolTable) -> io::Result<()> {
+        let mut lines: Vec<_> = self
+            .stacks()
+            .map(|(path, cycles)| (folded_path(path, symbols), cycles))
+            .collect();
+        lines.sort();
+
+        for (path, cycles) in lines {
+            writeln!(output, "{path} {cycles}")?;
+        }
+
+        Ok(())
+    }
+
+    pub fn save_folded<P: AsRef<Path>>(&self, path: P, symbols: &SymbolTable) -> io::Result<()> {
+        let mut output = BufWriter::new(File::create(path)?);
+        self.write_folded(&mut output, symbols)?;
+        output.flush()
+    }
+}
+
+fn folded_path(path: &[Word], symbols: &SymbolTable) -> String {
+    let mut folded = ROOT_FRAME.to_string();
+    for target in path {
+        folded.push(';');
+        match symbols.name_for(*target) {
+            Some(name) => folded.push_str(name),
+            None => folded.push_str(&format!("${target:04X}")),
+        }
+    }
+
+    folded
+}
+
+fn percent(part: u64, total: u64) -> f64 {
+    part as f64 * 100.0 / total as f64
+}
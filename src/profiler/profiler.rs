@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use crate::{
+    cpu::call_stack::CallFrame,
+    memory::page_table::MEMORY_SIZE,
+    shared::types::{Byte, Word},
+};
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct ExecutionStats {
+    pub count: u64,
+    pub cycles: u64,
+}
+
+impl ExecutionStats {
+    fn add(&mut self, cycles: u64) {
+        self.count += 1;
+        self.cycles += cycles;
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct SubroutineStats {
+    // JSR into it, or interrupt entries for handlers
+    pub calls: u64,
+    // spent in its own instructions
+    pub self_cycles: u64,
+    // including everything it called, recursion counted once
+    pub total_cycles: u64,
+}
+
+// execution counts collected by the CPU while enabled, subroutines are keyed by
+// the first instruction of the call frames the CPU tracks for JSR/RTS and interrupts
+pub struct Profiler {
+    by_address: Vec<ExecutionStats>,
+    by_opcode: Vec<ExecutionStats>,
+    subroutines: HashMap<Word, SubroutineStats>,
+    // cycles per call path, outermost frame first, empty path is code outside any call
+    stacks: HashMap<Vec<Word>, u64>,
+    total: ExecutionStats,
+    interrupt_cycles: u64,
+
+    // reused to look up the current call path without allocating
+    path: Vec<Word>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            by_address: vec![ExecutionStats::default(); MEMORY_SIZE],
+            by_opcode: vec![ExecutionStats::default(); 256],
+            subroutines: HashMap::new(),
+            stacks: HashMap::new(),
+            total: ExecutionStats::default(),
+            interrupt_cycles: 0,
+            path: Vec::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    // instructions executed and cycles used by them and interrupt entries
+    pub fn total(&self) -> ExecutionStats {
+        self.total
+    }
+
+    // cycles of the hardware interrupt sequence, not of handler code
+    pub fn interrupt_cycles(&self) -> u64 {
+        self.interrupt_cycles
+    }
+
+    pub fn address_stats(&self, address: Word) -> ExecutionStats {
+        self.by_address[usize::from(address)]
+    }
+
+    pub fn opcode_stats(&self, opcode: Byte) -> ExecutionStats {
+        self.by_opcode[usize::from(opcode)]
+    }
+
+    pub fn subroutine_stats(&self, target: Word) -> Option<SubroutineStats> {
+        self.subroutines.get(&target).copied()
+    }
+
+    // executed addresses, unordered
+    pub fn addresses(&self) -> impl Iterator<Item = (Word, ExecutionStats)> + '_ {
+        self.by_address
+            .iter()
+            .enumerate()
+            .filter(|(_, stats)| stats.count > 0)
+            .map(|(address, stats)| (address as Word, *stats))
+    }
+
+    pub fn opcodes(&self) -> impl Iterator<Item = (Byte, ExecutionStats)> + '_ {
+        self.by_opcode
+            .iter()
+            .enumerate()
+            .filter(|(_, stats)| stats.count > 0)
+            .map(|(opcode, stats)| (opcode as Byte, *stats))
+    }
+
+    pub fn subroutines(&self) -> impl Iterator<Item = (Word, SubroutineStats)> + '_ {
+        self.subroutines
+            .iter()
+            .map(|(target, stats)| (*target, *stats))
+    }
+
+    pub fn stacks(&self) -> impl Iterator<Item = (&[Word], u64)> {
+        self.stacks
+            .iter()
+            .map(|(path, cycles)| (path.as_slice(), *cycles))
+    }
+
+    // call_stack is the one the instruction ran in, a JSR still belongs to its caller
+    pub(crate) fn record_instruction(
+        &mut self,
+        address: Word,
+        opcode: Byte,
+        cycles: u64,
+        call_stack: &[CallFrame],
+    ) {
+        self.by_address[usize::from(address)].add(cycles);
+        self.by_opcode[usize::from(opcode)].add(cycles);
+        self.total.add(cycles);
+        self.attribute(cycles, call_stack);
+    }
+
+    // call_stack already holds the frame of the entered handler
+    pub(crate) fn record_interrupt_entry(&mut self, cycles: u64, call_stack: &[CallFrame]) {
+        self.total.cycles += cycles;
+        self.interrupt_cycles += cycles;
+        self.attribute(cycles, call_stack);
+    }
+
+    pub(crate) fn record_call(&mut self, target: Word) {
+        self.subroutines.entry(target).or_default().calls += 1;
+    }
+
+    fn attribute(&mut self, cycles: u64, call_stack: &[CallFrame]) {
+        self.path.clear();
+        self.path
+            .extend(call_stack.iter().map(|frame| frame.target));
+
+        for (depth, target) in self.path.iter().enumerate() {
+            if self.path[..depth].contains(target) {
+                continue;
+            }
+            self.subroutines.entry(*target).or_default().total_cycles += cycles;
+        }
+        if let Some(innermost) = self.path.last() {
+            self.subroutines.entry(*innermost).or_default().self_cycles += cycles;
+        }
+
+        match self.stacks.get_mut(self.path.as_slice()) {
+            Some(stack_cycles) => *stack_cycles += cycles,
+            None => {
+                self.stacks.insert(self.path.clone(), cycles);
+            }
+        }
+    }
+}
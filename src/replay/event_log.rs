@@ -0,0 +1,187 @@
+use std::{fmt, fs, io, path::Path};
+
+use super::input_event::TimedEvent;
+
+const HEADER: &str = "# 6502 event log v1";
+
+#[derive(Debug)]
+pub enum EventLogError {
+    Io(io::Error),
+    MissingHeader,
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for EventLogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventLogError::Io(error) => write!(f, "{error}"),
+            EventLogError::MissingHeader => write!(f, "not an event log, expected \"{HEADER}\""),
+            EventLogError::Parse { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl From<io::Error> for EventLogError {
+    fn from(error: io::Error) -> Self {
+        EventLogError::Io(error)
+    }
+}
+
+// external events in cycle order, replayed on top of the snapshot taken when recording started
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EventLog {
+    pub events: Vec<TimedEvent>,
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, event: TimedEvent) {
+        self.events.push(event);
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, EventLogError> {
+        let mut lines = contents.lines().enumerate();
+        if lines.next().map(|(_, line)| line.trim()) != Some(HEADER) {
+            return Err(EventLogError::MissingHeader);
+        }
+
+        let mut events = Vec::new();
+        for (index, line) in lines {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let event = TimedEvent::parse(line).map_err(|message| EventLogError::Parse {
+                line: index + 1,
+                message,
+            })?;
+
+            if events
+                .last()
+                .is_some_and(|last: &TimedEvent| last.cycle > event.cycle)
+            {
+                return Err(EventLogError::Parse {
+                    line: index + 1,
+                    message: "events are not in cycle order".to_string(),
+                });
+            }
+            events.push(event);
+        }
+
+        Ok(EventLog { events })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, EventLogError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+}
+
+impl fmt::Display for EventLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{HEADER}")?;
+        for event in &self.events {
+            writeln!(f, "{event}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::replay::input_event::InputEvent;
+
+    use super::*;
+
+    fn log() -> EventLog {
+        EventLog {
+            events: vec![
+                TimedEvent {
+                    cycle: 0,
+                    event: InputEvent::Reset,
+                },
+                TimedEvent {
+                    cycle: 12,
+                    event: InputEvent::IrqLine(true),
+                },
+                TimedEvent {
+                    cycle: 12,
+                    event: InputEvent::InputByte {
+                        address: 0xD010,
+                        value: 0x8D,
+                    },
+                },
+                TimedEvent {
+                    cycle: 40,
+                    event: InputEvent::IrqLine(false),
+                },
+                TimedEvent {
+                    cycle: 1_000_000,
+                    event: InputEvent::Nmi,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn serializes_one_event_per_line() {
+        assert_eq!(
+            log().to_string(),
+            "# 6502 event log v1\n\
+             0 reset\n\
+             12 irq 1\n\
+             12 input $D010 $8D\n\
+             40 irq 0\n\
+             1000000 nmi\n"
+        );
+    }
+
+    #[test]
+    fn parses_what_it_serializes() {
+        let log = log();
+
+        assert_eq!(EventLog::parse(&log.to_string()).unwrap(), log);
+    }
+
+    #[test]
+    fn skips_blank_lines_and_comments() {
+        let log = EventLog::parse("# 6502 event log v1\n\n# keyboard\n  5 nmi  \n").unwrap();
+
+        assert_eq!(
+            log.events,
+            [TimedEvent {
+                cycle: 5,
+                event: InputEvent::Nmi,
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_missing_header() {
+        assert!(matches!(
+            EventLog::parse("0 reset\n"),
+            Err(EventLogError::MissingHeader)
+        ));
+    }
+
+    #[test]
+    fn reports_line_of_invalid_events() {
+        let error_line = |contents: &str| match EventLog::parse(contents) {
+            Err(EventLogError::Parse { line, .. }) => line,
+            other => panic!("expected parse error, got {other:?}"),
+        };
+
+        assert_eq!(error_line("# 6502 event log v1\n0 irq 2\n"), 2);
+        assert_eq!(error_line("# 6502 event log v1\nsoon nmi\n"), 2);
+        assert_eq!(error_line("# 6502 event log v1\n\n0 input $D010 $100\n"), 3);
+        assert_eq!(error_line("# 6502 event log v1\n10 nmi\n9 nmi\n"), 3);
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt;
+
+use crate::shared::{
+    parsing::{parse_byte, parse_word},
+    types::{Byte, Word},
+};
+
+// anything that reaches the machine from outside of the instruction stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEvent {
+    IrqLine(bool),
+    Nmi,
+    // device register filled by the outside world, e.g. a keyboard latch
+    InputByte { address: Word, value: Byte },
+    Reset,
+}
+
+// event applied between instructions once CPU cycle counter reached cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedEvent {
+    pub cycle: u64,
+    pub event: InputEvent,
+}
+
+impl fmt::Display for TimedEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ", self.cycle)?;
+
+        match self.event {
+            InputEvent::IrqLine(asserted) => write!(f, "irq {}", u8::from(asserted)),
+            InputEvent::Nmi => write!(f, "nmi"),
+            InputEvent::InputByte { address, value } => {
+                write!(f, "input ${address:04X} ${value:02X}")
+            }
+            InputEvent::Reset => write!(f, "reset"),
+        }
+    }
+}
+
+impl TimedEvent {
+    // "<cycle> irq 0|1", "<cycle> nmi", "<cycle> input $addr $byte", "<cycle> reset"
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some((cycle, event)) = tokens.split_first() else {
+            return Err("empty event".to_string());
+        };
+
+        let cycle = cycle
+            .parse()
+            .map_err(|_| format!("invalid cycle {cycle}"))?;
+        let event = match event {
+            ["irq", "1"] => InputEvent::IrqLine(true),
+            ["irq", "0"] => InputEvent::IrqLine(false),
+            ["nmi"] => InputEvent::Nmi,
+            ["input", address, value] => InputEvent::InputByte {
+                address: parse_word(address).ok_or(format!("invalid address {address}"))?,
+                value: parse_byte(value).ok_or(format!("invalid byte {value}"))?,
+            },
+            ["reset"] => InputEvent::Reset,
+            _ => return Err(format!("unknown event \"{}\"", event.join(" "))),
+        };
+
+        Ok(TimedEvent { cycle, event })
+    }
+}
@@ -0,0 +1,3 @@
+pub mod event_log;
+pub mod input_event;
+pub mod replayer;
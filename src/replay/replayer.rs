@@ -0,0 +1,38 @@
+use super::{
+    event_log::EventLog,
+    input_event::{InputEvent, TimedEvent},
+};
+
+// hands out logged events once the CPU reaches their cycle
+#[derive(Debug, Clone)]
+pub struct Replayer {
+    events: Vec<TimedEvent>,
+    next: usize,
+}
+
+impl Replayer {
+    pub fn new(log: EventLog) -> Self {
+        Self {
+            events: log.events,
+            next: 0,
+        }
+    }
+
+    pub fn next_due(&mut self, cycle: u64) -> Option<InputEvent> {
+        let event = self.events.get(self.next)?;
+        if event.cycle > cycle {
+            return None;
+        }
+
+        self.next += 1;
+        Some(event.event)
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.events.len() - self.next
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.remaining() == 0
+    }
+}
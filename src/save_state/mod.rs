@@ -0,0 +1,2 @@
+pub mod save_state;
+pub mod save_state_error;
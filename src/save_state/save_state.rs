@@ -0,0 +1,464 @@
+use std::{fs, path::Path};
+
+use crate::{
+    cpu::{
+        call_stack::{CallFrame, CallKind},
+        registers::Registers,
+    },
+    memory::{
+        device::DeviceId,
+        memory_snapshot::{DeviceSnapshot, MemorySnapshot},
+        page_table::{Page, PageKind, MEMORY_SIZE, PAGE_COUNT, PAGE_SIZE},
+    },
+    shared::types::{Byte, Word},
+};
+
+use super::save_state_error::SaveStateError;
+
+const MAGIC: &[u8; 8] = b"6502SAVE";
+// bump on every layout change, files of any other version are rejected
+pub const SAVE_STATE_VERSION: u16 = 1;
+
+const PAGE_RAM: u8 = 0;
+const PAGE_ROM: u8 = 1;
+const PAGE_DEVICE: u8 = 2;
+const PAGE_UNMAPPED: u8 = 3;
+
+// complete machine state, restored with CPU::restore_state
+#[derive(Clone, PartialEq, Eq)]
+pub struct SaveState {
+    pub registers: Registers,
+    pub cycles: u64,
+    pub irq_line: bool,
+    pub nmi_pending: bool,
+    // kept so debuggers can still step out after restore
+    pub call_stack: Vec<CallFrame>,
+    pub memory: MemorySnapshot,
+}
+
+impl SaveState {
+    // layout, all integers LE:
+    // magic[8] version:u16
+    // A X Y SP P:u8 PC:u16 cycles:u64 irq_line:u8 nmi_pending:u8
+    // frame_count:u16 { kind:u8 call_site:u16 target:u16 return_address:u16 stack_ptr:u8 }*
+    // rom_write_protected:u8 memory_size:u32 memory[memory_size]
+    // bank_count:u16 { pages:u16 }* { kind:u8 device:u16 offset:u32 }[256] data_bus:u8
+    // device_count:u16 { name_length:u8 name[name_length] state_length:u32 state[state_length] }*
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SaveStateError> {
+        let mut bytes = Vec::with_capacity(MEMORY_SIZE + 64);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&SAVE_STATE_VERSION.to_le_bytes());
+
+        let registers = &self.registers;
+        bytes.extend_from_slice(&[
+            registers.acc,
+            registers.x_reg,
+            registers.y_reg,
+            registers.stack_ptr,
+            registers.status,
+        ]);
+        bytes.extend_from_slice(&registers.program_counter.to_le_bytes());
+        bytes.extend_from_slice(&self.cycles.to_le_bytes());
+        bytes.push(self.irq_line.into());
+        bytes.push(self.nmi_pending.into());
+
+        bytes.extend_from_slice(&(self.call_stack.len() as u16).to_le_bytes());
+        for frame in &self.call_stack {
+            bytes.push(match frame.kind {
+                CallKind::Subroutine => 0,
+                CallKind::Interrupt => 1,
+            });
+            bytes.extend_from_slice(&frame.call_site.to_le_bytes());
+            bytes.extend_from_slice(&frame.target.to_le_bytes());
+            bytes.extend_from_slice(&frame.return_address.to_le_bytes());
+            bytes.push(frame.stack_ptr);
+        }
+
+        bytes.push(self.memory.rom_write_protected.into());
+        bytes.extend_from_slice(&(self.memory.data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.memory.data);
+
+        bytes.extend_from_slice(&(self.memory.bank_pages.len() as u16).to_le_bytes());
+        for &pages in &self.memory.bank_pages {
+            bytes.extend_from_slice(&(pages as u16).to_le_bytes());
+        }
+        for page in &self.memory.pages {
+            let (kind, device) = match page.kind {
+                PageKind::Ram => (PAGE_RAM, 0),
+                PageKind::Rom => (PAGE_ROM, 0),
+                PageKind::Device(id) => (PAGE_DEVICE, id.0),
+                PageKind::Unmapped => (PAGE_UNMAPPED, 0),
+            };
+            bytes.push(kind);
+            bytes.extend_from_slice(&device.to_le_bytes());
+            bytes.extend_from_slice(&page.offset.to_le_bytes());
+        }
+        bytes.push(self.memory.data_bus);
+
+        bytes.extend_from_slice(&(self.memory.devices.len() as u16).to_le_bytes());
+        for device in &self.memory.devices {
+            // a truncated length would shift everything after the name
+            let name_length = u8::try_from(device.name.len()).map_err(|_| {
+                SaveStateError::InvalidData(format!(
+                    "device name {} is longer than 255 bytes",
+                    device.name
+                ))
+            })?;
+            bytes.push(name_length);
+            bytes.extend_from_slice(device.name.as_bytes());
+            bytes.extend_from_slice(&(device.state.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&device.state);
+        }
+
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SaveStateError> {
+        let mut reader = Reader { bytes, position: 0 };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(SaveStateError::NotASaveState);
+        }
+        let version = reader.word()?;
+        if version != SAVE_STATE_VERSION {
+            return Err(SaveStateError::UnsupportedVersion(version));
+        }
+
+        let [acc, x_reg, y_reg, stack_ptr, status] = reader.array()?;
+        let registers = Registers {
+            acc,
+            x_reg,
+            y_reg,
+            stack_ptr,
+            status,
+            program_counter: reader.word()?,
+        };
+        let cycles = u64::from_le_bytes(reader.array()?);
+        let irq_line = reader.flag()?;
+        let nmi_pending = reader.flag()?;
+
+        let frame_count = reader.word()?;
+        let call_stack = (0..frame_count)
+            .map(|_| {
+                let kind = match reader.byte()? {
+                    0 => CallKind::Subroutine,
+                    1 => CallKind::Interrupt,
+                    kind => {
+                        return Err(SaveStateError::InvalidData(format!(
+                            "unknown call frame kind {kind}"
+                        )))
+                    }
+                };
+
+                Ok(CallFrame {
+                    kind,
+                    call_site: reader.word()?,
+                    target: reader.word()?,
+                    return_address: reader.word()?,
+                    stack_ptr: reader.byte()?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let rom_write_protected = reader.flag()?;
+        let memory_size = u32::from_le_bytes(reader.array()?) as usize;
+        if memory_size < MEMORY_SIZE {
+            return Err(SaveStateError::InvalidData(format!(
+                "memory is {memory_size} bytes, expected {MEMORY_SIZE}"
+            )));
+        }
+        let data = reader.take(memory_size)?.to_vec();
+
+        let (bank_pages, pages) = reader.page_table(memory_size)?;
+        let data_bus = reader.byte()?;
+        let devices = reader.devices()?;
+
+        if reader.position != bytes.len() {
+            return Err(SaveStateError::InvalidData(format!(
+                "{} trailing bytes",
+                bytes.len() - reader.position
+            )));
+        }
+
+        Ok(SaveState {
+            registers,
+            cycles,
+            irq_line,
+            nmi_pending,
+            call_stack,
+            memory: MemorySnapshot {
+                data,
+                bank_pages,
+                pages,
+                rom_write_protected,
+                data_bus,
+                devices,
+            },
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SaveStateError> {
+        fs::write(path, self.to_bytes()?)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SaveStateError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], SaveStateError> {
+        let end = self.position + count;
+        let slice = self
+            .bytes
+            .get(self.position..end)
+            .ok_or(SaveStateError::Truncated)?;
+        self.position = end;
+
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SaveStateError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn byte(&mut self) -> Result<Byte, SaveStateError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn word(&mut self) -> Result<Word, SaveStateError> {
+        Ok(Word::from_le_bytes(self.array()?))
+    }
+
+    // bank sizes must add up to memory, base bank first
+    fn page_table(
+        &mut self,
+        memory_size: usize,
+    ) -> Result<(Vec<usize>, Vec<Page>), SaveStateError> {
+        let bank_count = self.word()?;
+        let bank_pages = (0..bank_count)
+            .map(|_| Ok(self.word()? as usize))
+            .collect::<Result<Vec<_>, SaveStateError>>()?;
+        if bank_pages.first() != Some(&PAGE_COUNT)
+            || bank_pages.iter().sum::<usize>() * PAGE_SIZE != memory_size
+        {
+            return Err(SaveStateError::InvalidData(
+                "banks do not match memory size".to_string(),
+            ));
+        }
+
+        let pages = (0..PAGE_COUNT)
+            .map(|index| {
+                let kind = self.byte()?;
+                let device = self.word()?;
+                let offset = u32::from_le_bytes(self.array()?);
+                let storage_offset = offset as usize;
+                let kind = match kind {
+                    PAGE_RAM | PAGE_ROM
+                        if !storage_offset.is_multiple_of(PAGE_SIZE)
+                            || storage_offset >= memory_size =>
+                    {
+                        return Err(SaveStateError::InvalidData(format!(
+                            "page {index:#04X} points outside of memory"
+                        )))
+                    }
+                    PAGE_RAM => PageKind::Ram,
+                    PAGE_ROM => PageKind::Rom,
+                    PAGE_DEVICE => PageKind::Device(DeviceId(device)),
+                    PAGE_UNMAPPED => PageKind::Unmapped,
+                    kind => {
+                        return Err(SaveStateError::InvalidData(format!(
+                            "unknown page kind {kind}"
+                        )))
+                    }
+                };
+
+                Ok(Page { kind, offset })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((bank_pages, pages))
+    }
+
+    fn devices(&mut self) -> Result<Vec<DeviceSnapshot>, SaveStateError> {
+        let device_count = self.word()?;
+        (0..device_count)
+            .map(|_| {
+                let name_length = self.byte()? as usize;
+                let name = String::from_utf8(self.take(name_length)?.to_vec()).map_err(|_| {
+                    SaveStateError::InvalidData("device name is not UTF-8".to_string())
+                })?;
+                let state_length = u32::from_le_bytes(self.array()?) as usize;
+
+                Ok(DeviceSnapshot {
+                    name,
+                    state: self.take(state_length)?.to_vec(),
+                })
+            })
+            .collect()
+    }
+
+    fn flag(&mut self) -> Result<bool, SaveStateError> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(SaveStateError::InvalidData(format!(
+                "flag byte {value:#04X}"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cpu::cpu::CPU,
+        memory::{device::Device, device_error::DeviceError, memory_errors::MemoryError},
+    };
+
+    use super::*;
+
+    // single register device, its value is the state a save has to carry
+    struct Latch(Byte);
+
+    impl Device for Latch {
+        fn name(&self) -> &'static str {
+            "latch"
+        }
+
+        fn read(&mut self, _offset: Word) -> Result<Byte, DeviceError> {
+            Ok(self.0)
+        }
+
+        fn write(&mut self, _offset: Word, value: Byte) -> Result<(), DeviceError> {
+            self.0 = value;
+            Ok(())
+        }
+
+        fn peek(&self, _offset: Word) -> Byte {
+            self.0
+        }
+
+        fn save_state(&self) -> Vec<Byte> {
+            vec![self.0]
+        }
+
+        fn restore_state(&mut self, state: &[Byte]) -> Result<(), DeviceError> {
+            self.0 = state[0];
+            Ok(())
+        }
+    }
+
+    fn cpu_with_latch() -> CPU {
+        let mut cpu = CPU::new();
+        cpu.memory_mut().map_device(0xD0, 1, Latch(0)).unwrap();
+        cpu
+    }
+
+    fn saved_cpu() -> SaveState {
+        let mut cpu = cpu_with_latch();
+        cpu.set_registers(&Registers {
+            acc: 0x12,
+            x_reg: 0x34,
+            y_reg: 0x56,
+            stack_ptr: 0xF0,
+            program_counter: 0xC123,
+            status: 0xA5,
+        });
+        cpu.memory_mut().load(0x0200, &[0xDE, 0xAD]);
+        cpu.memory_mut().poke(0xD000, 0x42).unwrap();
+        cpu.save_state()
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let state = saved_cpu();
+
+        let loaded = SaveState::from_bytes(&state.to_bytes().unwrap()).unwrap();
+
+        assert!(loaded == state);
+    }
+
+    #[test]
+    fn restores_registers_memory_and_devices() {
+        let state = SaveState::from_bytes(&saved_cpu().to_bytes().unwrap()).unwrap();
+        let mut cpu = cpu_with_latch();
+
+        cpu.restore_state(&state).unwrap();
+
+        assert!(cpu.registers() == state.registers);
+        assert_eq!(cpu.memory().peek(0x0200), 0xDE);
+        assert_eq!(cpu.memory().peek(0x0201), 0xAD);
+        assert_eq!(cpu.memory().peek(0xD000), 0x42);
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut bytes = saved_cpu().to_bytes().unwrap();
+        bytes[MAGIC.len()..MAGIC.len() + 2]
+            .copy_from_slice(&(SAVE_STATE_VERSION + 1).to_le_bytes());
+
+        let result = SaveState::from_bytes(&bytes);
+
+        assert!(matches!(
+            result,
+            Err(SaveStateError::UnsupportedVersion(version)) if version == SAVE_STATE_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        let bytes = saved_cpu().to_bytes().unwrap();
+
+        assert!(matches!(
+            SaveState::from_bytes(b"NOTSAVED\x01\x00"),
+            Err(SaveStateError::NotASaveState)
+        ));
+        assert!(matches!(
+            SaveState::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(SaveStateError::Truncated)
+        ));
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(matches!(
+            SaveState::from_bytes(&trailing),
+            Err(SaveStateError::InvalidData(_))
+        ));
+    }
+
+    #[test]
+    fn restore_without_saved_device_leaves_cpu_untouched() {
+        let state = saved_cpu();
+        let mut cpu = CPU::new();
+        let registers = cpu.registers();
+
+        let result = cpu.restore_state(&state);
+
+        assert!(matches!(
+            result,
+            Err(SaveStateError::Memory(MemoryError::MissingDevice { .. }))
+        ));
+        assert!(cpu.registers() == registers);
+        assert_eq!(cpu.memory().peek(0x0200), 0x00);
+    }
+
+    #[test]
+    fn rejects_device_names_longer_than_the_length_prefix() {
+        let mut state = saved_cpu();
+        state.memory.devices[0].name = "l".repeat(256);
+
+        assert!(matches!(
+            state.to_bytes(),
+            Err(SaveStateError::InvalidData(message)) if message.contains("255 bytes")
+        ));
+    }
+}
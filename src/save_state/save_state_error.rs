@@ -0,0 +1,52 @@
+use std::{error::Error, fmt, io};
+
+use crate::memory::memory_errors::MemoryError;
+
+#[derive(Debug)]
+pub enum SaveStateError {
+    Io(io::Error),
+    NotASaveState,
+    UnsupportedVersion(u16),
+    Truncated,
+    InvalidData(String),
+    // state is readable but this machine can not take it, e.g. a device is not mapped
+    Memory(MemoryError),
+}
+
+impl fmt::Display for SaveStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveStateError::Io(error) => write!(f, "{error}"),
+            SaveStateError::NotASaveState => write!(f, "not a save state file"),
+            SaveStateError::UnsupportedVersion(version) => {
+                write!(f, "save state version {version} is not supported")
+            }
+            SaveStateError::Truncated => write!(f, "save state is truncated"),
+            SaveStateError::InvalidData(message) => write!(f, "invalid save state: {message}"),
+            SaveStateError::Memory(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+// wrapped errors are displayed in place, their sources continue the chain
+impl Error for SaveStateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SaveStateError::Io(error) => error.source(),
+            SaveStateError::Memory(error) => error.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<MemoryError> for SaveStateError {
+    fn from(error: MemoryError) -> Self {
+        SaveStateError::Memory(error)
+    }
+}
+
+impl From<io::Error> for SaveStateError {
+    fn from(error: io::Error) -> Self {
+        SaveStateError::Io(error)
+    }
+}
@@ -1,3 +1,16 @@
+use super::types::Word;
+
 pub const BIT_SET: u8 = 0b00000001;
 pub const BIT_CLEAR: u8 = 0b00000000;
 pub const NTSC_NES_CPU_DEFAULT_FREQUENCY_HZ: u32 = 1_789_773;
+pub const PAL_NES_CPU_FREQUENCY_HZ: u32 = 1_662_607;
+pub const C64_PAL_CPU_FREQUENCY_HZ: u32 = 985_248;
+pub const ONE_MHZ_FREQUENCY_HZ: u32 = 1_000_000;
+
+// hardware vectors, each holds LE address of the handler
+pub const NMI_VECTOR: Word = 0xFFFA;
+pub const RESET_VECTOR: Word = 0xFFFC;
+pub const IRQ_VECTOR: Word = 0xFFFE;
+
+// stack lives in page 1, SP is an offset into it
+pub const STACK_PAGE_START: Word = 0x0100;
@@ -0,0 +1,14 @@
+use std::error::Error;
+
+// "outer: cause: root cause", for UIs that show errors as one line
+pub fn error_chain(error: &dyn Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+
+    message
+}
@@ -0,0 +1,143 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::Utc;
+
+use super::{
+    log_record::{LogFormat, LogRecord, HOST_HW_NAME},
+    log_sink::LogSink,
+    logger, LogLevel,
+};
+
+// target of records forwarded to log, the bridge skips them so both directions can be active
+pub const LOG_TARGET: &str = "cpu_emu::hw";
+
+// set once install_log_bridge made the emulator logger the log backend
+static IS_BRIDGE_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+fn to_log_level(level: LogLevel) -> log::Level {
+    match level {
+        LogLevel::Error => log::Level::Error,
+        LogLevel::Warn => log::Level::Warn,
+        LogLevel::Info => log::Level::Info,
+        LogLevel::Debug => log::Level::Debug,
+        LogLevel::Verbose => log::Level::Trace,
+    }
+}
+
+fn from_log_level(level: log::Level) -> LogLevel {
+    match level {
+        log::Level::Error => LogLevel::Error,
+        log::Level::Warn => LogLevel::Warn,
+        log::Level::Info => LogLevel::Info,
+        log::Level::Debug => LogLevel::Debug,
+        log::Level::Trace => LogLevel::Verbose,
+    }
+}
+
+// emulator records into the log facade, "[HW]::[operation] [ctx]: message" with target cpu_emu::hw
+pub struct LogCrateSink;
+
+impl LogSink for LogCrateSink {
+    // host records came from the host ecosystem and are not echoed back
+    fn write_record(&mut self, record: &LogRecord, _format: LogFormat) {
+        if record.hw == HOST_HW_NAME {
+            return;
+        }
+
+        let ctx = record
+            .ctx
+            .as_deref()
+            .map(|ctx| format!(" [{ctx}]"))
+            .unwrap_or_default();
+
+        log::log!(
+            target: LOG_TARGET,
+            to_log_level(record.level),
+            "[{}]::[{}]{ctx}: {}",
+            record.hw,
+            record.operation,
+            record.message
+        );
+    }
+
+    // with the bridge installed the log backend is the emulator logger, whose sinks lock
+    // is held while this runs, and it has nothing of its own to flush
+    fn flush(&mut self) {
+        if !IS_BRIDGE_INSTALLED.load(Ordering::Relaxed) {
+            log::logger().flush();
+        }
+    }
+}
+
+// host log records into the emulator logger as HOST with the log target as operation,
+// install with install_log_bridge
+pub struct LogCrateBridge;
+
+impl log::Log for LogCrateBridge {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target() != LOG_TARGET
+            && logger().is_log_level_enabled_for_operation(
+                HOST_HW_NAME,
+                metadata.target(),
+                from_log_level(metadata.level()),
+            )
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        logger().dispatch(&LogRecord {
+            level: from_log_level(record.level()),
+            hw: HOST_HW_NAME,
+            operation: record.target().to_string(),
+            ctx: None,
+            fields: Vec::new(),
+            timestamp: Utc::now(),
+            message: record.args().to_string(),
+        });
+    }
+
+    fn flush(&self) {
+        logger().flush();
+    }
+}
+
+// makes the emulator logger the global log backend, filtering is left to its level rules
+pub fn install_log_bridge() -> Result<(), log::SetLoggerError> {
+    static BRIDGE: LogCrateBridge = LogCrateBridge;
+
+    log::set_logger(&BRIDGE)?;
+    log::set_max_level(log::LevelFilter::Trace);
+    IS_BRIDGE_INSTALLED.store(true, Ordering::Relaxed);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, thread, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn flush_with_both_directions_active_returns() {
+        // fails harmlessly when another test installed it first
+        let _ = install_log_bridge();
+        let id = logger().add_sink(LogCrateSink, LogLevel::Verbose);
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            log::info!(target: "host", "forwarded both ways");
+            logger().flush();
+            let _ = sender.send(());
+        });
+        // a deadlocked flush keeps the sinks lock, the sink can only be removed after it returned
+        assert!(
+            receiver.recv_timeout(Duration::from_secs(5)).is_ok(),
+            "flush deadlocked"
+        );
+        logger().remove_sink(id);
+    }
+}
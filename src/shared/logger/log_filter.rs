@@ -0,0 +1,203 @@
+use super::LogLevel;
+
+// environment variable read when the logger is created, e.g. "info,CPU=debug,MEM=off"
+pub const LOG_FILTER_ENV: &str = "CPU_EMU_LOG";
+
+// "HW=level" or "HW::operation=level", names may use * wildcards,
+// None level switches matching records off
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterRule {
+    pub hw: String,
+    pub operation: Option<String>,
+    pub level: Option<LogLevel>,
+}
+
+impl FilterRule {
+    pub fn matches(&self, hw: &str, operation: Option<&str>) -> bool {
+        if !wildcard_match(&self.hw, hw) {
+            return false;
+        }
+
+        match (&self.operation, operation) {
+            (None, _) => true,
+            (Some(pattern), Some(operation)) => wildcard_match(pattern, operation),
+            (Some(_), None) => false,
+        }
+    }
+
+    // operation rules beat component rules, then more literal characters win
+    fn specificity(&self) -> (bool, usize) {
+        let literal = |pattern: &str| pattern.chars().filter(|c| *c != '*').count();
+
+        (
+            self.operation.is_some(),
+            literal(&self.hw) + self.operation.as_deref().map_or(0, literal),
+        )
+    }
+}
+
+// parsed filter string, a bare level sets the global one
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LogFilter {
+    pub global_level: Option<LogLevel>,
+    pub rules: Vec<FilterRule>,
+}
+
+impl LogFilter {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut filter = LogFilter::default();
+
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let Some((target, level)) = directive.split_once('=') else {
+                filter.global_level =
+                    Some(LogLevel::parse(directive).ok_or(format!("unknown level {directive}"))?);
+                continue;
+            };
+
+            let level = match level.trim() {
+                "off" => None,
+                level => Some(LogLevel::parse(level).ok_or(format!("unknown level {level}"))?),
+            };
+            let (hw, operation) = match target.trim().split_once("::") {
+                Some((hw, operation)) => (hw, Some(operation.to_string())),
+                None => (target.trim(), None),
+            };
+            if hw.is_empty() {
+                return Err(format!("missing component in {directive}"));
+            }
+
+            filter.rules.push(FilterRule {
+                hw: hw.to_string(),
+                operation,
+                level,
+            });
+        }
+
+        Ok(filter)
+    }
+}
+
+// most specific matching rule, later rules win ties, Some(None) means switched off
+pub(super) fn find_rule_level(
+    rules: &[FilterRule],
+    hw: &str,
+    operation: Option<&str>,
+) -> Option<Option<LogLevel>> {
+    rules
+        .iter()
+        .filter(|rule| rule.matches(hw, operation))
+        .max_by_key(|rule| rule.specificity())
+        .map(|rule| rule.level)
+}
+
+// case sensitive glob where * matches any run of characters
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let Some((prefix, rest)) = pattern.split_once('*') else {
+        return pattern == text;
+    };
+    let Some(mut remaining) = text.strip_prefix(prefix) else {
+        return false;
+    };
+
+    let mut parts: Vec<&str> = rest.split('*').collect();
+    let suffix = parts.pop().unwrap_or_default();
+    for part in parts {
+        match remaining.find(part) {
+            Some(position) => remaining = &remaining[position + part.len()..],
+            None => return false,
+        }
+    }
+
+    remaining.len() >= suffix.len() && remaining.ends_with(suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level_of(spec: &str, hw: &str, operation: Option<&str>) -> Option<Option<LogLevel>> {
+        find_rule_level(&LogFilter::parse(spec).unwrap().rules, hw, operation)
+    }
+
+    #[test]
+    fn parses_global_level_and_rules() {
+        let filter = LogFilter::parse(" warn , CPU=debug,MEM::read=off ").unwrap();
+
+        assert_eq!(filter.global_level, Some(LogLevel::Warn));
+        assert_eq!(
+            filter.rules,
+            [
+                FilterRule {
+                    hw: "CPU".to_string(),
+                    operation: None,
+                    level: Some(LogLevel::Debug),
+                },
+                FilterRule {
+                    hw: "MEM".to_string(),
+                    operation: Some("read".to_string()),
+                    level: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_levels_and_missing_components() {
+        assert!(LogFilter::parse("loud").is_err());
+        assert!(LogFilter::parse("CPU=loud").is_err());
+        assert!(LogFilter::parse("=debug").is_err());
+        assert!(LogFilter::parse("::step=debug").is_err());
+    }
+
+    #[test]
+    fn wildcards_match_any_run_of_characters() {
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("*", "CPU"));
+        assert!(wildcard_match("C*", "CPU"));
+        assert!(wildcard_match("*U", "CPU"));
+        assert!(wildcard_match("C*U", "CU"));
+        assert!(wildcard_match("step*_in*", "step_back_into"));
+        assert!(!wildcard_match("C*U", "CPUX"));
+        assert!(!wildcard_match("a*a", "a"));
+        assert!(!wildcard_match("cpu", "CPU"));
+    }
+
+    #[test]
+    fn operation_rules_need_an_operation() {
+        assert_eq!(level_of("CPU::step=debug", "CPU", None), None);
+        assert_eq!(
+            level_of("CPU::st*=debug", "CPU", Some("step")),
+            Some(Some(LogLevel::Debug))
+        );
+        assert_eq!(level_of("CPU::st*=debug", "CPU", Some("reset")), None);
+    }
+
+    #[test]
+    fn operation_rule_beats_component_rule() {
+        let spec = "CPU::step=off,CPU=verbose";
+
+        assert_eq!(level_of(spec, "CPU", Some("step")), Some(None));
+        assert_eq!(
+            level_of(spec, "CPU", Some("reset")),
+            Some(Some(LogLevel::Verbose))
+        );
+    }
+
+    #[test]
+    fn more_literal_pattern_wins() {
+        let spec = "M*=error,MEM=debug,*=warn";
+
+        assert_eq!(level_of(spec, "MEM", None), Some(Some(LogLevel::Debug)));
+        assert_eq!(level_of(spec, "MAP", None), Some(Some(LogLevel::Error)));
+        assert_eq!(level_of(spec, "CPU", None), Some(Some(LogLevel::Warn)));
+    }
+
+    #[test]
+    fn later_rule_wins_ties() {
+        assert_eq!(level_of("CPU=debug,CPU=off", "CPU", None), Some(None));
+        assert_eq!(
+            level_of("C*=off,*U=info", "CPU", None),
+            Some(Some(LogLevel::Info))
+        );
+    }
+}
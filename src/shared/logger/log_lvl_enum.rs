@@ -1,3 +1,4 @@
+// ordered by verbosity, a threshold admits its own level and every one before it
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 #[repr(u8)]
 pub enum LogLevel {
@@ -21,6 +22,18 @@ impl LogLevel {
         }
     }
 
+    // case insensitive name, "warning" and "trace" are accepted as aliases
+    pub fn parse(input: &str) -> Option<LogLevel> {
+        match input.trim().to_ascii_lowercase().as_str() {
+            "error" => Some(LogLevel::Error),
+            "warn" | "warning" => Some(LogLevel::Warn),
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            "verbose" | "trace" => Some(LogLevel::Verbose),
+            _ => None,
+        }
+    }
+
     pub fn ansi_color_code(&self) -> &'static str {
         match self {
             LogLevel::Error => "\x1b[31m",
@@ -0,0 +1,184 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{Map, Value};
+
+use super::LogLevel;
+
+// hardware name of records bridged in from the host application's log/tracing
+pub const HOST_HW_NAME: &str = "HOST";
+
+// single log call, owned so sinks can keep it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+    pub level: LogLevel,
+    pub hw: &'static str,
+    pub operation: String,
+    pub ctx: Option<String>,
+    // structured context, filled instead of ctx in JSON mode
+    pub fields: Vec<(&'static str, Value)>,
+    pub timestamp: DateTime<Utc>,
+    pub message: String,
+}
+
+// how text sinks render records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogFormat {
+    pub use_colors: bool,
+    pub use_timestamps: bool,
+    pub json: bool,
+}
+
+impl LogRecord {
+    pub fn format(&self, format: LogFormat) -> String {
+        if format.json {
+            self.format_json(format.use_timestamps)
+        } else {
+            self.format_line(format)
+        }
+    }
+
+    // one object per line: timestamp, level, hw, operation, ctx fields, message
+    pub fn format_json(&self, use_timestamps: bool) -> String {
+        let mut object = Map::new();
+
+        if use_timestamps {
+            object.insert(
+                "timestamp".to_string(),
+                Value::from(self.timestamp.to_rfc3339_opts(SecondsFormat::Micros, true)),
+            );
+        }
+        object.insert("level".to_string(), Value::from(self.level.as_str()));
+        object.insert("hw".to_string(), Value::from(self.hw));
+        object.insert(
+            "operation".to_string(),
+            Value::from(self.operation.as_str()),
+        );
+
+        if !self.fields.is_empty() {
+            let fields = self
+                .fields
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect();
+            object.insert("ctx".to_string(), Value::Object(fields));
+        } else if let Some(ctx) = &self.ctx {
+            object.insert("ctx".to_string(), Value::from(ctx.as_str()));
+        }
+
+        object.insert("message".to_string(), Value::from(self.message.as_str()));
+
+        Value::Object(object).to_string()
+    }
+
+    // "[LEVEL] [HW]::[operation] [ctx] [timestamp]: message", wrapped in ANSI color codes on request
+    pub fn format_line(&self, format: LogFormat) -> String {
+        let mut line = String::new();
+
+        if format.use_colors {
+            line.push_str(self.level.ansi_color_code());
+        }
+
+        line.push_str(&format!(
+            "[{}] [{}]::[{}] ",
+            self.level.as_str(),
+            self.hw,
+            self.operation
+        ));
+
+        if let Some(ctx) = &self.ctx {
+            line.push_str(&format!("[{ctx}] "));
+        }
+
+        if format.use_timestamps {
+            line.push_str(&format!("[{}]", self.timestamp.format("%Y-%m-%d %H:%M:%S")));
+        }
+
+        line.push_str(&format!(": {}", self.message));
+
+        if format.use_colors {
+            line.push_str(LogLevel::ANSI_RESET);
+        }
+
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    const JSON: LogFormat = LogFormat {
+        use_colors: true,
+        use_timestamps: true,
+        json: true,
+    };
+
+    fn record(ctx: Option<&str>, fields: Vec<(&'static str, Value)>) -> LogRecord {
+        LogRecord {
+            level: LogLevel::Error,
+            hw: "CPU",
+            operation: "step".to_string(),
+            ctx: ctx.map(str::to_string),
+            fields,
+            timestamp: Utc.with_ymd_and_hms(2024, 5, 6, 7, 8, 9).unwrap(),
+            message: "opcode \"$02\" is\nunimplemented".to_string(),
+        }
+    }
+
+    fn parsed(line: &str) -> Map<String, Value> {
+        assert!(!line.contains('\n'));
+        match serde_json::from_str(line).unwrap() {
+            Value::Object(object) => object,
+            _ => panic!("expected a JSON object"),
+        }
+    }
+
+    #[test]
+    fn json_line_has_fixed_keys_without_colors() {
+        let line = record(None, Vec::new()).format(JSON);
+
+        let object = parsed(&line);
+        let mut keys: Vec<&str> = object.keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, ["hw", "level", "message", "operation", "timestamp"]);
+        assert_eq!(object["timestamp"], "2024-05-06T07:08:09.000000Z");
+        assert_eq!(object["level"], "ERROR");
+        assert_eq!(object["message"], "opcode \"$02\" is\nunimplemented");
+        assert!(!line.contains('\u{1b}'));
+    }
+
+    #[test]
+    fn json_fields_replace_text_context() {
+        let fields = vec![("pc", Value::from("$0200")), ("cycles", Value::from(7))];
+
+        let object = parsed(&record(Some("PC=$0200"), fields).format_json(false));
+
+        assert!(!object.contains_key("timestamp"));
+        assert_eq!(object["ctx"]["pc"], "$0200");
+        assert_eq!(object["ctx"]["cycles"], 7);
+    }
+
+    #[test]
+    fn json_falls_back_to_text_context() {
+        let object = parsed(&record(Some("PC=$0200"), Vec::new()).format(JSON));
+
+        assert_eq!(object["ctx"], "PC=$0200");
+    }
+
+    #[test]
+    fn text_line_keeps_its_layout() {
+        let format = LogFormat {
+            use_colors: false,
+            use_timestamps: true,
+            json: false,
+        };
+
+        let line = record(Some("PC=$0200"), Vec::new()).format(format);
+
+        assert_eq!(
+            line,
+            "[ERROR] [CPU]::[step] [PC=$0200] [2024-05-06 07:08:09]: opcode \"$02\" is\nunimplemented"
+        );
+    }
+}
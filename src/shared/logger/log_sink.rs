@@ -0,0 +1,76 @@
+use std::io::{self, Write};
+
+use super::log_record::{LogFormat, LogRecord};
+
+// destination of log records, the logger filters by level before calling it
+pub trait LogSink: Send {
+    fn write_record(&mut self, record: &LogRecord, format: LogFormat);
+
+    fn flush(&mut self) {}
+
+    // structured sinks get context fields even when output is not JSON
+    fn wants_fields(&self) -> bool {
+        false
+    }
+}
+
+// default sink, keeps log lines out of program and protocol output on stdout
+pub struct StderrSink;
+
+impl LogSink for StderrSink {
+    fn write_record(&mut self, record: &LogRecord, format: LogFormat) {
+        eprintln!("{}", record.format(format));
+    }
+}
+
+// any writer, lines are never colored since it is rarely a terminal
+pub struct WriterSink<W: Write + Send> {
+    writer: W,
+}
+
+impl<W: Write + Send> WriterSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write + Send> LogSink for WriterSink<W> {
+    fn write_record(&mut self, record: &LogRecord, format: LogFormat) {
+        let format = LogFormat {
+            use_colors: false,
+            ..format
+        };
+        // a failing log destination must not take the emulator down
+        let _ = writeln!(self.writer, "{}", record.format(format));
+    }
+
+    fn flush(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+// hands every record to a closure, e.g. to forward it into a host application,
+// the closure runs under the sink lock and must not log through LoggingHw itself
+pub struct CallbackSink<F: FnMut(&LogRecord) + Send> {
+    callback: F,
+}
+
+impl<F: FnMut(&LogRecord) + Send> CallbackSink<F> {
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+impl<F: FnMut(&LogRecord) + Send> LogSink for CallbackSink<F> {
+    fn write_record(&mut self, record: &LogRecord, _format: LogFormat) {
+        (self.callback)(record);
+    }
+}
+
+pub(super) fn io_error_to_stderr(context: &str, error: &io::Error) {
+    eprintln!("log sink {context}: {error}");
+}
@@ -1,64 +1,272 @@
 use std::{
-    collections::HashMap,
+    env,
     sync::{
-        atomic::{AtomicBool, AtomicU8, Ordering},
+        atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering},
         Mutex, OnceLock,
     },
 };
 
 use crate::shared::logger::log_lvl_enum::LogLevel;
 
+use super::{
+    log_filter::{find_rule_level, FilterRule, LogFilter, LOG_FILTER_ENV},
+    log_record::{LogFormat, LogRecord},
+    log_sink::{LogSink, StderrSink},
+};
+
+// handle for changing or removing a sink after it was added
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct SinkId(u32);
+
+impl SinkId {
+    // stderr sink every logger starts with
+    pub const STDERR: SinkId = SinkId(0);
+}
+
+struct SinkEntry {
+    id: SinkId,
+    // most verbose level this sink takes
+    level: LogLevel,
+    sink: Box<dyn LogSink>,
+}
+
+// level_limit value while no record can pass, disabled or without sinks
+const NO_LEVEL: u8 = 0;
+
 pub struct Logger {
     is_enabled: AtomicBool,
+    // one past the most verbose level any filter and any sink admits, lets disabled
+    // calls return after one atomic load instead of taking the rules lock
+    level_limit: AtomicU8,
+    has_rules: AtomicBool,
     global_level: AtomicU8,
-    hw_levels: Mutex<HashMap<&'static str, LogLevel>>,
+    // per component and operation overrides of global_level
+    rules: Mutex<Vec<FilterRule>>,
     use_colors: AtomicBool,
     use_timestamps: AtomicBool,
+    use_json: AtomicBool,
+    // a sink asked for context fields, kept next to level_limit
+    sinks_want_fields: AtomicBool,
+    sinks: Mutex<Vec<SinkEntry>>,
+    next_sink_id: AtomicU32,
 }
 
 static LOGGER: OnceLock<Logger> = OnceLock::new();
 
 pub fn logger() -> &'static Logger {
-    LOGGER.get_or_init(|| Logger::new())
+    LOGGER.get_or_init(Logger::new)
 }
 
 impl Logger {
     fn new() -> Self {
-        Self {
+        let logger = Self {
             is_enabled: AtomicBool::new(true),
+            level_limit: AtomicU8::new(u8::from(LogLevel::Info) + 1),
+            has_rules: AtomicBool::new(false),
             global_level: AtomicU8::new(LogLevel::Info.into()),
-            hw_levels: Mutex::new(HashMap::with_capacity(5)),
+            rules: Mutex::new(Vec::new()),
             use_colors: AtomicBool::new(true),
             use_timestamps: AtomicBool::new(true),
+            use_json: AtomicBool::new(false),
+            sinks_want_fields: AtomicBool::new(false),
+            sinks: Mutex::new(vec![SinkEntry {
+                id: SinkId::STDERR,
+                level: LogLevel::Verbose,
+                sink: Box::new(StderrSink),
+            }]),
+            next_sink_id: AtomicU32::new(1),
+        };
+
+        // tests assert on default filters, a developer's CPU_EMU_LOG must not change them
+        if cfg!(test) {
+            return logger;
+        }
+        if let Ok(spec) = env::var(LOG_FILTER_ENV) {
+            if let Err(error) = logger.configure(&spec) {
+                eprintln!("ignoring {LOG_FILTER_ENV}: {error}");
+            }
+        }
+
+        logger
+    }
+
+    // level is the most verbose one the sink receives, on top of the hardware filters
+    pub fn add_sink<S: LogSink + 'static>(&self, sink: S, level: LogLevel) -> SinkId {
+        let id = SinkId(self.next_sink_id.fetch_add(1, Ordering::Relaxed));
+        self.sinks.lock().unwrap().push(SinkEntry {
+            id,
+            level,
+            sink: Box::new(sink),
+        });
+        self.refresh_level_limit();
+
+        id
+    }
+
+    pub fn remove_sink(&self, id: SinkId) -> bool {
+        let mut sinks = self.sinks.lock().unwrap();
+        let count_before = sinks.len();
+        sinks.retain(|entry| entry.id != id);
+        let is_removed = sinks.len() != count_before;
+        drop(sinks);
+        self.refresh_level_limit();
+
+        is_removed
+    }
+
+    pub fn set_sink_level(&self, id: SinkId, level: LogLevel) -> bool {
+        let mut sinks = self.sinks.lock().unwrap();
+        match sinks.iter_mut().find(|entry| entry.id == id) {
+            Some(entry) => {
+                entry.level = level;
+                drop(sinks);
+                self.refresh_level_limit();
+                true
+            }
+            None => false,
         }
     }
 
+    // stderr included, records are dropped until a sink is added
+    pub fn clear_sinks(&self) {
+        self.sinks.lock().unwrap().clear();
+        self.refresh_level_limit();
+    }
+
+    pub fn flush(&self) {
+        for entry in self.sinks.lock().unwrap().iter_mut() {
+            entry.sink.flush();
+        }
+    }
+
+    // hands an already filtered record to every sink whose level admits it
+    pub fn dispatch(&self, record: &LogRecord) {
+        let format = LogFormat {
+            use_colors: self.use_colors(),
+            use_timestamps: self.use_timestamps(),
+            json: self.use_json(),
+        };
+
+        for entry in self.sinks.lock().unwrap().iter_mut() {
+            if record.level <= entry.level {
+                entry.sink.write_record(record, format);
+            }
+        }
+    }
+
+    // component filters only, operation specific rules are ignored
     pub fn is_log_level_enabled_for_hw(&self, hw: &'static str, log_level: LogLevel) -> bool {
-        if !self.is_enabled.load(Ordering::Relaxed) {
+        self.is_enabled_for(hw, None, log_level)
+    }
+
+    pub fn is_log_level_enabled_for_operation(
+        &self,
+        hw: &'static str,
+        operation: &str,
+        log_level: LogLevel,
+    ) -> bool {
+        self.is_enabled_for(hw, Some(operation), log_level)
+    }
+
+    // no filter or sink takes records this verbose, one atomic load
+    pub fn may_log(&self, log_level: LogLevel) -> bool {
+        u8::from(log_level) < self.level_limit.load(Ordering::Relaxed)
+    }
+
+    // a level threshold admits itself and everything more severe
+    fn is_enabled_for(&self, hw: &str, operation: Option<&str>, log_level: LogLevel) -> bool {
+        if !self.may_log(log_level) {
             return false;
         }
-
-        let hw_levels_map = self.hw_levels.lock().unwrap();
-        if let Some(hw_level) = hw_levels_map.get(hw) {
-            return log_level >= *hw_level;
+        if !self.has_rules.load(Ordering::Relaxed) {
+            return log_level <= self.global_level();
         }
 
-        let global_level = self.global_level.load(Ordering::Relaxed);
+        let threshold = match find_rule_level(&self.rules.lock().unwrap(), hw, operation) {
+            Some(Some(level)) => level,
+            Some(None) => return false,
+            None => self.global_level.load(Ordering::Relaxed).into(),
+        };
+
+        log_level <= threshold
+    }
 
-        log_level >= global_level.into()
+    pub fn global_level(&self) -> LogLevel {
+        self.global_level.load(Ordering::Relaxed).into()
     }
 
     pub fn set_global_level(&self, level: LogLevel) {
         self.global_level.store(level.into(), Ordering::Relaxed);
+        self.refresh_level_limit();
     }
 
     pub fn set_hw_level(&self, hw: &'static str, level: LogLevel) {
-        let mut hw_levels_map = self.hw_levels.lock().unwrap();
-        hw_levels_map.insert(hw, level);
+        self.add_filter_rule(FilterRule {
+            hw: hw.to_string(),
+            operation: None,
+            level: Some(level),
+        });
+    }
+
+    // replaces a rule for the same names
+    pub fn add_filter_rule(&self, rule: FilterRule) {
+        let mut rules = self.rules.lock().unwrap();
+        rules.retain(|existing| existing.hw != rule.hw || existing.operation != rule.operation);
+        rules.push(rule);
+        drop(rules);
+        self.refresh_level_limit();
+    }
+
+    pub fn clear_filter_rules(&self) {
+        self.rules.lock().unwrap().clear();
+        self.refresh_level_limit();
+    }
+
+    // "info,CPU=debug,MEM=warn,GDB::serve=off,*::fetch*=verbose", added on top of existing rules
+    pub fn configure(&self, spec: &str) -> Result<(), String> {
+        let filter = LogFilter::parse(spec)?;
+
+        if let Some(level) = filter.global_level {
+            self.set_global_level(level);
+        }
+        for rule in filter.rules {
+            self.add_filter_rule(rule);
+        }
+
+        Ok(())
     }
 
     pub fn set_enabled(&self, enabled: bool) {
         self.is_enabled.store(enabled, Ordering::Relaxed);
+        self.refresh_level_limit();
+    }
+
+    // called after every change of filters or sinks
+    fn refresh_level_limit(&self) {
+        let rules = self.rules.lock().unwrap();
+        let filter_level = rules
+            .iter()
+            .filter_map(|rule| rule.level)
+            .chain([self.global_level()])
+            .max();
+        self.has_rules.store(!rules.is_empty(), Ordering::Relaxed);
+        drop(rules);
+
+        let sinks = self.sinks.lock().unwrap();
+        let sink_level = sinks.iter().map(|entry| entry.level).max();
+        self.sinks_want_fields.store(
+            sinks.iter().any(|entry| entry.sink.wants_fields()),
+            Ordering::Relaxed,
+        );
+        drop(sinks);
+
+        let level_limit = match (filter_level, sink_level) {
+            (Some(filter_level), Some(sink_level)) if self.is_enabled.load(Ordering::Relaxed) => {
+                u8::from(filter_level.min(sink_level)) + 1
+            }
+            _ => NO_LEVEL,
+        };
+        self.level_limit.store(level_limit, Ordering::Relaxed);
     }
 
     pub fn use_colors(&self) -> bool {
@@ -76,4 +284,120 @@ impl Logger {
     pub fn set_use_timestamps(&self, use_timestamps: bool) {
         self.use_timestamps.store(use_timestamps, Ordering::Relaxed);
     }
+
+    // JSON lines for tooling instead of colored text, context comes as fields
+    pub fn use_json(&self) -> bool {
+        self.use_json.load(Ordering::Relaxed)
+    }
+
+    pub fn set_use_json(&self, use_json: bool) {
+        self.use_json.store(use_json, Ordering::Relaxed);
+    }
+
+    // records need context fields, for JSON output or a structured sink
+    pub fn wants_fields(&self) -> bool {
+        self.use_json() || self.sinks_want_fields.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::Utc;
+    use serde_json::Value;
+
+    use super::*;
+
+    const ALL_LEVELS: [LogLevel; 5] = [
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Verbose,
+    ];
+
+    #[test]
+    fn default_logger_admits_info_and_more_severe() {
+        let logger = Logger::new();
+        logger.clear_filter_rules();
+        logger.set_global_level(LogLevel::Info);
+
+        assert!(logger.may_log(LogLevel::Error));
+        assert!(logger.is_log_level_enabled_for_hw("CPU", LogLevel::Info));
+        assert!(!logger.may_log(LogLevel::Debug));
+        assert!(!logger.is_log_level_enabled_for_hw("CPU", LogLevel::Debug));
+    }
+
+    #[test]
+    fn disabled_logger_rejects_every_level() {
+        let logger = Logger::new();
+        logger.set_global_level(LogLevel::Verbose);
+        logger.set_enabled(false);
+
+        for level in ALL_LEVELS {
+            assert!(!logger.may_log(level));
+            assert!(!logger.is_log_level_enabled_for_hw("CPU", level));
+            assert!(!logger.is_log_level_enabled_for_operation("CPU", "step", level));
+        }
+
+        logger.set_enabled(true);
+        assert!(logger.is_log_level_enabled_for_hw("CPU", LogLevel::Verbose));
+    }
+
+    #[test]
+    fn logger_without_sinks_rejects_every_level() {
+        let logger = Logger::new();
+        logger.set_global_level(LogLevel::Verbose);
+        logger.clear_sinks();
+
+        for level in ALL_LEVELS {
+            assert!(!logger.may_log(level));
+            assert!(!logger.is_log_level_enabled_for_hw("CPU", level));
+        }
+    }
+
+    #[test]
+    fn sink_level_caps_what_filters_admit() {
+        let logger = Logger::new();
+        logger.set_global_level(LogLevel::Verbose);
+        logger.set_sink_level(SinkId::STDERR, LogLevel::Warn);
+
+        assert!(logger.may_log(LogLevel::Warn));
+        assert!(!logger.may_log(LogLevel::Info));
+    }
+
+    // formatted lines, as a text sink would write them
+    struct LineSink(Arc<Mutex<Vec<String>>>);
+
+    impl LogSink for LineSink {
+        fn write_record(&mut self, record: &LogRecord, format: LogFormat) {
+            self.0.lock().unwrap().push(record.format(format));
+        }
+    }
+
+    #[test]
+    fn json_mode_formats_dispatched_records_as_json_lines() {
+        let logger = Logger::new();
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        logger.clear_sinks();
+        logger.add_sink(LineSink(lines.clone()), LogLevel::Verbose);
+        logger.set_use_json(true);
+
+        assert!(logger.wants_fields());
+        logger.dispatch(&LogRecord {
+            level: LogLevel::Warn,
+            hw: "MEM",
+            operation: "write".to_string(),
+            ctx: None,
+            fields: vec![("pc", Value::from("$0200"))],
+            timestamp: Utc::now(),
+            message: "write ignored".to_string(),
+        });
+
+        let line: Value = serde_json::from_str(&lines.lock().unwrap()[0]).unwrap();
+        assert_eq!(line["level"], "WARN");
+        assert_eq!(line["ctx"]["pc"], "$0200");
+        assert!(line["timestamp"].is_string());
+    }
 }
@@ -1,8 +1,17 @@
+use std::{error::Error, fmt};
+
 use chrono::Utc;
+use serde_json::Value;
+
+use crate::shared::error_chain::error_chain;
 
-use super::logger;
+use super::{log_record::LogRecord, logger, LogLevel};
 
-use super::LogLevel;
+// verbose and debug calls compile to nothing with the max-level-info feature
+#[cfg(feature = "max-level-info")]
+const STATIC_MAX_LEVEL: LogLevel = LogLevel::Info;
+#[cfg(not(feature = "max-level-info"))]
+const STATIC_MAX_LEVEL: LogLevel = LogLevel::Verbose;
 
 pub trait LoggingHw {
     fn hw_name(&self) -> &'static str;
@@ -10,40 +19,62 @@ pub trait LoggingHw {
         None
     }
 
-    fn log(&self, level: LogLevel, operation: &str, message: &str) {
-        let hw = self.hw_name();
+    // named context values for JSON output, get_ctx is used when empty
+    fn get_ctx_fields(&self) -> Vec<(&'static str, Value)> {
+        Vec::new()
+    }
 
-        if !logger().is_log_level_enabled_for_hw(hw, level) {
-            return;
+    // cheap check to guard building an expensive message
+    fn log_enabled(&self, level: LogLevel, operation: &str) -> bool {
+        if level > STATIC_MAX_LEVEL {
+            return false;
         }
 
-        let mut log_string_builder = String::new();
-        let use_colors = logger().use_colors();
+        logger().is_log_level_enabled_for_operation(self.hw_name(), operation, level)
+    }
 
-        if use_colors {
-            log_string_builder.push_str(level.ansi_color_code());
+    fn log(&self, level: LogLevel, operation: &str, message: &str) {
+        if !self.log_enabled(level, operation) {
+            return;
         }
 
-        log_string_builder.push_str(&format!("[{}] [{}]::[{}] ", level.as_str(), hw, operation));
-
-        match self.get_ctx() {
-            Some(ctx) => {
-                log_string_builder.push_str(&format!("[{}] ", ctx));
-            }
-            None => {}
-        }
+        self.emit(level, operation, message.to_string());
+    }
 
-        if logger().use_timestamps() {
-            log_string_builder.push_str(&format!("[{}]", Utc::now().format("%Y-%m-%d %H:%M:%S")));
+    // message is only formatted when the record passes the filters
+    fn log_fmt(&self, level: LogLevel, operation: &str, args: fmt::Arguments) {
+        if !self.log_enabled(level, operation) {
+            return;
         }
 
-        log_string_builder.push_str(&format!(": {}", message));
+        self.emit(level, operation, args.to_string());
+    }
 
-        if use_colors {
-            log_string_builder.push_str(LogLevel::ANSI_RESET);
-        }
+    // context is built here, after the level check
+    fn emit(&self, level: LogLevel, operation: &str, message: String) {
+        let hw = self.hw_name();
 
-        println!("{}", log_string_builder);
+        let fields = if logger().wants_fields() {
+            self.get_ctx_fields()
+        } else {
+            Vec::new()
+        };
+        // text sinks still render ctx when only a structured sink asked for fields
+        let ctx = if fields.is_empty() || !logger().use_json() {
+            self.get_ctx()
+        } else {
+            None
+        };
+
+        logger().dispatch(&LogRecord {
+            level,
+            hw,
+            operation: operation.to_string(),
+            ctx,
+            fields,
+            timestamp: Utc::now(),
+            message,
+        });
     }
 
     fn log_verbose(&self, operation: &str, message: &str) {
@@ -65,4 +96,13 @@ pub trait LoggingHw {
     fn log_error(&self, operation: &str, message: &str) {
         self.log(LogLevel::Error, operation, message);
     }
+
+    // logs error followed by its source chain
+    fn log_error_source(&self, operation: &str, error: &dyn Error) {
+        if !self.log_enabled(LogLevel::Error, operation) {
+            return;
+        }
+
+        self.emit(LogLevel::Error, operation, error_chain(error));
+    }
 }
@@ -1,6 +1,15 @@
+#[cfg(feature = "log")]
+pub mod log_bridge;
+pub mod log_filter;
 pub mod log_lvl_enum;
+pub mod log_record;
+pub mod log_sink;
 pub mod logger;
 pub mod logging_hw_trait;
+pub mod ring_buffer_sink;
+pub mod rotating_file_sink;
+#[cfg(feature = "tracing")]
+pub mod tracing_bridge;
 
 pub use log_lvl_enum::LogLevel;
 pub use logger::logger;
@@ -0,0 +1,53 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use super::{
+    log_record::{LogFormat, LogRecord},
+    log_sink::LogSink,
+};
+
+// keeps the newest records in memory, clones share the buffer so one
+// can be handed to the logger while another is queried
+#[derive(Clone)]
+pub struct RingBufferSink {
+    capacity: usize,
+    records: Arc<Mutex<VecDeque<LogRecord>>>,
+}
+
+impl RingBufferSink {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            records: Arc::new(Mutex::new(VecDeque::with_capacity(capacity.max(1)))),
+        }
+    }
+
+    // oldest first
+    pub fn records(&self) -> Vec<LogRecord> {
+        self.records.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.records.lock().unwrap().clear();
+    }
+}
+
+impl LogSink for RingBufferSink {
+    fn write_record(&mut self, record: &LogRecord, _format: LogFormat) {
+        let mut records = self.records.lock().unwrap();
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record.clone());
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use super::{
+    log_record::{LogFormat, LogRecord},
+    log_sink::{io_error_to_stderr, LogSink},
+};
+
+pub const DEFAULT_LOG_FILE_MAX_BYTES: u64 = 10 * 1024 * 1024;
+pub const DEFAULT_LOG_FILE_COUNT: usize = 3;
+
+// appends to path, once it grows past max_bytes it becomes path.1, path.1 becomes path.2
+// and so on, files beyond max_files rotated copies are deleted
+pub struct RotatingFileSink {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: Option<BufWriter<File>>,
+    written_bytes: u64,
+}
+
+impl RotatingFileSink {
+    pub fn new<P: AsRef<Path>>(path: P, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written_bytes = file.metadata()?.len();
+
+        Ok(Self {
+            path,
+            max_bytes: max_bytes.max(1),
+            max_files,
+            file: Some(BufWriter::new(file)),
+            written_bytes,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        PathBuf::from(path)
+    }
+
+    // the handle is only dropped once everything buffered reached the file
+    fn rotate(&mut self) -> io::Result<()> {
+        if let Some(file) = self.file.as_mut() {
+            file.flush()?;
+        }
+        self.file = None;
+
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let oldest = self.rotated_path(self.max_files);
+            if oldest.exists() {
+                fs::remove_file(oldest)?;
+            }
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        self.file = Some(BufWriter::new(File::create(&self.path)?));
+        self.written_bytes = 0;
+
+        Ok(())
+    }
+
+    // after a rotation that failed part way records go to whatever file is at path now
+    fn reopen(&mut self) -> io::Result<()> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.file = Some(BufWriter::new(file));
+
+        Ok(())
+    }
+}
+
+impl LogSink for RotatingFileSink {
+    fn write_record(&mut self, record: &LogRecord, format: LogFormat) {
+        if self.written_bytes >= self.max_bytes {
+            if let Err(error) = self.rotate() {
+                io_error_to_stderr("rotation failed", &error);
+                // retried once another max_bytes went into the current file
+                self.written_bytes = 0;
+            }
+        }
+
+        if self.file.is_none() {
+            if let Err(error) = self.reopen() {
+                io_error_to_stderr("reopen failed", &error);
+                return;
+            }
+        }
+        let Some(file) = self.file.as_mut() else {
+            return;
+        };
+
+        let line = record.format(LogFormat {
+            use_colors: false,
+            ..format
+        });
+        match writeln!(file, "{line}") {
+            Ok(()) => self.written_bytes += line.len() as u64 + 1,
+            Err(error) => io_error_to_stderr("write failed", &error),
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(file) = self.file.as_mut() {
+            let _ = file.flush();
+        }
+    }
+}
+
+impl Drop for RotatingFileSink {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use crate::shared::logger::LogLevel;
+
+    use super::*;
+
+    const FORMAT: LogFormat = LogFormat {
+        use_colors: false,
+        use_timestamps: false,
+        json: false,
+    };
+
+    fn record(message: &str) -> LogRecord {
+        LogRecord {
+            level: LogLevel::Info,
+            hw: "CPU",
+            operation: "step".to_string(),
+            ctx: None,
+            fields: Vec::new(),
+            timestamp: Utc::now(),
+            message: message.to_string(),
+        }
+    }
+
+    fn log_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cpu_emu_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn rotates_into_numbered_files() {
+        let dir = log_dir("rotates");
+        let path = dir.join("emu.log");
+        let mut sink = RotatingFileSink::new(&path, 1, 2).unwrap();
+
+        for message in ["first", "second", "third", "fourth"] {
+            sink.write_record(&record(message), FORMAT);
+        }
+        sink.flush();
+
+        let read = |path: PathBuf| fs::read_to_string(path).unwrap();
+        assert!(read(path.clone()).ends_with(": fourth\n"));
+        assert!(read(dir.join("emu.log.1")).ends_with(": third\n"));
+        assert!(read(dir.join("emu.log.2")).ends_with(": second\n"));
+        assert!(!dir.join("emu.log.3").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_logging_when_rotation_fails() {
+        let dir = log_dir("rotation_fails");
+        let path = dir.join("emu.log");
+        // a directory where the rotated file goes can not be removed as a file
+        fs::create_dir(dir.join("emu.log.1")).unwrap();
+        let mut sink = RotatingFileSink::new(&path, 1, 1).unwrap();
+
+        for message in ["first", "second", "third"] {
+            sink.write_record(&record(message), FORMAT);
+        }
+        sink.flush();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 3);
+        assert!(contents.ends_with(": third\n"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,247 @@
+use std::fmt;
+
+use chrono::Utc;
+use serde_json::{Map, Value};
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, Layer};
+
+use super::{
+    log_record::{LogFormat, LogRecord, HOST_HW_NAME},
+    log_sink::LogSink,
+    logger, LogLevel,
+};
+
+// target of events emitted for emulator records, the layer skips them so both directions can be active
+pub const TRACING_TARGET: &str = "cpu_emu::hw";
+
+fn from_tracing_level(level: Level) -> LogLevel {
+    match level {
+        Level::ERROR => LogLevel::Error,
+        Level::WARN => LogLevel::Warn,
+        Level::INFO => LogLevel::Info,
+        Level::DEBUG => LogLevel::Debug,
+        Level::TRACE => LogLevel::Verbose,
+    }
+}
+
+// tracing field names are fixed per callsite, these CPU context fields get their own,
+// any other record field ends up in a JSON object under "fields"
+const CONTEXT_FIELDS: [&str; 8] = ["cycles", "pc", "sp", "a", "x", "y", "p", "symbol"];
+
+// emulator records as tracing events with hw, operation and context fields,
+// ctx is only sent for records without fields
+pub struct TracingSink;
+
+impl LogSink for TracingSink {
+    // host records came from the host ecosystem and are not echoed back
+    fn write_record(&mut self, record: &LogRecord, _format: LogFormat) {
+        if record.hw == HOST_HW_NAME {
+            return;
+        }
+
+        let field = |name: &str| {
+            record
+                .fields
+                .iter()
+                .find(|(field, _)| *field == name)
+                .map(|(_, value)| value)
+        };
+        let number = |name: &str| field(name).and_then(Value::as_u64);
+        let symbol = field("symbol").and_then(Value::as_str);
+        let ctx = match record.fields.is_empty() {
+            true => record.ctx.as_deref(),
+            false => None,
+        };
+        let other_fields: Map<String, Value> = record
+            .fields
+            .iter()
+            .filter(|(name, _)| !CONTEXT_FIELDS.contains(name))
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        let other_fields =
+            (!other_fields.is_empty()).then(|| Value::Object(other_fields).to_string());
+
+        // event! needs the level as a constant
+        macro_rules! emit {
+            ($level:expr) => {
+                tracing::event!(
+                    target: TRACING_TARGET,
+                    $level,
+                    hw = record.hw,
+                    operation = record.operation.as_str(),
+                    ctx,
+                    cycles = number("cycles"),
+                    pc = number("pc"),
+                    sp = number("sp"),
+                    a = number("a"),
+                    x = number("x"),
+                    y = number("y"),
+                    p = number("p"),
+                    symbol,
+                    fields = other_fields.as_deref(),
+                    "{}",
+                    record.message
+                )
+            };
+        }
+
+        match record.level {
+            LogLevel::Error => emit!(Level::ERROR),
+            LogLevel::Warn => emit!(Level::WARN),
+            LogLevel::Info => emit!(Level::INFO),
+            LogLevel::Debug => emit!(Level::DEBUG),
+            LogLevel::Verbose => emit!(Level::TRACE),
+        }
+    }
+
+    fn wants_fields(&self) -> bool {
+        true
+    }
+}
+
+// host tracing events into the emulator logger as HOST with the event target as operation,
+// event fields other than message become record fields
+pub struct TracingLayer;
+
+impl<S: Subscriber> Layer<S> for TracingLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let level = from_tracing_level(*metadata.level());
+        if metadata.target() == TRACING_TARGET
+            || !logger().is_log_level_enabled_for_operation(HOST_HW_NAME, metadata.target(), level)
+        {
+            return;
+        }
+
+        let mut visitor = FieldCollector::default();
+        event.record(&mut visitor);
+
+        logger().dispatch(&LogRecord {
+            level,
+            hw: HOST_HW_NAME,
+            operation: metadata.target().to_string(),
+            ctx: None,
+            fields: visitor.fields,
+            timestamp: Utc::now(),
+            message: visitor.message,
+        });
+    }
+}
+
+#[derive(Default)]
+struct FieldCollector {
+    message: String,
+    fields: Vec<(&'static str, Value)>,
+}
+
+impl Visit for FieldCollector {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.fields.push((field.name(), Value::from(value)));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.fields.push((field.name(), Value::from(value)));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.fields.push((field.name(), Value::from(value)));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields.push((field.name(), Value::from(value)));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+        } else {
+            self.fields
+                .push((field.name(), Value::from(format!("{value:?}"))));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+    use super::*;
+
+    // fields of every emulator event, collected the way TracingLayer reads host events
+    struct Capture(Arc<Mutex<Vec<(&'static str, Value)>>>);
+
+    impl<S: Subscriber> Layer<S> for Capture {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            let mut visitor = FieldCollector::default();
+            event.record(&mut visitor);
+            self.0.lock().unwrap().extend(visitor.fields);
+        }
+    }
+
+    fn forwarded_fields(record: &LogRecord) -> Vec<(&'static str, Value)> {
+        let fields = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = Registry::default().with(Capture(fields.clone()));
+        let format = LogFormat {
+            use_colors: false,
+            use_timestamps: false,
+            json: false,
+        };
+
+        tracing::subscriber::with_default(subscriber, || {
+            TracingSink.write_record(record, format);
+        });
+
+        let fields = fields.lock().unwrap().clone();
+        fields
+    }
+
+    fn record(ctx: Option<&str>, fields: Vec<(&'static str, Value)>) -> LogRecord {
+        LogRecord {
+            level: LogLevel::Info,
+            hw: "CPU",
+            operation: "step".to_string(),
+            ctx: ctx.map(str::to_string),
+            fields,
+            timestamp: Utc::now(),
+            message: "stepped".to_string(),
+        }
+    }
+
+    #[test]
+    fn forwards_record_fields_as_tracing_fields() {
+        let fields = forwarded_fields(&record(
+            Some("PC=$C000"),
+            vec![
+                ("pc", Value::from(0xC000)),
+                ("a", Value::from(0x10)),
+                ("symbol", Value::from("reset")),
+                ("bank", Value::from(3)),
+            ],
+        ));
+
+        assert!(fields.contains(&("pc", Value::from(0xC000))));
+        assert!(fields.contains(&("a", Value::from(0x10))));
+        assert!(fields.contains(&("symbol", Value::from("reset"))));
+        assert!(fields.contains(&("fields", Value::from(r#"{"bank":3}"#))));
+        assert!(fields
+            .iter()
+            .all(|(name, _)| *name != "ctx" && *name != "x"));
+    }
+
+    #[test]
+    fn sends_ctx_for_records_without_fields() {
+        let fields = forwarded_fields(&record(Some("PC=$C000"), Vec::new()));
+
+        assert!(fields.contains(&("ctx", Value::from("PC=$C000"))));
+        assert!(fields.iter().all(|(name, _)| *name != "pc"));
+    }
+}
@@ -1,4 +1,6 @@
 pub mod constants;
+pub mod error_chain;
 pub mod logger;
+pub mod parsing;
 pub mod traits;
 pub mod types;
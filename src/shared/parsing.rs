@@ -0,0 +1,39 @@
+use super::types::{Byte, Word};
+
+// accepts "$nnnn", "0xnnnn" and plain decimal
+pub fn parse_word(input: &str) -> Option<Word> {
+    let input = input.trim();
+
+    if let Some(hex) = input.strip_prefix('$') {
+        return Word::from_str_radix(hex, 16).ok();
+    }
+
+    if let Some(hex) = input
+        .strip_prefix("0x")
+        .or_else(|| input.strip_prefix("0X"))
+    {
+        return Word::from_str_radix(hex, 16).ok();
+    }
+
+    input.parse::<Word>().ok()
+}
+
+pub fn parse_byte(input: &str) -> Option<Byte> {
+    parse_word(input).and_then(|value| Byte::try_from(value).ok())
+}
+
+// monitor style, bare digits are hex, "$" and "0x" prefixes are accepted too
+pub fn parse_hex_word(input: &str) -> Option<Word> {
+    let input = input.trim();
+    let hex = input
+        .strip_prefix('$')
+        .or_else(|| input.strip_prefix("0x"))
+        .or_else(|| input.strip_prefix("0X"))
+        .unwrap_or(input);
+
+    Word::from_str_radix(hex, 16).ok()
+}
+
+pub fn parse_hex_byte(input: &str) -> Option<Byte> {
+    parse_hex_word(input).and_then(|value| Byte::try_from(value).ok())
+}
@@ -0,0 +1,293 @@
+use std::{fmt, fs, io, path::Path};
+
+use crate::shared::{parsing::parse_word, types::Word};
+
+use super::symbol_table::SymbolTable;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolFormat {
+    // cc65 toolchain debug info, ld65 --dbgfile
+    Ca65Debug,
+    // "al C:c123 .print_string", VICE monitor / ld65 -Ln
+    ViceLabels,
+    // "print_string = $C123"
+    LabelFile,
+}
+
+impl SymbolFormat {
+    pub fn detect(contents: &str) -> Self {
+        let first_line = contents
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !is_comment(line))
+            .unwrap_or_default();
+
+        if first_line.starts_with("version") && first_line.contains("major=") {
+            SymbolFormat::Ca65Debug
+        } else if first_line.starts_with("al ") {
+            SymbolFormat::ViceLabels
+        } else {
+            SymbolFormat::LabelFile
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SymbolLoadError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for SymbolLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolLoadError::Io(error) => write!(f, "{error}"),
+            SymbolLoadError::Parse { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl From<io::Error> for SymbolLoadError {
+    fn from(error: io::Error) -> Self {
+        SymbolLoadError::Io(error)
+    }
+}
+
+// format is picked from file contents, not the extension
+pub fn load_symbols<P: AsRef<Path>>(path: P) -> Result<SymbolTable, SymbolLoadError> {
+    let contents = fs::read_to_string(path)?;
+    parse_symbols(&contents, SymbolFormat::detect(&contents))
+}
+
+pub fn parse_symbols(contents: &str, format: SymbolFormat) -> Result<SymbolTable, SymbolLoadError> {
+    let mut symbols = SymbolTable::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || is_comment(line) {
+            continue;
+        }
+
+        let parsed = match format {
+            SymbolFormat::Ca65Debug => parse_ca65_debug_line(line),
+            SymbolFormat::ViceLabels => parse_vice_label_line(line),
+            SymbolFormat::LabelFile => parse_label_line(line),
+        }
+        .map_err(|message| SymbolLoadError::Parse {
+            line: index + 1,
+            message,
+        })?;
+
+        if let Some((name, address)) = parsed {
+            symbols.insert(name, address);
+        }
+    }
+
+    Ok(symbols)
+}
+
+fn is_comment(line: &str) -> bool {
+    line.starts_with(';') || line.starts_with('#')
+}
+
+// sym	id=0,name="print_string",addrsize=absolute,scope=0,def=12,ref=14,val=0xC123,seg=0,type=lab
+// only labels are taken, equates are constants as often as they are addresses
+fn parse_ca65_debug_line(line: &str) -> Result<Option<(String, Word)>, String> {
+    let Some((kind, attributes)) = line.split_once(char::is_whitespace) else {
+        return Ok(None);
+    };
+    if kind != "sym" {
+        return Ok(None);
+    }
+
+    let attributes = split_attributes(attributes.trim())?;
+    let attribute = |key: &str| {
+        attributes
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| value.as_str())
+    };
+
+    if attribute("type") != Some("lab") {
+        return Ok(None);
+    }
+
+    let name = attribute("name").ok_or("symbol without name")?;
+    let value = attribute("val").ok_or(format!("label {name} without value"))?;
+    let address = parse_word(value).ok_or(format!("invalid value {value} for {name}"))?;
+
+    Ok(Some((name.to_string(), address)))
+}
+
+// key=value pairs separated by commas, values may be quoted and contain commas
+fn split_attributes(input: &str) -> Result<Vec<(&str, String)>, String> {
+    let mut attributes = Vec::new();
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let (key, after_key) = rest
+            .split_once('=')
+            .ok_or(format!("expected key=value in \"{rest}\""))?;
+
+        let (value, after_value) = match after_key.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"').ok_or("unterminated string")?;
+                (quoted[..end].to_string(), &quoted[end + 1..])
+            }
+            None => {
+                let end = after_key.find(',').unwrap_or(after_key.len());
+                (after_key[..end].to_string(), &after_key[end..])
+            }
+        };
+
+        attributes.push((key.trim(), value));
+        rest = after_value
+            .strip_prefix(',')
+            .unwrap_or(after_value)
+            .trim_start();
+    }
+
+    Ok(attributes)
+}
+
+// al C:c123 .print_string
+fn parse_vice_label_line(line: &str) -> Result<Option<(String, Word)>, String> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let [command, address, name] = tokens.as_slice() else {
+        return Err(format!("expected \"al <addr> .<name>\", got \"{line}\""));
+    };
+    if *command != "al" {
+        return Ok(None);
+    }
+
+    // memory space prefix, C: is the CPU view
+    let address = address
+        .split_once(':')
+        .map_or(*address, |(_, address)| address);
+    let address =
+        Word::from_str_radix(address, 16).map_err(|_| format!("invalid address {address}"))?;
+
+    Ok(Some((name.trim_start_matches('.').to_string(), address)))
+}
+
+// print_string = $C123 ; trailing comment
+fn parse_label_line(line: &str) -> Result<Option<(String, Word)>, String> {
+    let line = line.split(';').next().unwrap_or_default().trim();
+    let (name, value) = line
+        .split_once('=')
+        .ok_or(format!("expected \"name = $addr\", got \"{line}\""))?;
+
+    let name = name.trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(format!("invalid symbol name \"{name}\""));
+    }
+    let address = parse_word(value).ok_or(format!("invalid address {}", value.trim()))?;
+
+    Ok(Some((name.to_string(), address)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CA65_DEBUG: &str = "version\tmajor=2,minor=0
+sym\tid=0,name=\"print_string\",addrsize=absolute,scope=0,def=12,ref=14,val=0xC123,seg=0,type=lab
+sym\tid=1,name=\"SCREEN_WIDTH\",addrsize=zeropage,scope=0,def=3,val=0x28,type=equ
+sym\tid=2,name=\"a,b\",addrsize=absolute,scope=0,def=20,val=0xC200,seg=0,type=lab
+";
+
+    fn parse_error_line(contents: &str, format: SymbolFormat) -> usize {
+        match parse_symbols(contents, format) {
+            Err(SymbolLoadError::Parse { line, .. }) => line,
+            _ => panic!("expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn detects_format_from_first_meaningful_line() {
+        assert_eq!(SymbolFormat::detect(CA65_DEBUG), SymbolFormat::Ca65Debug);
+        assert_eq!(
+            SymbolFormat::detect("; labels\n\nal C:c123 .print_string"),
+            SymbolFormat::ViceLabels
+        );
+        assert_eq!(
+            SymbolFormat::detect("# labels\nprint_string = $C123"),
+            SymbolFormat::LabelFile
+        );
+    }
+
+    #[test]
+    fn ca65_debug_takes_labels_only() {
+        let symbols = parse_symbols(CA65_DEBUG, SymbolFormat::Ca65Debug).unwrap();
+
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols.address_of("print_string"), Some(0xC123));
+        assert_eq!(symbols.address_of("a,b"), Some(0xC200));
+        assert_eq!(symbols.address_of("SCREEN_WIDTH"), None);
+    }
+
+    #[test]
+    fn ca65_debug_reports_broken_symbols() {
+        let contents = "version\tmajor=2,minor=0\nsym\tid=0,name=\"open,type=lab";
+        assert_eq!(parse_error_line(contents, SymbolFormat::Ca65Debug), 2);
+
+        let contents = "sym\tid=0,name=\"loop\",val=0x1FFFF,type=lab";
+        assert_eq!(parse_error_line(contents, SymbolFormat::Ca65Debug), 1);
+    }
+
+    #[test]
+    fn vice_labels_drop_memory_space_and_dot() {
+        let contents = "al C:c123 .print_string\nal 0200 .buffer\nbk C:c000 .ignored";
+
+        let symbols = parse_symbols(contents, SymbolFormat::ViceLabels).unwrap();
+
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols.address_of("print_string"), Some(0xC123));
+        assert_eq!(symbols.name_for(0x0200), Some("buffer"));
+    }
+
+    #[test]
+    fn vice_labels_report_malformed_lines() {
+        assert_eq!(
+            parse_error_line("al C:zzzz .broken", SymbolFormat::ViceLabels),
+            1
+        );
+        assert_eq!(
+            parse_error_line("al C:c000 .ok\nbreak c000", SymbolFormat::ViceLabels),
+            2
+        );
+    }
+
+    #[test]
+    fn label_file_accepts_number_formats_and_comments() {
+        let contents = "; generated\nprint_string = $C123 ; entry\nbuffer = 0x0200\nlimit = 512";
+
+        let symbols = parse_symbols(contents, SymbolFormat::LabelFile).unwrap();
+
+        assert_eq!(symbols.address_of("print_string"), Some(0xC123));
+        assert_eq!(symbols.address_of("buffer"), Some(0x0200));
+        assert_eq!(symbols.address_of("limit"), Some(0x0200));
+        assert_eq!(symbols.name_for(0x0200), Some("buffer"));
+    }
+
+    #[test]
+    fn label_file_reports_line_of_invalid_entry() {
+        assert_eq!(
+            parse_error_line("ok = $10\nbad name = $20", SymbolFormat::LabelFile),
+            2
+        );
+        assert_eq!(
+            parse_error_line("\nmissing_value", SymbolFormat::LabelFile),
+            2
+        );
+        assert_eq!(parse_error_line("big = $10000", SymbolFormat::LabelFile), 1);
+    }
+
+    #[test]
+    fn load_symbols_reports_missing_file() {
+        assert!(matches!(
+            load_symbols("/nonexistent/cpu_emu.sym"),
+            Err(SymbolLoadError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::shared::types::Word;
+
+// address <-> name lookup shared by disassembler, debuggers and log context
+#[derive(Debug, Default, Clone)]
+pub struct SymbolTable {
+    // name shown for an address, the first one defined wins
+    by_address: BTreeMap<Word, String>,
+    by_name: HashMap<String, Word>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, address: Word) {
+        let name = name.into();
+        self.by_address
+            .entry(address)
+            .or_insert_with(|| name.clone());
+        self.by_name.insert(name, address);
+    }
+
+    pub fn extend(&mut self, other: &SymbolTable) {
+        for (name, &address) in &other.by_name {
+            self.insert(name.clone(), address);
+        }
+    }
+
+    pub fn name_for(&self, address: Word) -> Option<&str> {
+        self.by_address.get(&address).map(String::as_str)
+    }
+
+    pub fn address_of(&self, name: &str) -> Option<Word> {
+        self.by_name.get(name).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_name.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_name.is_empty()
+    }
+
+    // sorted by address, one entry per name
+    pub fn iter(&self) -> impl Iterator<Item = (Word, &str)> {
+        let mut symbols: Vec<(Word, &str)> = self
+            .by_name
+            .iter()
+            .map(|(name, &address)| (address, name.as_str()))
+            .collect();
+        symbols.sort();
+        symbols.into_iter()
+    }
+
+    // "$C123 <print_string>" or plain "$C123"
+    pub fn describe(&self, address: Word) -> String {
+        match self.name_for(address) {
+            Some(name) => format!("${address:04X} <{name}>"),
+            None => format!("${address:04X}"),
+        }
+    }
+}
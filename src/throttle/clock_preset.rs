@@ -0,0 +1,65 @@
+use std::fmt;
+
+use crate::shared::constants::{
+    C64_PAL_CPU_FREQUENCY_HZ, NTSC_NES_CPU_DEFAULT_FREQUENCY_HZ, ONE_MHZ_FREQUENCY_HZ,
+    PAL_NES_CPU_FREQUENCY_HZ,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ClockPreset {
+    #[default]
+    NtscNes,
+    PalNes,
+    C64,
+    OneMhz,
+    Custom(u32),
+}
+
+impl ClockPreset {
+    pub fn frequency_hz(&self) -> u32 {
+        match self {
+            ClockPreset::NtscNes => NTSC_NES_CPU_DEFAULT_FREQUENCY_HZ,
+            ClockPreset::PalNes => PAL_NES_CPU_FREQUENCY_HZ,
+            ClockPreset::C64 => C64_PAL_CPU_FREQUENCY_HZ,
+            ClockPreset::OneMhz => ONE_MHZ_FREQUENCY_HZ,
+            ClockPreset::Custom(frequency_hz) => *frequency_hz,
+        }
+    }
+
+    // "ntsc", "pal", "c64", "1mhz", plain Hz ("2000000") or MHz ("1.5mhz")
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim().to_ascii_lowercase();
+
+        let preset = match input.as_str() {
+            "ntsc" => ClockPreset::NtscNes,
+            "pal" => ClockPreset::PalNes,
+            "c64" => ClockPreset::C64,
+            "1mhz" => ClockPreset::OneMhz,
+            _ => {
+                let frequency_hz = match input.strip_suffix("mhz") {
+                    Some(megahertz) => (megahertz.parse::<f64>().ok()? * 1_000_000.0).round(),
+                    None => input.parse::<f64>().ok()?,
+                };
+                if !(1.0..=u32::MAX as f64).contains(&frequency_hz) {
+                    return None;
+                }
+                ClockPreset::Custom(frequency_hz as u32)
+            }
+        };
+
+        Some(preset)
+    }
+}
+
+impl fmt::Display for ClockPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let megahertz = f64::from(self.frequency_hz()) / 1_000_000.0;
+
+        match self {
+            ClockPreset::NtscNes => write!(f, "NTSC {megahertz:.6} MHz"),
+            ClockPreset::PalNes => write!(f, "PAL {megahertz:.6} MHz"),
+            ClockPreset::C64 => write!(f, "C64 {megahertz:.6} MHz"),
+            ClockPreset::OneMhz | ClockPreset::Custom(_) => write!(f, "{megahertz:.6} MHz"),
+        }
+    }
+}
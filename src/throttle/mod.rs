@@ -0,0 +1,2 @@
+pub mod clock_preset;
+pub mod throttle;
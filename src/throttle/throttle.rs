@@ -0,0 +1,220 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::cpu::{breakpoints::stop_reason::StopReason, cpu::CPU};
+
+use super::clock_preset::ClockPreset;
+
+// one video frame, short enough for input latency, long enough for OS sleep resolution
+pub const DEFAULT_BATCH_DURATION: Duration = Duration::from_micros(16_667);
+// falling further behind than this (host stall, debugger pause) drops the debt instead of racing
+const MAX_LAG: Duration = Duration::from_millis(250);
+const MEASUREMENT_WINDOW: Duration = Duration::from_secs(1);
+
+// paces emulated cycles to wall clock, sleeping once per batch
+pub struct Throttle {
+    clock: ClockPreset,
+    speed: f64,
+    turbo: bool,
+    batch_duration: Duration,
+
+    // wall clock and cycle counter the pacing is measured from
+    reference_time: Instant,
+    reference_cycles: u64,
+    // counter from the last pace, rate changes measure from it
+    last_cycles: u64,
+
+    window_time: Instant,
+    window_cycles: u64,
+    effective_hz: f64,
+}
+
+impl Throttle {
+    pub fn new(clock: ClockPreset) -> Self {
+        let now = Instant::now();
+
+        Self {
+            clock,
+            speed: 1.0,
+            turbo: false,
+            batch_duration: DEFAULT_BATCH_DURATION,
+            reference_time: now,
+            reference_cycles: 0,
+            last_cycles: 0,
+            window_time: now,
+            window_cycles: 0,
+            effective_hz: 0.0,
+        }
+    }
+
+    pub fn clock(&self) -> ClockPreset {
+        self.clock
+    }
+
+    pub fn set_clock(&mut self, clock: ClockPreset) {
+        self.clock = clock;
+        self.rebase();
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    // 2.0 runs twice as fast as the clock, 0.5 at half speed
+    pub fn set_speed(&mut self, speed: f64) {
+        if speed > 0.0 && speed.is_finite() {
+            self.speed = speed;
+            self.rebase();
+        }
+    }
+
+    pub fn turbo(&self) -> bool {
+        self.turbo
+    }
+
+    // unlimited speed, effective MHz is still measured
+    pub fn set_turbo(&mut self, turbo: bool) {
+        self.turbo = turbo;
+        self.rebase();
+    }
+
+    // cycles run at the old rate must not count against the new one
+    fn rebase(&mut self) {
+        self.reference_time = Instant::now();
+        self.reference_cycles = self.last_cycles;
+    }
+
+    pub fn set_batch_duration(&mut self, batch_duration: Duration) {
+        self.batch_duration = batch_duration.max(Duration::from_millis(1));
+    }
+
+    pub fn target_hz(&self) -> f64 {
+        f64::from(self.clock.frequency_hz()) * self.speed
+    }
+
+    // cycles executed between two sleeps
+    pub fn batch_cycles(&self) -> u64 {
+        ((self.target_hz() * self.batch_duration.as_secs_f64()) as u64).max(1)
+    }
+
+    // starts pacing from cycles, call after the CPU was paused or cycles jumped (restore, rewind)
+    pub fn resync(&mut self, cycles: u64) {
+        let now = Instant::now();
+        self.reference_time = now;
+        self.reference_cycles = cycles;
+        self.last_cycles = cycles;
+        self.window_time = now;
+        self.window_cycles = cycles;
+        self.effective_hz = 0.0;
+    }
+
+    // called after a batch with the CPU cycle counter, sleeps until wall clock catches up
+    pub fn pace(&mut self, cycles: u64) {
+        if cycles < self.reference_cycles {
+            self.resync(cycles);
+            return;
+        }
+        self.last_cycles = cycles;
+
+        if !self.turbo {
+            let emulated =
+                Duration::from_secs_f64((cycles - self.reference_cycles) as f64 / self.target_hz());
+            let elapsed = self.reference_time.elapsed();
+
+            if emulated > elapsed {
+                thread::sleep(emulated - elapsed);
+            } else if elapsed - emulated > MAX_LAG {
+                self.reference_time = Instant::now();
+                self.reference_cycles = cycles;
+            }
+        }
+
+        let window_elapsed = self.window_time.elapsed();
+        if window_elapsed >= MEASUREMENT_WINDOW {
+            self.effective_hz =
+                cycles.saturating_sub(self.window_cycles) as f64 / window_elapsed.as_secs_f64();
+            self.window_time = Instant::now();
+            self.window_cycles = cycles;
+        }
+    }
+
+    // measured over the last full second, or since resync while the first one is running
+    pub fn effective_mhz(&self, cycles: u64) -> f64 {
+        if self.effective_hz > 0.0 {
+            return self.effective_hz / 1_000_000.0;
+        }
+
+        let elapsed = self.window_time.elapsed().as_secs_f64();
+        if elapsed == 0.0 {
+            return 0.0;
+        }
+        cycles.saturating_sub(self.window_cycles) as f64 / elapsed / 1_000_000.0
+    }
+}
+
+// CPU::run in batches with a sleep between them, debug stops end it like run does
+pub fn run_throttled(
+    cpu: &mut CPU,
+    throttle: &mut Throttle,
+    max_cycles: Option<u64>,
+) -> StopReason {
+    let cycle_limit = max_cycles.map(|max_cycles| cpu.cycles().saturating_add(max_cycles));
+    throttle.resync(cpu.cycles());
+
+    loop {
+        let mut batch = throttle.batch_cycles();
+        if let Some(cycle_limit) = cycle_limit {
+            let remaining = cycle_limit.saturating_sub(cpu.cycles());
+            if remaining == 0 {
+                return StopReason::CycleLimit;
+            }
+            batch = batch.min(remaining);
+        }
+
+        let stop_reason = cpu.run(Some(batch));
+        throttle.pace(cpu.cycles());
+
+        if !matches!(stop_reason, StopReason::CycleLimit) {
+            return stop_reason;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_changes_measure_from_the_last_paced_cycles() {
+        let mut throttle = Throttle::new(ClockPreset::OneMhz);
+        throttle.resync(0);
+        throttle.set_turbo(true);
+        throttle.pace(5_000_000);
+
+        throttle.set_turbo(false);
+        let started = Instant::now();
+        throttle.pace(5_000_100);
+
+        assert_eq!(throttle.reference_cycles, 5_000_000);
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        throttle.pace(5_000_200);
+        throttle.set_speed(2.0);
+        throttle.set_clock(ClockPreset::C64);
+        assert_eq!(throttle.reference_cycles, 5_000_200);
+    }
+
+    #[test]
+    fn ignores_speeds_that_are_not_positive() {
+        let mut throttle = Throttle::new(ClockPreset::OneMhz);
+
+        throttle.set_speed(0.0);
+        throttle.set_speed(f64::NAN);
+        assert_eq!(throttle.speed(), 1.0);
+
+        throttle.set_speed(0.5);
+        assert_eq!(throttle.target_hz(), 500_000.0);
+    }
+}